@@ -48,6 +48,130 @@ impl Parse for FieldThenParams {
     }
 }
 
+/// The arguments of a `#[rust_sitter::grammar(...)]` attribute: the name of the grammar,
+/// optionally followed by named parameters such as `extends = "base"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrammarArgs {
+    pub name: LitStr,
+    pub comma: Option<Token![,]>,
+    pub params: Punctuated<NameValueExpr, Token![,]>,
+}
+
+impl Parse for GrammarArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        let comma: Option<Token![,]> = input.parse()?;
+        let params = if comma.is_some() {
+            input.parse_terminated(NameValueExpr::parse)?
+        } else {
+            Punctuated::new()
+        };
+
+        Ok(GrammarArgs {
+            name,
+            comma,
+            params,
+        })
+    }
+}
+
+impl GrammarArgs {
+    /// Looks up the value of a named parameter, such as `extends`.
+    pub fn param(&self, name: &str) -> Option<&Expr> {
+        self.params
+            .iter()
+            .find(|param| param.path == name)
+            .map(|param| &param.expr)
+    }
+
+    /// The names of the wrapper types registered with `wrappers = [...]`, in addition to
+    /// the [`BUILTIN_WRAPPERS`].
+    pub fn wrappers(&self) -> Vec<String> {
+        match self.param("wrappers") {
+            Some(Expr::Array(array)) => array
+                .elems
+                .iter()
+                .map(|e| {
+                    if let Expr::Path(p) = e {
+                        p.path.segments.last().unwrap().ident.to_string()
+                    } else {
+                        panic!("Expected a type name in `wrappers`");
+                    }
+                })
+                .collect(),
+            Some(_) => panic!("Expected an array of type names for `wrappers`"),
+            None => vec![],
+        }
+    }
+
+    /// Whether a boolean parameter such as `unparse = true` is enabled. Missing parameters are
+    /// disabled.
+    pub fn flag(&self, name: &str) -> bool {
+        self.param(name).is_some_and(|e| {
+            if let Expr::Lit(ExprLit {
+                lit: Lit::Bool(b), ..
+            }) = e
+            {
+                b.value
+            } else {
+                panic!("Expected boolean literal for `{name}`");
+            }
+        })
+    }
+
+    /// The name of the grammar this one extends, if any.
+    pub fn extends(&self) -> Option<String> {
+        self.param("extends").map(|e| {
+            if let Expr::Lit(ExprLit {
+                lit: Lit::Str(s), ..
+            }) = e
+            {
+                s.value()
+            } else {
+                panic!("Expected string literal for `extends`");
+            }
+        })
+    }
+}
+
+/// Types that wrap a single grammar value without affecting the grammar. Other wrappers can
+/// be registered with the `wrappers` parameter of the grammar attribute.
+pub const BUILTIN_WRAPPERS: [&str; 4] = ["Spanned", "Box", "Rc", "Arc"];
+
+/// Built-in wrappers that are extracted through their own `Extract` implementations rather
+/// than through the `rust_sitter::Wrapper` trait.
+const DIRECT_WRAPPERS: [&str; 2] = ["Spanned", "Box"];
+
+/// Finds the `#[rust_sitter::grammar(...)]` attribute among the given attributes and parses
+/// its arguments.
+pub fn grammar_args(attrs: &[Attribute]) -> Option<GrammarArgs> {
+    attrs.iter().find_map(|a| {
+        if a.path == syn::parse_quote!(rust_sitter::grammar) {
+            Some(
+                a.parse_args_with(GrammarArgs::parse)
+                    .unwrap_or_else(|_| panic!("Expected string literal for grammar name")),
+            )
+        } else {
+            None
+        }
+    })
+}
+
+/// The first type argument of a generic type, skipping over lifetimes such as in `Cow<'a, T>`.
+fn first_type_arg(args: &PathArguments) -> Type {
+    if let PathArguments::AngleBracketed(p) = args {
+        p.args
+            .iter()
+            .find_map(|a| match a {
+                GenericArgument::Type(t) => Some(t.clone()),
+                _ => None,
+            })
+            .unwrap_or_else(|| panic!("Argument in angle brackets must be a type"))
+    } else {
+        panic!("Expected angle bracketed path");
+    }
+}
+
 pub fn try_extract_inner_type(
     ty: &Type,
     inner_of: &str,
@@ -56,27 +180,13 @@ pub fn try_extract_inner_type(
     if let Type::Path(p) = &ty {
         let type_segment = p.path.segments.last().unwrap();
         if type_segment.ident == inner_of {
-            let leaf_type = if let PathArguments::AngleBracketed(p) = &type_segment.arguments {
-                if let GenericArgument::Type(t) = p.args.first().unwrap().clone() {
-                    t
-                } else {
-                    panic!("Argument in angle brackets must be a type")
-                }
-            } else {
-                panic!("Expected angle bracketed path");
-            };
-
-            (leaf_type, true)
+            (first_type_arg(&type_segment.arguments), true)
         } else if skip_over.contains(type_segment.ident.to_string().as_str()) {
-            if let PathArguments::AngleBracketed(p) = &type_segment.arguments {
-                if let GenericArgument::Type(t) = p.args.first().unwrap().clone() {
-                    try_extract_inner_type(&t, inner_of, skip_over)
-                } else {
-                    panic!("Argument in angle brackets must be a type")
-                }
-            } else {
-                panic!("Expected angle bracketed path");
-            }
+            try_extract_inner_type(
+                &first_type_arg(&type_segment.arguments),
+                inner_of,
+                skip_over,
+            )
         } else {
             (ty.clone(), false)
         }
@@ -89,15 +199,7 @@ pub fn filter_inner_type(ty: &Type, skip_over: &HashSet<&str>) -> Type {
     if let Type::Path(p) = &ty {
         let type_segment = p.path.segments.last().unwrap();
         if skip_over.contains(type_segment.ident.to_string().as_str()) {
-            if let PathArguments::AngleBracketed(p) = &type_segment.arguments {
-                if let GenericArgument::Type(t) = p.args.first().unwrap().clone() {
-                    filter_inner_type(&t, skip_over)
-                } else {
-                    panic!("Argument in angle brackets must be a type")
-                }
-            } else {
-                panic!("Expected angle bracketed path");
-            }
+            filter_inner_type(&first_type_arg(&type_segment.arguments), skip_over)
         } else {
             ty.clone()
         }
@@ -106,7 +208,9 @@ pub fn filter_inner_type(ty: &Type, skip_over: &HashSet<&str>) -> Type {
     }
 }
 
-pub fn wrap_leaf_type(ty: &Type, skip_over: &HashSet<&str>) -> Type {
+/// Wraps the leaf type inside `ty` (skipping over the containers in `skip_over`) in the
+/// extractor `leaf_extractor`, such as `rust_sitter::WithLeaf`.
+pub fn wrap_leaf_type(ty: &Type, skip_over: &HashSet<&str>, leaf_extractor: &syn::Path) -> Type {
     let mut ty = ty.clone();
     if let Type::Path(p) = &mut ty {
         let type_segment = p.path.segments.last_mut().unwrap();
@@ -114,7 +218,7 @@ pub fn wrap_leaf_type(ty: &Type, skip_over: &HashSet<&str>) -> Type {
             if let PathArguments::AngleBracketed(args) = &mut type_segment.arguments {
                 for a in args.args.iter_mut() {
                     if let syn::GenericArgument::Type(t) = a {
-                        *t = wrap_leaf_type(t, skip_over);
+                        *t = wrap_leaf_type(t, skip_over, leaf_extractor);
                     }
                 }
 
@@ -123,9 +227,273 @@ pub fn wrap_leaf_type(ty: &Type, skip_over: &HashSet<&str>) -> Type {
                 panic!("Expected angle bracketed path");
             }
         } else {
-            parse_quote!(rust_sitter::WithLeaf<#ty>)
+            parse_quote!(#leaf_extractor<#ty>)
         }
     } else {
-        parse_quote!(rust_sitter::WithLeaf<#ty>)
+        parse_quote!(#leaf_extractor<#ty>)
+    }
+}
+
+/// Rewrites every wrapper type in `ty` that is extracted through the `rust_sitter::Wrapper`
+/// trait, such as `Rc<T>`, into `rust_sitter::Wrapped<Rc<_>, T>`.
+pub fn wrap_wrapper_types(ty: &Type, wrappers: &HashSet<&str>) -> Type {
+    let mut ty = ty.clone();
+    if let Type::Path(p) = &mut ty {
+        let type_segment = p.path.segments.last_mut().unwrap();
+        if let PathArguments::AngleBracketed(args) = &mut type_segment.arguments {
+            for a in args.args.iter_mut() {
+                if let GenericArgument::Type(t) = a {
+                    *t = wrap_wrapper_types(t, wrappers);
+                }
+            }
+        }
+
+        let ident = type_segment.ident.to_string();
+        if wrappers.contains(ident.as_str()) && !DIRECT_WRAPPERS.contains(&ident.as_str()) {
+            let inner = first_type_arg(&type_segment.arguments);
+            if let PathArguments::AngleBracketed(args) = &mut type_segment.arguments {
+                if let Some(GenericArgument::Type(t)) = args
+                    .args
+                    .iter_mut()
+                    .find(|a| matches!(a, GenericArgument::Type(_)))
+                {
+                    *t = parse_quote!(_);
+                }
+            }
+
+            return parse_quote!(rust_sitter::Wrapped<#ty, #inner>);
+        }
+    }
+
+    ty
+}
+
+fn mentions_type(ty: &Type, name: &str) -> bool {
+    match ty {
+        Type::Path(p) => p.path.segments.iter().any(|segment| {
+            segment.ident == name
+                || matches!(&segment.arguments, PathArguments::AngleBracketed(args)
+                    if args.args.iter().any(|a| matches!(a, GenericArgument::Type(t) if mentions_type(t, name))))
+        }),
+        Type::Reference(r) => mentions_type(&r.elem, name),
+        Type::Tuple(t) => t.elems.iter().any(|t| mentions_type(t, name)),
+        _ => false,
+    }
+}
+
+/// Whether the rule of the root type is kept in the Tree Sitter grammar. Only the copy of it
+/// named `source_file` is reachable, unless another type in the grammar contains the root type.
+pub fn root_rule_is_reachable(items: &[Item], root_type: &str) -> bool {
+    items.iter().any(|item| match item {
+        Item::Struct(s) => s.fields.iter().any(|f| mentions_type(&f.ty, root_type)),
+        Item::Enum(e) => e
+            .variants
+            .iter()
+            .flat_map(|v| v.fields.iter())
+            .any(|f| mentions_type(&f.ty, root_type)),
+        _ => false,
+    })
+}
+
+/// The highlight name given by a `#[rust_sitter::highlight("...")]` annotation, such as
+/// `keyword` or `punctuation.delimiter`.
+fn highlight_name(attrs: &[Attribute]) -> Option<String> {
+    let attr = attrs
+        .iter()
+        .find(|attr| attr.path == syn::parse_quote!(rust_sitter::highlight))?;
+    let name = attr
+        .parse_args::<LitStr>()
+        .expect("Expected a string literal for `highlight`")
+        .value();
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c))
+    {
+        panic!("Invalid highlight name `{name}`");
     }
+
+    Some(name)
+}
+
+/// Collects the highlight patterns for the fields of a struct or variant whose rules are named
+/// after `path`. Leaves are highlighted through their own node kind, and other fields through the
+/// field of the containing nodes, whose kinds are `nodes`.
+fn field_highlights(path: &str, nodes: &[&str], fields: &Fields, out: &mut Vec<(String, String)>) {
+    fields.iter().enumerate().for_each(|(i, field)| {
+        if field.attrs.iter().any(|attr| {
+            attr.path == syn::parse_quote!(rust_sitter::skip)
+                || attr.path == syn::parse_quote!(rust_sitter::trivia)
+        }) {
+            return;
+        }
+
+        let ident_str = field
+            .ident
+            .as_ref()
+            .map(|v| v.to_string())
+            .unwrap_or(format!("{i}"));
+        let field_path = format!("{path}_{ident_str}");
+
+        if let Some(name) = highlight_name(&field.attrs) {
+            if field
+                .attrs
+                .iter()
+                .any(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf))
+            {
+                out.push((format!("({field_path}) @{name}"), name));
+            } else {
+                out.extend(
+                    nodes
+                        .iter()
+                        .map(|node| (format!("({node} {ident_str}: (_) @{name})"), name.clone())),
+                );
+            }
+        }
+
+        if let Some(name) = field
+            .attrs
+            .iter()
+            .find(|attr| attr.path == syn::parse_quote!(rust_sitter::delimited))
+            .and_then(|a| a.parse_args_with(FieldThenParams::parse).ok())
+            .and_then(|p| highlight_name(&p.field.attrs))
+        {
+            out.push((format!("({field_path}_vec_delimiter) @{name}"), name));
+        }
+    });
+}
+
+/// Collects the Tree Sitter query patterns, which capture the highlighted node, along with their
+/// highlight names for the `#[rust_sitter::highlight(...)]` annotations on the types, variants
+/// and fields of a grammar.
+pub fn highlight_patterns(items: &[Item]) -> Vec<(String, String)> {
+    let mut out = vec![];
+    items.iter().for_each(|item| {
+        let (ident, attrs) = match item {
+            Item::Struct(s) => (&s.ident, &s.attrs),
+            Item::Enum(e) => (&e.ident, &e.attrs),
+            _ => return,
+        };
+
+        let is_root = attrs
+            .iter()
+            .any(|attr| attr.path == syn::parse_quote!(rust_sitter::language));
+        let ident_str = ident.to_string();
+        // The root rule is copied to `source_file`, and Tree Sitter only keeps the original if
+        // another type refers to it.
+        let nodes: Vec<&str> = if !is_root {
+            vec![&ident_str]
+        } else if root_rule_is_reachable(items, &ident_str) {
+            vec!["source_file", &ident_str]
+        } else {
+            vec!["source_file"]
+        };
+
+        if let Some(name) = highlight_name(attrs) {
+            out.extend(
+                nodes
+                    .iter()
+                    .map(|node| (format!("({node}) @{name}"), name.clone())),
+            );
+        }
+
+        match item {
+            Item::Struct(s) => field_highlights(&ident_str, &nodes, &s.fields, &mut out),
+            Item::Enum(e) => e.variants.iter().for_each(|v| {
+                let path = format!("{ident}_{}", v.ident);
+                if let Some(name) = highlight_name(&v.attrs) {
+                    out.push((format!("({path}) @{name}"), name));
+                }
+                field_highlights(&path, &[&path], &v.fields, &mut out);
+            }),
+            _ => unreachable!(),
+        }
+    });
+
+    out
+}
+
+/// Generates the highlights query (the contents of `queries/highlights.scm`) for a grammar.
+pub fn highlights_query(items: &[Item]) -> String {
+    highlight_patterns(items)
+        .iter()
+        .map(|(pattern, _)| format!("{pattern}\n"))
+        .collect()
+}
+
+/// Maps the node kinds of a grammar to the Rust types, variants and fields they correspond to,
+/// such as `Expression_Sub` to `Expression::Sub` and `Expression_Sub_0` to `Expression::Sub.0`.
+/// The elements of a `Vec` field are named `Type.field[]` and its delimiter
+/// `Type.field.delimiter`. A kind may appear more than once, in which case the first name
+/// should be used.
+pub fn rule_names(
+    items: &[Item],
+    root_type: &str,
+    wrappers: &HashSet<&str>,
+) -> Vec<(String, String)> {
+    let mut out = vec![("source_file".to_string(), root_type.to_string())];
+    items.iter().for_each(|item| match item {
+        Item::Enum(e) => {
+            out.push((e.ident.to_string(), e.ident.to_string()));
+            e.variants.iter().for_each(|v| {
+                field_rule_names(
+                    format!("{}_{}", e.ident, v.ident),
+                    format!("{}::{}", e.ident, v.ident),
+                    &v.fields,
+                    wrappers,
+                    &mut out,
+                )
+            });
+        }
+        Item::Struct(s) => field_rule_names(
+            s.ident.to_string(),
+            s.ident.to_string(),
+            &s.fields,
+            wrappers,
+            &mut out,
+        ),
+        _ => {}
+    });
+
+    out
+}
+
+fn field_rule_names(
+    path: String,
+    rust_path: String,
+    fields: &Fields,
+    wrappers: &HashSet<&str>,
+    out: &mut Vec<(String, String)>,
+) {
+    out.push((path.clone(), rust_path.clone()));
+
+    fields.iter().enumerate().for_each(|(i, field)| {
+        if field.attrs.iter().any(|attr| {
+            attr.path == syn::parse_quote!(rust_sitter::skip)
+                || attr.path == syn::parse_quote!(rust_sitter::trivia)
+        }) {
+            return;
+        }
+
+        let ident_str = field
+            .ident
+            .as_ref()
+            .map(|v| v.to_string())
+            .unwrap_or(format!("{i}"));
+        let field_path = format!("{path}_{ident_str}");
+        let rust_field_path = format!("{rust_path}.{ident_str}");
+        if try_extract_inner_type(&field.ty, "Vec", wrappers).1 {
+            out.push((
+                format!("{field_path}_vec_contents"),
+                rust_field_path.clone(),
+            ));
+            out.push((
+                format!("{field_path}_vec_delimiter"),
+                format!("{rust_field_path}.delimiter"),
+            ));
+            out.push((field_path, format!("{rust_field_path}[]")));
+        } else {
+            out.push((field_path, rust_field_path));
+        }
+    });
 }
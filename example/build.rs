@@ -1,6 +1,25 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 fn main() {
-    println!("cargo:rerun-if-changed=src");
     rust_sitter_tool::build_parsers(&PathBuf::from("src/main.rs"));
+
+    // Random programs for the arithmetic grammar, which its tests check are all accepted.
+    let arithmetic = rust_sitter_tool::generate_grammars(Path::new("src/arithmetic.rs"))
+        .into_iter()
+        .find(|g| serde_json::from_str::<serde_json::Value>(g).unwrap()["name"] == "arithmetic")
+        .unwrap();
+    let samples = rust_sitter_tool::generate_samples(&arithmetic, 100, 6, 0);
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    std::fs::write(out_dir.join("arithmetic_samples.txt"), samples.join("\n")).unwrap();
+
+    // Typed bindings generated from the node types of the arithmetic grammar, which its tests
+    // use as if it were a language defined outside of Rust Sitter.
+    let node_types_path = Path::new("fixtures/arithmetic-node-types.json");
+    println!("cargo:rerun-if-changed={}", node_types_path.display());
+    let node_types = std::fs::read_to_string(node_types_path).unwrap();
+    std::fs::write(
+        out_dir.join("arithmetic_bindings.rs"),
+        rust_sitter_tool::generate_node_types_bindings(&node_types),
+    )
+    .unwrap();
 }
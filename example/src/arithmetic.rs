@@ -1,4 +1,4 @@
-#[rust_sitter::grammar("arithmetic")]
+#[rust_sitter::grammar("arithmetic", queries = true, visit = true)]
 pub mod grammar {
     #[rust_sitter::language]
     #[derive(PartialEq, Eq, Debug)]
@@ -19,6 +19,7 @@ pub mod grammar {
     }
 
     #[rust_sitter::extra]
+    #[allow(dead_code)]
     struct Whitespace {
         #[rust_sitter::leaf(pattern = r"\s")]
         _whitespace: (),
@@ -99,6 +100,130 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lazy_views() {
+        use grammar::views::ExpressionRef;
+
+        let input = "1 - 2 * 3";
+        let tree = grammar::parse_tree(input).unwrap();
+        match grammar::view(&tree, input) {
+            ExpressionRef::Sub(sub) => {
+                assert_eq!(sub._0().extract().unwrap(), Expression::Number(1));
+                match sub._2() {
+                    ExpressionRef::Mul(mul) => match mul._2() {
+                        ExpressionRef::Number(n) => assert_eq!(n._0(), "3"),
+                        _ => panic!("expected a number"),
+                    },
+                    _ => panic!("expected a multiplication"),
+                }
+            }
+            _ => panic!("expected a subtraction"),
+        }
+    }
+
+    #[test]
+    fn typed_queries() {
+        let query = rust_sitter::query!(
+            grammar,
+            "(Expression::Sub 0: (Expression (Expression::Number) @left) 2: (_) @right)"
+        )
+        .unwrap();
+
+        let input = "1 - 2 * 3 - 4";
+        let tree = grammar::parse_tree(input).unwrap();
+        let matches = query.matches(tree.root_node(), input);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].left.extract().unwrap(), Expression::Number(1));
+        assert_eq!(matches[0].right.kind(), "Expression");
+        assert_eq!(
+            matches[0].right.utf8_text(input.as_bytes()).unwrap(),
+            "2 * 3"
+        );
+
+        let query = rust_sitter::query!(
+            grammar,
+            "(Expression::Number) @number (Expression::Mul) @mul"
+        )
+        .unwrap();
+        let matches = query.matches(tree.root_node(), input);
+        let numbers: Vec<Expression> = matches
+            .iter()
+            .filter_map(|m| m.number.map(|n| n.extract().unwrap()))
+            .collect();
+        assert_eq!(
+            numbers,
+            vec![
+                Expression::Number(1),
+                Expression::Number(2),
+                Expression::Number(3),
+                Expression::Number(4)
+            ]
+        );
+        assert_eq!(matches.iter().filter(|m| m.mul.is_some()).count(), 1);
+    }
+
+    #[test]
+    fn visitors() {
+        use grammar::fold::{self, Fold};
+        use grammar::visit::{self, Visit};
+        use grammar::visit_mut::{self, VisitMut};
+
+        struct SumNumbers(i32);
+        impl Visit for SumNumbers {
+            fn visit_expression(&mut self, node: &Expression) {
+                if let Expression::Number(n) = node {
+                    self.0 += n;
+                }
+                visit::visit_expression(self, node);
+            }
+        }
+
+        struct Negate;
+        impl VisitMut for Negate {
+            fn visit_mut_expression(&mut self, node: &mut Expression) {
+                if let Expression::Number(n) = node {
+                    *n = -*n;
+                }
+                visit_mut::visit_mut_expression(self, node);
+            }
+        }
+
+        struct SubToMul;
+        impl Fold for SubToMul {
+            fn fold_expression(&mut self, node: Expression) -> Expression {
+                match fold::fold_expression(self, node) {
+                    Expression::Sub(l, (), r) => Expression::Mul(l, (), r),
+                    e => e,
+                }
+            }
+        }
+
+        let mut expr = grammar::parse("1 - 2 * 3").unwrap();
+
+        let mut sum = SumNumbers(0);
+        sum.visit_expression(&expr);
+        assert_eq!(sum.0, 6);
+
+        Negate.visit_mut_expression(&mut expr);
+        sum = SumNumbers(0);
+        sum.visit_expression(&expr);
+        assert_eq!(sum.0, -6);
+
+        assert_eq!(
+            SubToMul.fold_expression(grammar::parse("1 - 2 - 3").unwrap()),
+            grammar::parse("1 * 2 * 3").unwrap()
+        );
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn generated_samples() {
+        let samples = include_str!(concat!(env!("OUT_DIR"), "/arithmetic_samples.txt"));
+        for sample in samples.lines() {
+            assert!(grammar::parse(sample).is_ok(), "failed to parse {sample:?}");
+        }
+    }
+
     #[test]
     fn failed_parses() {
         insta::assert_debug_snapshot!(grammar::parse("1 + 2"));
@@ -106,4 +231,12 @@ mod tests {
         insta::assert_debug_snapshot!(grammar::parse("a1"));
         insta::assert_debug_snapshot!(grammar::parse("1a"));
     }
+
+    #[test]
+    fn corpus() {
+        rust_sitter::testing::run_corpus(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/corpus/arithmetic"),
+            grammar::parse,
+        );
+    }
 }
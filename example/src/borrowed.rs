@@ -0,0 +1,86 @@
+#[rust_sitter::grammar("borrowed", queries = true)]
+#[allow(dead_code)]
+pub mod grammar {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct Call<'a> {
+        #[rust_sitter::leaf(pattern = r"[a-z_]+")]
+        pub name: &'a str,
+        #[rust_sitter::leaf(text = "(")]
+        _open: (),
+        #[rust_sitter::delimited(
+            #[rust_sitter::leaf(text = ",")]
+            ()
+        )]
+        pub args: Vec<Argument<'a>>,
+        #[rust_sitter::leaf(text = ")")]
+        _close: (),
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    pub enum Argument<'a> {
+        Name(#[rust_sitter::leaf(pattern = r"[a-z_]+")] &'a str),
+        Number(#[rust_sitter::leaf(pattern = r"\d+")] u32),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::grammar::*;
+
+    #[test]
+    fn borrows_from_input() {
+        let input = String::from("print(x, 42, total)");
+        let call = parse(&input).unwrap();
+        assert_eq!(call.name, "print");
+        assert_eq!(
+            call.args,
+            vec![
+                Argument::Name("x"),
+                Argument::Number(42),
+                Argument::Name("total")
+            ]
+        );
+
+        // leaves point into the original input rather than copies of it
+        let offset = call.name.as_ptr() as usize - input.as_ptr() as usize;
+        assert_eq!(offset, 0);
+    }
+
+    #[test]
+    fn views_borrow_from_input() {
+        use super::grammar::views::ArgumentRef;
+
+        let input = "print(x, 42)";
+        let tree = parse_tree(input).unwrap();
+        let call = view(&tree, input);
+        assert_eq!(call.name(), "print");
+
+        let args = call.args().collect::<Vec<_>>();
+        assert_eq!(args.len(), 2);
+        assert!(matches!(args[0], ArgumentRef::Name(n) if n._0() == "x"));
+        assert_eq!(args[1].extract().unwrap(), Argument::Number(42));
+    }
+
+    #[test]
+    fn query_root_type() {
+        // `Call` is only kept in the parser as `source_file`, which its name refers to.
+        let query = rust_sitter::query!(super::grammar, "(Call name: (_) @name) @call").unwrap();
+
+        let input = "print(x, 42)";
+        let tree = parse_tree(input).unwrap();
+        let matches = query.matches(tree.root_node(), input);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].call.name(), "print");
+        assert_eq!(
+            matches[0].name.utf8_text(input.as_bytes()).unwrap(),
+            "print"
+        );
+    }
+}
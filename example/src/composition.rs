@@ -0,0 +1,65 @@
+#[rust_sitter::grammar("assignment", extends = "arithmetic")]
+#[allow(dead_code)]
+pub mod grammar {
+    use crate::arithmetic::grammar::Expression;
+
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct Assignment {
+        #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_string())]
+        pub name: String,
+        #[rust_sitter::leaf(text = "=")]
+        _eq: (),
+        pub value: Expression,
+    }
+}
+
+#[rust_sitter::grammar("pairs", extends = "leaves")]
+#[allow(dead_code)]
+pub mod pairs {
+    use crate::leaves::grammar::Pair;
+
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct Pairs {
+        pub pairs: Vec<Pair>,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arithmetic::grammar::Expression;
+
+    #[test]
+    fn extended_grammar() {
+        let assignment = grammar::parse("x = 1 - 2").unwrap();
+        assert_eq!(assignment.name, "x");
+        assert_eq!(
+            assignment.value,
+            Expression::Sub(
+                Box::new(Expression::Number(1)),
+                (),
+                Box::new(Expression::Number(2))
+            )
+        );
+
+        assert!(grammar::parse("x = ").is_err());
+    }
+
+    #[test]
+    fn extended_grammar_invalid_leaf() {
+        let pairs = pairs::parse("a: 1 b: 2").unwrap();
+        assert_eq!(pairs.pairs.len(), 2);
+        assert_eq!(pairs.pairs[1].value, 2);
+
+        // The `u8` leaf belongs to the base grammar, but is still reported as an error.
+        let errors = pairs::parse("a: 1 b: 300").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0].reason,
+            rust_sitter::errors::ParseErrorReason::InvalidLeaf(_)
+        ));
+        assert_eq!((errors[0].start, errors[0].end), (8, 11));
+    }
+}
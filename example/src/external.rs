@@ -0,0 +1,39 @@
+//! The arithmetic grammar used through bindings generated from its `node-types.json`, as
+//! would be done for a language whose grammar is not written with Rust Sitter. The node types
+//! are kept in `fixtures/arithmetic-node-types.json`, like those of a third-party language.
+
+#[allow(dead_code)]
+mod bindings {
+    include!(concat!(env!("OUT_DIR"), "/arithmetic_bindings.rs"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::bindings::{ExpressionChildren, SourceFile, SourceFileChildren};
+    use crate::arithmetic::grammar;
+
+    #[test]
+    fn typed_parse() {
+        let parsed: SourceFile =
+            rust_sitter::external::parse(grammar::language(), "1 - 2 * 3").unwrap();
+        let SourceFileChildren::ExpressionSub(sub) = parsed.children else {
+            panic!("Expected a subtraction, got {parsed:?}");
+        };
+        assert_eq!(sub._1.text, "-");
+
+        let ExpressionChildren::ExpressionNumber(left) = *sub._0.children else {
+            panic!("Expected a number");
+        };
+        assert_eq!(left._0.text, "1");
+
+        let ExpressionChildren::ExpressionMul(mul) = *sub._2.children else {
+            panic!("Expected a multiplication");
+        };
+        assert_eq!(mul._1.text, "*");
+    }
+
+    #[test]
+    fn typed_parse_errors() {
+        assert!(rust_sitter::external::parse::<SourceFile>(grammar::language(), "1 -").is_err());
+    }
+}
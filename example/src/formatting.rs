@@ -0,0 +1,121 @@
+#[rust_sitter::grammar("formatting", unparse = true)]
+pub mod grammar {
+    #[rust_sitter::language]
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    pub struct Program {
+        functions: Vec<Function>,
+    }
+
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    pub struct Function {
+        #[rust_sitter::trivia]
+        comments: Vec<Comment>,
+        #[rust_sitter::leaf(text = "fn")]
+        _fn: (),
+        #[rust_sitter::leaf(pattern = r"[a-z_]+")]
+        name: String,
+        #[rust_sitter::format(no_space)]
+        #[rust_sitter::leaf(text = "(")]
+        _open: (),
+        #[rust_sitter::format(no_space)]
+        #[rust_sitter::leaf(text = ")")]
+        _close: (),
+        #[rust_sitter::format(line_break_after)]
+        body: Block,
+    }
+
+    #[rust_sitter::format(group)]
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    pub struct Block {
+        #[rust_sitter::leaf(text = "{")]
+        _open: (),
+        #[rust_sitter::format(indent)]
+        statements: Vec<Statement>,
+        #[rust_sitter::leaf(text = "}")]
+        _close: (),
+    }
+
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    pub struct Statement {
+        #[rust_sitter::trivia]
+        comments: Vec<Comment>,
+        expression: Expression,
+        #[rust_sitter::format(no_space, line_break_after)]
+        #[rust_sitter::leaf(text = ";")]
+        _semicolon: (),
+    }
+
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    pub enum Expression {
+        Number(#[rust_sitter::leaf(pattern = r"\d+")] u32),
+        Call(
+            #[rust_sitter::leaf(pattern = r"[a-z_]+")] String,
+            #[rust_sitter::format(no_space)]
+            #[rust_sitter::leaf(text = "(")]
+            (),
+            #[rust_sitter::format(no_space)]
+            #[rust_sitter::delimited(
+                #[rust_sitter::format(no_space)]
+                #[rust_sitter::leaf(text = ",")]
+                ()
+            )]
+            Vec<Expression>,
+            #[rust_sitter::format(no_space)]
+            #[rust_sitter::leaf(text = ")")]
+            (),
+        ),
+    }
+
+    #[rust_sitter::extra]
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    pub struct Comment {
+        #[rust_sitter::leaf(pattern = r"//[^\n]*")]
+        text: String,
+    }
+
+    #[rust_sitter::extra]
+    #[allow(dead_code)]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn format_program() {
+        let ast = grammar::parse(
+            "// entry point\nfn main(){ // greet\n print(add(1,2)); exit( 0 );} fn empty ( ) {}",
+        )
+        .unwrap();
+
+        assert_eq!(
+            grammar::format(&ast, 80),
+            "// entry point\nfn main() {\n    // greet\n    print(add(1, 2));\n    exit(0);\n}\nfn empty() { }"
+        );
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn format_width() {
+        let ast = grammar::parse("fn main() { print(1); exit(0); }").unwrap();
+        assert_eq!(
+            grammar::format(&ast, 40),
+            "fn main() { print(1); exit(0); }"
+        );
+        assert_eq!(
+            grammar::format(&ast, 20),
+            "fn main() {\n    print(1);\n    exit(0);\n}"
+        );
+    }
+}
@@ -0,0 +1,79 @@
+#[rust_sitter::grammar("fuzzing", unparse = true, arbitrary = true)]
+pub mod grammar {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Program {
+        statements: Vec<Statement>,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Statement {
+        expression: Expression,
+        #[rust_sitter::leaf(text = ";")]
+        _semicolon: (),
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Expression {
+        Number(#[rust_sitter::leaf(pattern = r"\d+")] u8),
+        Call(
+            #[rust_sitter::leaf(pattern = r"[a-z]+")] String,
+            #[rust_sitter::leaf(text = "(")] (),
+            #[rust_sitter::delimited(
+                #[rust_sitter::leaf(text = ",")]
+                ()
+            )]
+            Vec<Expression>,
+            #[rust_sitter::leaf(text = ")")] (),
+        ),
+        Negate(#[rust_sitter::leaf(text = "-")] (), Option<Box<Expression>>),
+    }
+
+    #[rust_sitter::extra]
+    #[allow(dead_code)]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_sitter::arbitrary::{Arbitrary, Unstructured};
+
+    fn fuzzer_input() -> Vec<u8> {
+        (0..4096u32).map(|i| (i * 7919 % 251) as u8).collect()
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn arbitrary_round_trip() {
+        let data = fuzzer_input();
+        let mut u = Unstructured::new(&data);
+        while !u.is_empty() {
+            let ast = grammar::Program::arbitrary(&mut u).unwrap();
+            let printed = rust_sitter::unparse::to_string(&ast, " ");
+            assert_eq!(grammar::parse(&printed).unwrap(), ast, "{printed}");
+        }
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn arbitrary_narrow_integer_leaf() {
+        // Most matches of `\d+` overflow a `u8`, so they must be resampled.
+        let data = fuzzer_input();
+        let mut u = Unstructured::new(&data);
+        let mut numbers = 0;
+        while !u.is_empty() {
+            if let grammar::Expression::Number(_) = grammar::Expression::arbitrary(&mut u).unwrap()
+            {
+                numbers += 1;
+            }
+        }
+        assert!(numbers > 0);
+    }
+}
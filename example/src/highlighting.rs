@@ -0,0 +1,131 @@
+#[rust_sitter::grammar("highlighting")]
+#[allow(dead_code)]
+pub mod grammar {
+    #[rust_sitter::language]
+    #[derive(Debug)]
+    pub struct Program {
+        #[rust_sitter::repeat]
+        pub statements: Vec<Statement>,
+    }
+
+    #[derive(Debug)]
+    pub struct Statement {
+        #[rust_sitter::highlight("keyword")]
+        #[rust_sitter::leaf(text = "let")]
+        _let: (),
+        #[rust_sitter::highlight("variable")]
+        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+        pub name: String,
+        #[rust_sitter::highlight("operator")]
+        #[rust_sitter::leaf(text = "=")]
+        _eq: (),
+        #[rust_sitter::delimited(
+            #[rust_sitter::highlight("punctuation.delimiter")]
+            #[rust_sitter::leaf(text = ",")]
+            ()
+        )]
+        pub values: Vec<Value>,
+        #[rust_sitter::leaf(text = ";")]
+        _semicolon: (),
+    }
+
+    #[derive(Debug)]
+    pub enum Value {
+        #[rust_sitter::highlight("number")]
+        Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+        #[rust_sitter::highlight("string")]
+        Text {
+            #[rust_sitter::leaf(text = "\"")]
+            _open: (),
+            #[rust_sitter::highlight("string.special")]
+            #[rust_sitter::leaf(pattern = r"[a-z]*")]
+            text: String,
+            #[rust_sitter::leaf(text = "\"")]
+            _close: (),
+        },
+    }
+
+    #[rust_sitter::extra]
+    #[rust_sitter::highlight("comment")]
+    struct Comment {
+        #[rust_sitter::leaf(pattern = r"//[^\n]*")]
+        _text: (),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[rust_sitter::grammar("highlighting_root")]
+#[allow(dead_code)]
+pub mod root_grammar {
+    #[rust_sitter::language]
+    #[rust_sitter::highlight("module")]
+    #[derive(Debug)]
+    pub struct Module {
+        #[rust_sitter::highlight("keyword")]
+        #[rust_sitter::leaf(text = "module")]
+        _module: (),
+        #[rust_sitter::highlight("namespace")]
+        pub name: Name,
+    }
+
+    #[derive(Debug)]
+    pub struct Name {
+        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+        pub text: String,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{grammar, root_grammar};
+
+    #[test]
+    fn highlights() {
+        let source = "let a = 1, \"hi\"; // done";
+        let highlights: Vec<(&str, &str)> = grammar::highlight(source)
+            .into_iter()
+            .map(|((start, end), name)| (&source[start..end], name))
+            .collect();
+
+        assert_eq!(
+            highlights,
+            vec![
+                ("let", "keyword"),
+                ("a", "variable"),
+                ("=", "operator"),
+                ("1", "number"),
+                (",", "punctuation.delimiter"),
+                ("\"", "string"),
+                ("hi", "string.special"),
+                ("\"", "string"),
+                ("// done", "comment"),
+            ]
+        );
+    }
+
+    #[test]
+    fn root_highlights() {
+        // `Module` is only kept in the parser as `source_file`, which its highlights refer to.
+        let source = "module foo";
+        let highlights: Vec<(&str, &str)> = root_grammar::highlight(source)
+            .into_iter()
+            .map(|((start, end), name)| (&source[start..end], name))
+            .collect();
+
+        assert_eq!(
+            highlights,
+            vec![("module", "keyword"), (" ", "module"), ("foo", "namespace")]
+        );
+    }
+}
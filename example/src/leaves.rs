@@ -0,0 +1,39 @@
+#[rust_sitter::grammar("leaves")]
+#[allow(dead_code)]
+pub mod grammar {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct Pair {
+        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+        pub key: String,
+        #[rust_sitter::leaf(text = ":")]
+        _colon: (),
+        #[rust_sitter::leaf(pattern = r"\d+")]
+        pub value: u8,
+        #[rust_sitter::leaf(pattern = r"#[a-z]+")]
+        pub tag: Option<Box<str>>,
+    }
+
+    #[rust_sitter::extra]
+    #[allow(dead_code)]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_leaves() {
+        let pair = grammar::parse("abc: 12 #tag").unwrap();
+        assert_eq!(pair.key, "abc");
+        assert_eq!(pair.value, 12);
+        assert_eq!(pair.tag.as_deref(), Some("#tag"));
+
+        insta::assert_debug_snapshot!(grammar::parse("abc: 12"));
+        insta::assert_debug_snapshot!(grammar::parse("abc: 300"));
+    }
+}
@@ -5,9 +5,23 @@ use codemap_diagnostic::{ColorConfig, Diagnostic, Emitter, Level, SpanLabel, Spa
 use rust_sitter::errors::{ParseError, ParseErrorReason};
 
 mod arithmetic;
+mod borrowed;
+mod composition;
+mod external;
+mod formatting;
+#[cfg(feature = "arbitrary")]
+mod fuzzing;
+mod highlighting;
+mod leaves;
 mod optionals;
 mod repetitions;
+#[cfg(feature = "serde")]
+mod serialization;
+mod syntax;
+mod trivia;
+mod unparse;
 mod words;
+mod wrappers;
 
 fn convert_parse_error_to_diagnostics(
     file_span: &codemap::Span,
@@ -37,6 +51,17 @@ fn convert_parse_error_to_diagnostics(
             }],
         }),
 
+        ParseErrorReason::InvalidLeaf(message) => diagnostics.push(Diagnostic {
+            level: Level::Error,
+            message: format!("Invalid token: {message}"),
+            code: Some("S000".to_string()),
+            spans: vec![SpanLabel {
+                span: file_span.subspan(error.start as u64, error.end as u64),
+                style: SpanStyle::Primary,
+                label: Some("invalid".to_string()),
+            }],
+        }),
+
         ParseErrorReason::FailedNode(errors) => {
             if errors.is_empty() {
                 diagnostics.push(Diagnostic {
@@ -16,6 +16,7 @@ pub mod grammar {
     }
 
     #[rust_sitter::extra]
+    #[allow(dead_code)]
     struct Whitespace {
         #[rust_sitter::leaf(pattern = r"\s")]
         _whitespace: (),
@@ -35,6 +36,7 @@ pub mod grammar2 {
     }
 
     #[rust_sitter::extra]
+    #[allow(dead_code)]
     struct Whitespace {
         #[rust_sitter::leaf(pattern = r"\s")]
         _whitespace: (),
@@ -60,6 +62,7 @@ pub mod grammar3 {
     }
 
     #[rust_sitter::extra]
+    #[allow(dead_code)]
     struct Whitespace {
         #[rust_sitter::leaf(pattern = r"\s")]
         _whitespace: (),
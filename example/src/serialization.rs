@@ -0,0 +1,62 @@
+#[rust_sitter::grammar("serialization", serde = true)]
+#[allow(dead_code)]
+pub mod grammar {
+    use rust_sitter::Spanned;
+
+    #[rust_sitter::language]
+    #[derive(Debug)]
+    pub struct Assignment<'a> {
+        #[rust_sitter::leaf(pattern = r"[a-z_]+")]
+        pub name: &'a str,
+        #[rust_sitter::leaf(text = "=")]
+        _eq: (),
+        #[rust_sitter::delimited(
+            #[rust_sitter::leaf(text = ",")]
+            ()
+        )]
+        pub values: Vec<Spanned<Value>>,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    pub enum Value {
+        Number(#[rust_sitter::leaf(pattern = r"\d+")] u32),
+        Negated(#[rust_sitter::leaf(text = "-")] (), Box<Value>),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::grammar::*;
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn ast_round_trip() {
+        let ast = parse("total = 1, -2").unwrap();
+        let json = serde_json::to_string(&ast).unwrap();
+        assert_eq!(
+            json,
+            r#"{"name":"total","_eq":null,"values":[{"value":{"Number":1},"span":[8,9]},{"value":{"Negated":[null,{"Number":2}]},"span":[11,13]}]}"#
+        );
+
+        let deserialized: Assignment = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.name, "total");
+        assert_eq!(
+            deserialized.values[1].value,
+            Value::Negated((), Box::new(Value::Number(2)))
+        );
+        assert_eq!(deserialized.values[1].span, (11, 13));
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn errors_serialize() {
+        let errors = parse("total = 99999999999").unwrap_err();
+        insta::assert_snapshot!(serde_json::to_string_pretty(&errors).unwrap());
+    }
+}
@@ -0,0 +1,56 @@
+#[rust_sitter::grammar("syntax")]
+#[allow(dead_code)]
+pub mod grammar {
+    use rust_sitter::Spanned;
+
+    #[rust_sitter::language]
+    #[derive(Debug)]
+    pub struct List {
+        #[rust_sitter::leaf(text = "[")]
+        _open: (),
+        #[rust_sitter::delimited(
+            #[rust_sitter::leaf(text = ",")]
+            ()
+        )]
+        pub items: Vec<Spanned<Item>>,
+        #[rust_sitter::leaf(text = "]")]
+        _close: (),
+    }
+
+    #[derive(Debug)]
+    pub enum Item {
+        Number(#[rust_sitter::leaf(pattern = r"\d+")] u32),
+        List(Box<List>),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::grammar::*;
+
+    const SOURCE: &str = "  [1 , [2,3] ]\n";
+
+    #[test]
+    fn round_trips() {
+        let cst = parse_cst(SOURCE);
+        assert_eq!(cst.to_string(), SOURCE);
+        insta::assert_debug_snapshot!(cst);
+    }
+
+    #[test]
+    fn typed_nodes_to_syntax() {
+        let cst = parse_cst(SOURCE);
+        let list = parse(SOURCE).unwrap();
+
+        let nested = list.items[1].syntax(&cst).unwrap();
+        assert_eq!(nested.kind(), "Item");
+        assert_eq!(nested.text(), "[2,3]");
+        assert_eq!(nested.parent().unwrap().kind(), "List.items");
+    }
+}
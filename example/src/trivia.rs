@@ -0,0 +1,88 @@
+#[rust_sitter::grammar("trivia")]
+#[allow(dead_code)]
+pub mod grammar {
+    use rust_sitter::Spanned;
+
+    #[rust_sitter::language]
+    #[derive(Debug)]
+    pub struct Program {
+        #[rust_sitter::repeat]
+        pub declarations: Vec<Declaration>,
+    }
+
+    #[derive(Debug)]
+    pub struct Declaration {
+        #[rust_sitter::trivia]
+        pub doc_comments: Vec<Spanned<Comment>>,
+        #[rust_sitter::leaf(text = "let")]
+        _let: (),
+        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+        pub name: String,
+        #[rust_sitter::leaf(text = ";")]
+        _semicolon: (),
+        #[rust_sitter::trivia(trailing = true)]
+        pub trailing_comments: Vec<Comment>,
+    }
+
+    #[rust_sitter::extra]
+    #[derive(Debug, PartialEq, Eq)]
+    pub struct Comment {
+        #[rust_sitter::leaf(pattern = r"//[^\n]*")]
+        pub text: String,
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::grammar::*;
+
+    const SOURCE: &str =
+        "// the first\n// value\nlet a; // after a\nlet b;\n\n// the last value\nlet c;";
+
+    #[test]
+    fn attaches_comments() {
+        let program = parse(SOURCE).unwrap();
+        let comments = program
+            .declarations
+            .iter()
+            .map(|d| {
+                (
+                    d.name.as_str(),
+                    d.doc_comments
+                        .iter()
+                        .map(|c| c.text.as_str())
+                        .collect::<Vec<_>>(),
+                    d.trailing_comments
+                        .iter()
+                        .map(|c| c.text.as_str())
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            comments,
+            vec![
+                ("a", vec!["// the first", "// value"], vec!["// after a"]),
+                ("b", vec!["// after a"], vec!["// the last value"]),
+                ("c", vec!["// the last value"], vec![]),
+            ]
+        );
+        assert_eq!(program.declarations[0].doc_comments[0].span, (0, 12));
+    }
+
+    #[test]
+    fn all_extras() {
+        let comments = extras::<Comment>(SOURCE).unwrap();
+        assert_eq!(
+            comments.iter().map(|c| c.span).collect::<Vec<_>>(),
+            vec![(0, 12), (13, 21), (29, 39), (48, 65)]
+        );
+    }
+}
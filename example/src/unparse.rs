@@ -0,0 +1,66 @@
+#[rust_sitter::grammar("unparse", unparse = true)]
+pub mod grammar {
+    #[rust_sitter::language]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Program {
+        statements: Vec<Statement>,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Statement {
+        #[rust_sitter::trivia]
+        comments: Vec<Comment>,
+        expression: Expression,
+        #[rust_sitter::leaf(text = ";")]
+        _semicolon: (),
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub enum Expression {
+        Number(#[rust_sitter::leaf(pattern = r"\d+")] u32),
+        Call(
+            #[rust_sitter::leaf(pattern = r"[a-z]+", transform = |v| v.to_uppercase(), print = |v: &String| v.to_lowercase())]
+             String,
+            #[rust_sitter::leaf(text = "(")] (),
+            #[rust_sitter::delimited(
+                #[rust_sitter::leaf(text = ",")]
+                ()
+            )]
+            Vec<Expression>,
+            #[rust_sitter::leaf(text = ")")] (),
+        ),
+        Negate(#[rust_sitter::leaf(text = "-")] (), Option<Box<Expression>>),
+    }
+
+    #[rust_sitter::extra]
+    #[derive(PartialEq, Eq, Debug)]
+    #[allow(dead_code)]
+    pub struct Comment {
+        #[rust_sitter::leaf(pattern = r"//[^\n]*")]
+        text: String,
+    }
+
+    #[rust_sitter::extra]
+    #[allow(dead_code)]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    #[test]
+    fn round_trip() {
+        let ast = grammar::parse("max( 1,-2 , min(3) ); -; // done\n-4;").unwrap();
+        let printed = rust_sitter::unparse::to_string(&ast, " ");
+        assert_eq!(printed, "max ( 1 , - 2 , min ( 3 ) ) ; - ; // done\n- 4 ;");
+        assert_eq!(grammar::parse(&printed).unwrap(), ast);
+    }
+}
@@ -2,6 +2,7 @@
 pub mod grammar {
     #[rust_sitter::language]
     #[derive(Debug)]
+    #[allow(dead_code)]
     pub struct Words {
         #[rust_sitter::leaf(text = r"if")]
         _keyword: (),
@@ -11,6 +12,7 @@ pub mod grammar {
     }
 
     #[rust_sitter::extra]
+    #[allow(dead_code)]
     struct Whitespace {
         #[rust_sitter::leaf(pattern = r"\s")]
         _whitespace: (),
@@ -0,0 +1,107 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A node in the tree along with a unique ID, for attaching information to nodes
+/// after parsing.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct Node<T> {
+    pub id: usize,
+    pub value: T,
+}
+
+impl<T> rust_sitter::Wrapper for Node<T> {
+    type Inner = T;
+
+    fn wrap(value: T, _span: (usize, usize)) -> Self {
+        Node {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            value,
+        }
+    }
+
+    fn inner(&self) -> &T {
+        &self.value
+    }
+}
+
+#[rust_sitter::grammar("wrappers", wrappers = [Node], unparse = true)]
+#[allow(dead_code)]
+pub mod grammar {
+    use super::Node;
+    use std::borrow::Cow;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    #[rust_sitter::language]
+    #[derive(Debug)]
+    pub struct Program<'a> {
+        #[rust_sitter::repeat]
+        pub statements: Vec<Node<Statement<'a>>>,
+    }
+
+    #[derive(Debug)]
+    pub enum Statement<'a> {
+        Print(
+            #[rust_sitter::leaf(text = "print")] (),
+            Rc<Value<'a>>,
+            #[rust_sitter::leaf(text = ";")] (),
+        ),
+        Assert(
+            #[rust_sitter::leaf(text = "assert")] (),
+            Arc<Value<'a>>,
+            #[rust_sitter::leaf(text = ";")] (),
+        ),
+    }
+
+    #[derive(Debug)]
+    pub enum Value<'a> {
+        Name(#[rust_sitter::leaf(pattern = r"[a-z]+")] Cow<'a, str>),
+        Number(#[rust_sitter::leaf(pattern = r"\d+")] Node<u32>),
+    }
+
+    #[rust_sitter::extra]
+    struct Whitespace {
+        #[rust_sitter::leaf(pattern = r"\s")]
+        _whitespace: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::grammar::*;
+
+    #[test]
+    fn custom_wrappers() {
+        let program = parse("print x; assert 1;").unwrap();
+        assert_eq!(program.statements.len(), 2);
+
+        let ids = program.statements.iter().map(|s| s.id).collect::<Vec<_>>();
+        assert_ne!(ids[0], ids[1]);
+
+        match &program.statements[0].value {
+            Statement::Print((), value, ()) => match value.as_ref() {
+                Value::Name(name) => assert_eq!(name, "x"),
+                _ => panic!("expected a name"),
+            },
+            _ => panic!("expected a print statement"),
+        }
+
+        match &program.statements[1].value {
+            Statement::Assert((), value, ()) => match value.as_ref() {
+                Value::Number(n) => assert_eq!(n.value, 1),
+                _ => panic!("expected a number"),
+            },
+            _ => panic!("expected an assert statement"),
+        }
+    }
+
+    #[test]
+    fn unparse_custom_wrappers() {
+        let program = parse("print x;assert  1;").unwrap();
+        let printed = rust_sitter::unparse::to_string(&program, " ");
+        assert_eq!(printed, "print x ; assert 1 ;");
+        assert_eq!(parse(&printed).unwrap().statements.len(), 2);
+    }
+}
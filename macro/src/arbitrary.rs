@@ -0,0 +1,360 @@
+use std::collections::{HashMap, HashSet};
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use rust_sitter_common::*;
+use syn::{punctuated::Punctuated, *};
+
+use crate::expansion::{is_text_leaf_type, source_lifetime};
+
+/// The parameters of a `#[rust_sitter::leaf(...)]` annotation that determine its values.
+struct Leaf {
+    text: Option<Expr>,
+    pattern: Option<Expr>,
+    transform: Option<Expr>,
+}
+
+fn leaf_params(attrs: &[Attribute]) -> Option<Leaf> {
+    let leaf_attr = attrs
+        .iter()
+        .find(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf))?;
+
+    let params = leaf_attr
+        .parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+        .ok()?;
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|param| param.path == name)
+            .map(|p| p.expr.clone())
+    };
+
+    Some(Leaf {
+        text: param("text"),
+        pattern: param("pattern"),
+        transform: param("transform"),
+    })
+}
+
+fn is_non_empty(attrs: &[Attribute]) -> bool {
+    attrs
+        .iter()
+        .find(|attr| attr.path == syn::parse_quote!(rust_sitter::repeat))
+        .and_then(|a| {
+            a.parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+                .ok()
+        })
+        .map(|params| {
+            params
+                .iter()
+                .any(|p| p.path == "non_empty" && p.expr == syn::parse_quote!(true))
+        })
+        .unwrap_or(false)
+}
+
+/// The outer type of a wrapper, `Vec` or `Option` type, along with the type it contains.
+fn split_wrapper(ty: &Type, wrappers: &HashSet<&str>) -> Option<(String, Type)> {
+    if let Type::Path(p) = ty {
+        let outer = p.path.segments.last().unwrap().ident.to_string();
+        if outer == "Vec" || outer == "Option" || wrappers.contains(outer.as_str()) {
+            let inner = filter_inner_type(ty, &[outer.as_str()].into());
+            if &inner != ty {
+                return Some((outer, inner));
+            }
+        }
+    }
+
+    None
+}
+
+/// Generates the expression for a leaf value, starting from text that matches the leaf.
+fn gen_leaf_value(ty: &Type, leaf: &Leaf) -> TokenStream {
+    if matches!(ty, Type::Tuple(t) if t.elems.is_empty()) {
+        return quote!(());
+    }
+
+    if let Type::Reference(_) = ty {
+        // Borrowed leaves cannot point into generated text, so they are arbitrary strings.
+        return quote!(u.arbitrary::<#ty>()?);
+    }
+
+    let text = match (&leaf.text, &leaf.pattern) {
+        (Some(text), _) => quote!(::std::string::ToString::to_string(#text)),
+        (None, Some(pattern)) => quote!(rust_sitter::arbitrary::arbitrary_match(u, #pattern)?),
+        (None, None) => quote!(::std::string::String::new()),
+    };
+
+    if let Some(transform) = &leaf.transform {
+        quote!({
+            let transform: &dyn Fn(&str) -> #ty = &#transform;
+            transform(&#text)
+        })
+    } else if is_text_leaf_type(ty) || *ty == syn::parse_quote!(Box<str>) {
+        quote!(::std::convert::From::from(#text))
+    } else if let (None, Some(pattern)) = (&leaf.text, &leaf.pattern) {
+        quote!(rust_sitter::arbitrary::arbitrary_leaf(u, #pattern, |text| {
+            ::std::str::FromStr::from_str(text).ok()
+        })?)
+    } else {
+        quote!(::std::str::FromStr::from_str(&#text)
+            .map_err(|_| rust_sitter::arbitrary::Error::IncorrectFormat)?)
+    }
+}
+
+/// Generates the expression for an arbitrary value of type `ty`, looking through `Vec`, `Option`
+/// and wrapper types down to the leaf or grammar type.
+fn gen_value(
+    ty: &Type,
+    leaf: Option<&Leaf>,
+    non_empty: bool,
+    wrappers: &HashSet<&str>,
+) -> TokenStream {
+    if let Some(leaf) = leaf.filter(|_| *ty == syn::parse_quote!(Box<str>)) {
+        return gen_leaf_value(ty, leaf);
+    }
+
+    match split_wrapper(ty, wrappers) {
+        Some((outer, inner)) if outer == "Vec" => {
+            let element = gen_value(&inner, leaf, false, wrappers);
+            let min = usize::from(non_empty);
+            quote!(rust_sitter::arbitrary::arbitrary_vec(u, #min, limited, |u| Ok(#element))?)
+        }
+        Some((outer, inner)) if outer == "Option" => {
+            let inner = gen_value(&inner, leaf, false, wrappers);
+            quote!(if !limited && u.arbitrary::<bool>()? {
+                Some(#inner)
+            } else {
+                None
+            })
+        }
+        Some((_, inner)) => {
+            let inner = gen_value(&inner, leaf, non_empty, wrappers);
+            quote!(<#ty as rust_sitter::Wrapper>::wrap(#inner, (0, 0)))
+        }
+        None => match leaf {
+            Some(leaf) => gen_leaf_value(ty, leaf),
+            None => quote!(rust_sitter::arbitrary::ArbitraryNode::arbitrary_node(
+                u,
+                depth + 1
+            )?),
+        },
+    }
+}
+
+/// The grammar type that must be generated for a value of type `ty`, looking through wrappers
+/// and `non_empty` repeats, or `None` if the value can be generated without one.
+fn required_type(
+    ty: &Type,
+    leaf: bool,
+    non_empty: bool,
+    wrappers: &HashSet<&str>,
+) -> Option<String> {
+    match split_wrapper(ty, wrappers) {
+        Some((outer, _)) if outer == "Option" => None,
+        Some((outer, inner)) if outer == "Vec" => {
+            if non_empty {
+                required_type(&inner, leaf, false, wrappers)
+            } else {
+                None
+            }
+        }
+        Some((_, inner)) => required_type(&inner, leaf, non_empty, wrappers),
+        None if leaf => None,
+        None => match ty {
+            Type::Path(p) => Some(p.path.segments.last().unwrap().ident.to_string()),
+            _ => None,
+        },
+    }
+}
+
+fn is_skip_or_trivia(field: &Field) -> Option<TokenStream> {
+    if let Some(skip_attr) = field
+        .attrs
+        .iter()
+        .find(|attr| attr.path == syn::parse_quote!(rust_sitter::skip))
+    {
+        let value: Expr = skip_attr.parse_args().unwrap();
+        return Some(quote!(#value));
+    }
+
+    if field
+        .attrs
+        .iter()
+        .any(|attr| attr.path == syn::parse_quote!(rust_sitter::trivia))
+    {
+        return Some(quote!(::std::vec::Vec::new()));
+    }
+
+    None
+}
+
+fn gen_fields(path: TokenStream, fields: &Fields, wrappers: &HashSet<&str>) -> TokenStream {
+    let values = fields.iter().map(|field| {
+        let value = is_skip_or_trivia(field).unwrap_or_else(|| {
+            gen_value(
+                &field.ty,
+                leaf_params(&field.attrs).as_ref(),
+                is_non_empty(&field.attrs),
+                wrappers,
+            )
+        });
+
+        match &field.ident {
+            Some(name) => quote!(#name: #value),
+            None => value,
+        }
+    });
+
+    match fields {
+        Fields::Named(_) => quote!(#path { #(#values),* }),
+        Fields::Unnamed(_) => quote!(#path(#(#values),*)),
+        Fields::Unit => path,
+    }
+}
+
+/// The depth of the shallowest value that can be generated for a struct or variant with the given
+/// fields, given the depths known so far, or `None` if it is not known to terminate yet.
+fn fields_depth(
+    fields: &Fields,
+    depths: &HashMap<String, Option<usize>>,
+    wrappers: &HashSet<&str>,
+) -> Option<usize> {
+    fields
+        .iter()
+        .filter(|field| is_skip_or_trivia(field).is_none())
+        .filter_map(|field| {
+            required_type(
+                &field.ty,
+                leaf_params(&field.attrs).is_some(),
+                is_non_empty(&field.attrs),
+                wrappers,
+            )
+        })
+        // Types from other grammars generate their own shallowest values.
+        .map(|ty| depths.get(&ty).copied().unwrap_or(Some(1)))
+        .try_fold(0, |depth, field_depth| Some(depth.max(field_depth?)))
+        .map(|depth| depth + 1)
+}
+
+/// Computes the depth of the shallowest value of each type in the grammar, as a fixed point
+/// over the types that refer to each other. Types whose values are all infinitely deep have a
+/// depth of `None`.
+pub fn termination_depths(
+    items: &[Item],
+    wrappers: &HashSet<&str>,
+) -> HashMap<String, Option<usize>> {
+    let mut depths: HashMap<String, Option<usize>> = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Struct(s) => Some((s.ident.to_string(), None)),
+            Item::Enum(e) => Some((e.ident.to_string(), None)),
+            _ => None,
+        })
+        .collect();
+
+    loop {
+        let mut changed = false;
+        for item in items {
+            let (ident, depth) = match item {
+                Item::Struct(s) => (&s.ident, fields_depth(&s.fields, &depths, wrappers)),
+                Item::Enum(e) => (
+                    &e.ident,
+                    e.variants
+                        .iter()
+                        .filter_map(|v| fields_depth(&v.fields, &depths, wrappers))
+                        .min(),
+                ),
+                _ => continue,
+            };
+
+            let known = depths.get_mut(&ident.to_string()).unwrap();
+            if depth.is_some() && *known != depth {
+                *known = depth;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return depths;
+        }
+    }
+}
+
+/// Generates the `Arbitrary` and `ArbitraryNode` implementations for a struct or enum in the
+/// grammar.
+pub fn gen_arbitrary_impls(
+    item: &Item,
+    depths: &HashMap<String, Option<usize>>,
+    wrappers: &HashSet<&str>,
+) -> Vec<Item> {
+    let (ident, generics, body) = match item {
+        Item::Struct(s) => {
+            let construct = gen_fields(quote!(Self), &s.fields, wrappers);
+            (&s.ident, &s.generics, quote!(Ok(#construct)))
+        }
+        Item::Enum(e) => {
+            let arms = e.variants.iter().enumerate().map(|(i, v)| {
+                let variant = &v.ident;
+                let construct = gen_fields(quote!(Self::#variant), &v.fields, wrappers);
+                quote!(#i => #construct)
+            });
+
+            // Once the maximum depth is reached, only the variants leading to the shallowest
+            // values are generated, so that every level gets closer to the leaves.
+            let variant_depths: Vec<Option<usize>> = e
+                .variants
+                .iter()
+                .map(|v| fields_depth(&v.fields, depths, wrappers))
+                .collect();
+            let shallowest_depth = variant_depths.iter().flatten().min();
+            let shallowest = variant_depths
+                .iter()
+                .enumerate()
+                .filter(|(_, d)| shallowest_depth.is_none() || d.as_ref() == shallowest_depth)
+                .map(|(i, _)| i);
+            let count = e.variants.len();
+
+            (
+                &e.ident,
+                &e.generics,
+                quote! {
+                    let variant = if limited {
+                        let shallowest = [#(#shallowest),*];
+                        shallowest[u.choose_index(shallowest.len())?]
+                    } else {
+                        u.choose_index(#count)?
+                    };
+
+                    Ok(match variant {
+                        #(#arms,)*
+                        _ => unreachable!(),
+                    })
+                },
+            )
+        }
+        _ => return vec![],
+    };
+
+    let lifetime =
+        source_lifetime(generics).unwrap_or_else(|| Lifetime::new("'arbitrary", Span::call_site()));
+    let (_, ty_generics, _) = generics.split_for_impl();
+
+    vec![
+        syn::parse_quote! {
+            impl<#lifetime> rust_sitter::arbitrary::ArbitraryNode<#lifetime> for #ident #ty_generics {
+                #[allow(unused_variables, clippy::needless_question_mark)]
+                fn arbitrary_node(u: &mut rust_sitter::arbitrary::Unstructured<#lifetime>, depth: usize) -> rust_sitter::arbitrary::Result<Self> {
+                    let limited = depth >= rust_sitter::arbitrary::MAX_DEPTH;
+                    #body
+                }
+            }
+        },
+        syn::parse_quote! {
+            impl<#lifetime> rust_sitter::arbitrary::Arbitrary<#lifetime> for #ident #ty_generics {
+                fn arbitrary(u: &mut rust_sitter::arbitrary::Unstructured<#lifetime>) -> rust_sitter::arbitrary::Result<Self> {
+                    rust_sitter::arbitrary::ArbitraryNode::arbitrary_node(u, 0)
+                }
+            }
+        },
+    ]
+}
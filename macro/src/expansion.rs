@@ -3,7 +3,12 @@ use std::collections::HashSet;
 use proc_macro2::Span;
 use quote::ToTokens;
 use rust_sitter_common::*;
-use syn::{parse::Parse, punctuated::Punctuated, *};
+use syn::{punctuated::Punctuated, *};
+
+use crate::arbitrary::{gen_arbitrary_impls, termination_depths};
+use crate::query::gen_query_schema;
+use crate::unparse::gen_unparse_impl;
+use crate::visit::gen_visitors;
 
 fn is_sitter_attr(attr: &Attribute) -> bool {
     let ident = &attr.path.segments.iter().next().unwrap().ident;
@@ -24,7 +29,55 @@ impl ToTokens for ParamOrField {
     }
 }
 
-fn gen_field(path: String, ident_str: String, leaf: Field, out: &mut Vec<Item>) {
+/// Whether a leaf type can be extracted directly from the text of a leaf, without a `transform`.
+pub fn is_text_leaf_type(ty: &Type) -> bool {
+    match ty {
+        Type::Tuple(t) => t.elems.is_empty(),
+        Type::Reference(_) => true,
+        Type::Path(p) => {
+            p.path.is_ident("str")
+                || p.path.is_ident("String")
+                || p.path.segments.last().unwrap().ident == "Cow"
+        }
+        _ => false,
+    }
+}
+
+/// The lifetime of the source that a grammar type borrows from, which is its only lifetime
+/// parameter, if it has one.
+pub fn source_lifetime(generics: &Generics) -> Option<Lifetime> {
+    if generics.type_params().next().is_some()
+        || generics.const_params().next().is_some()
+        || generics.lifetimes().count() > 1
+    {
+        panic!("Grammar types can only have a single lifetime parameter");
+    }
+
+    generics.lifetimes().next().map(|l| l.lifetime.clone())
+}
+
+/// The type of the source passed to generated extraction functions, which is tied to the
+/// lifetime of the grammar type (if it has one) so that leaves can borrow from the source.
+fn source_type(generics: &Generics) -> Type {
+    match source_lifetime(generics) {
+        Some(lifetime) => syn::parse_quote!(&#lifetime [u8]),
+        None => syn::parse_quote!(&[u8]),
+    }
+}
+
+/// The lifetime argument used for the `Extract` implementation of a grammar type.
+fn extract_lifetime(generics: &Generics) -> Lifetime {
+    source_lifetime(generics).unwrap_or_else(|| Lifetime::new("'_", Span::call_site()))
+}
+
+fn gen_field(
+    path: String,
+    ident_str: String,
+    leaf: Field,
+    generics: &Generics,
+    wrappers: &HashSet<&str>,
+    out: &mut Vec<Item>,
+) {
     let extract_ident = Ident::new(&format!("extract_{path}"), Span::call_site());
     let leaf_type = leaf.ty;
 
@@ -44,36 +97,41 @@ fn gen_field(path: String, ident_str: String, leaf: Field, out: &mut Vec<Item>)
             .map(|p| p.expr.clone())
     });
 
-    let mut skip_over = HashSet::new();
-    skip_over.insert("Spanned");
-    skip_over.insert("Box");
+    let mut non_leaf = wrappers.clone();
+    non_leaf.insert("Option");
+    non_leaf.insert("Vec");
+
+    // Leaves without a transform are parsed with `FromStr`, unless the leaf type can hold the
+    // text directly. Text that cannot be parsed is reported as an error by `parse`.
+    let inner_type = filter_inner_type(&leaf_type, &non_leaf);
+    let leaf_extractor: Option<syn::Path> = if transform_param.is_some() {
+        Some(syn::parse_quote!(rust_sitter::WithLeaf))
+    } else if leaf_params.is_some() && !is_text_leaf_type(&inner_type) {
+        Some(syn::parse_quote!(rust_sitter::FromStrLeaf))
+    } else {
+        None
+    };
 
-    let (leaf_stmts, leaf_expr): (Vec<Stmt>, Expr) = match transform_param {
-        Some(closure) => {
-            let mut non_leaf = HashSet::new();
-            non_leaf.insert("Spanned");
-            non_leaf.insert("Box");
-            non_leaf.insert("Option");
-            non_leaf.insert("Vec");
-            let wrapped_leaf_type = wrap_leaf_type(&leaf_type, &non_leaf);
+    let leaf_fn: Expr = match transform_param {
+        Some(closure) => syn::parse_quote!(Some(&#closure)),
+        None => syn::parse_quote!(None),
+    };
 
-            (
-                vec![],
-                syn::parse_quote!(<#wrapped_leaf_type as rust_sitter::Extract<_>>::extract(node, source, *last_idx, Some(&#closure))),
-            )
-        }
-        None => (
-            vec![],
-            syn::parse_quote!(<#leaf_type as rust_sitter::Extract<_>>::extract(node, source, *last_idx, None)),
+    let extract_type = match leaf_extractor {
+        Some(leaf_extractor) => wrap_wrapper_types(
+            &wrap_leaf_type(&leaf_type, &non_leaf, &leaf_extractor),
+            wrappers,
         ),
+        None => wrap_wrapper_types(&leaf_type, wrappers),
     };
 
+    let leaf_expr: Expr = syn::parse_quote!(<#extract_type as rust_sitter::Extract<_>>::try_extract(node, source, *last_idx, #leaf_fn));
+
+    let source_type = source_type(generics);
     out.push(syn::parse_quote! {
         #[allow(non_snake_case)]
         #[allow(clippy::unused_unit)]
-        fn #extract_ident(cursor_opt: &mut Option<rust_sitter::tree_sitter::TreeCursor>, source: &[u8], last_idx: &mut usize) -> #leaf_type {
-            #(#leaf_stmts)*
-
+        fn #extract_ident #generics(cursor_opt: &mut Option<rust_sitter::tree_sitter::TreeCursor>, source: #source_type, last_idx: &mut usize) -> core::result::Result<#leaf_type, rust_sitter::errors::ParseError> {
             if let Some(cursor) = cursor_opt.as_mut() {
                 loop {
                     let n = cursor.node();
@@ -110,11 +168,52 @@ fn gen_field(path: String, ident_str: String, leaf: Field, out: &mut Vec<Item>)
     });
 }
 
+/// Generates the expression collecting the extras around a node for a `#[rust_sitter::trivia]`
+/// field, which must be a `Vec` of some (possibly wrapped) extra type.
+fn gen_trivia(attr: &Attribute, ty: &Type, wrappers: &HashSet<&str>) -> Expr {
+    let trailing = if attr.tokens.is_empty() {
+        false
+    } else {
+        let params = attr
+            .parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+            .expect("Expected named parameters for `trivia`");
+        params.iter().any(|p| {
+            p.path == "trailing"
+                && matches!(&p.expr, Expr::Lit(ExprLit { lit: Lit::Bool(b), .. }) if b.value)
+        })
+    };
+
+    let (element_type, is_vec) = try_extract_inner_type(ty, "Vec", &HashSet::new());
+    if !is_vec {
+        panic!("Trivia fields must be a `Vec` of extras");
+    }
+
+    let kind = if let Type::Path(p) = filter_inner_type(&element_type, wrappers) {
+        p.path.segments.last().unwrap().ident.to_string()
+    } else {
+        panic!("Expected a path for the type of trivia");
+    };
+
+    let element_type = wrap_wrapper_types(&element_type, wrappers);
+    let collect_ident = if trailing {
+        Ident::new("trailing", Span::call_site())
+    } else {
+        Ident::new("leading", Span::call_site())
+    };
+
+    syn::parse_quote! {
+        rust_sitter::trivia::#collect_ident::<#element_type, _>(node, source, #kind)?
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn gen_struct_or_variant(
     path: String,
     fields: Fields,
     variant_ident: Option<Ident>,
     containing_type: Ident,
+    generics: &Generics,
+    wrappers: &HashSet<&str>,
     out: &mut Vec<Item>,
 ) {
     fields.iter().enumerate().for_each(|(i, field)| {
@@ -124,15 +223,16 @@ fn gen_struct_or_variant(
             .map(|v| v.to_string())
             .unwrap_or(format!("{i}"));
 
-        if !field
-            .attrs
-            .iter()
-            .any(|attr| attr.path == syn::parse_quote!(rust_sitter::skip))
-        {
+        if !field.attrs.iter().any(|attr| {
+            attr.path == syn::parse_quote!(rust_sitter::skip)
+                || attr.path == syn::parse_quote!(rust_sitter::trivia)
+        }) {
             gen_field(
                 format!("{}_{}", path.clone(), ident_str),
                 ident_str,
                 field.clone(),
+                generics,
+                wrappers,
                 out,
             );
         }
@@ -152,6 +252,12 @@ fn gen_struct_or_variant(
                 .find(|attr| attr.path == syn::parse_quote!(rust_sitter::skip))
             {
                 skip_attrs.parse_args::<syn::Expr>().unwrap()
+            } else if let Some(trivia_attr) = field
+                .attrs
+                .iter()
+                .find(|attr| attr.path == syn::parse_quote!(rust_sitter::trivia))
+            {
+                gen_trivia(trivia_attr, &field.ty, wrappers)
             } else {
                 let ident_str = field
                     .ident
@@ -165,14 +271,11 @@ fn gen_struct_or_variant(
                 );
 
                 syn::parse_quote! {
-                    #ident(&mut cursor, source, &mut last_idx)
+                    #ident(&mut cursor, source, &mut last_idx)?
                 }
             };
 
-            if field.ident.is_none() {
-                ParamOrField::Param(expr)
-            } else {
-                let field_name = field.ident.as_ref().unwrap();
+            if let Some(field_name) = &field.ident {
                 have_named_field = true;
                 ParamOrField::Field(FieldValue {
                     attrs: vec![],
@@ -180,6 +283,8 @@ fn gen_struct_or_variant(
                     colon_token: Some(Token![:](Span::call_site())),
                     expr,
                 })
+            } else {
+                ParamOrField::Param(expr)
             }
         })
         .collect::<Vec<ParamOrField>>();
@@ -206,9 +311,11 @@ fn gen_struct_or_variant(
         }
     };
 
+    let source_type = source_type(generics);
+    let (_, ty_generics, _) = generics.split_for_impl();
     out.push(syn::parse_quote! {
         #[allow(non_snake_case)]
-        fn #extract_ident(node: rust_sitter::tree_sitter::Node, source: &[u8]) -> #containing_type {
+        fn #extract_ident #generics(node: rust_sitter::tree_sitter::Node, source: #source_type) -> core::result::Result<#containing_type #ty_generics, rust_sitter::errors::ParseError> {
             let mut last_idx = node.start_byte();
             let mut parent_cursor = node.walk();
             let mut cursor = if parent_cursor.goto_first_child() {
@@ -217,45 +324,291 @@ fn gen_struct_or_variant(
                 None
             };
 
-            #construct_expr
+            Ok(#construct_expr)
         }
     });
 }
 
-pub fn expand_grammar(input: ItemMod) -> ItemMod {
-    let grammar_name = input
+/// Generates the accessor for a field in a view, which looks up the corresponding child node
+/// and wraps it in a view (or returns the text for leaves).
+fn gen_view_accessor(
+    field: &Field,
+    i: usize,
+    lifetime: &Lifetime,
+    wrappers: &HashSet<&str>,
+) -> Option<ImplItem> {
+    if field.attrs.iter().any(|attr| {
+        attr.path == syn::parse_quote!(rust_sitter::skip)
+            || attr.path == syn::parse_quote!(rust_sitter::trivia)
+    }) || matches!(&field.ty, Type::Tuple(t) if t.elems.is_empty())
+    {
+        return None;
+    }
+
+    let field_name = field
+        .ident
+        .as_ref()
+        .map(|v| v.to_string())
+        .unwrap_or(format!("{i}"));
+    let method_ident = field
+        .ident
+        .clone()
+        .unwrap_or_else(|| Ident::new(&format!("_{i}"), Span::call_site()));
+
+    let is_leaf = field
         .attrs
         .iter()
-        .find_map(|a| {
-            if a.path == syn::parse_quote!(rust_sitter::grammar) {
-                let grammar_name_expr = a.parse_args_with(Expr::parse).ok();
-                if let Some(Expr::Lit(ExprLit {
-                    attrs: _,
-                    lit: Lit::Str(s),
-                })) = grammar_name_expr
-                {
-                    Some(s.value())
-                } else {
-                    panic!("Expected string literal for grammar name");
+        .any(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf));
+
+    let mut element_wrappers = wrappers.clone();
+    element_wrappers.insert("Option");
+
+    let view_of = |ty: &Type| -> (Type, Expr) {
+        if is_leaf {
+            (
+                syn::parse_quote!(&#lifetime str),
+                syn::parse_quote!(n.utf8_text(source).unwrap()),
+            )
+        } else {
+            let ty = filter_inner_type(ty, &element_wrappers);
+            (
+                syn::parse_quote!(<#ty as rust_sitter::views::HasView<#lifetime>>::View),
+                syn::parse_quote!(rust_sitter::views::view::<#ty>(n, source)),
+            )
+        }
+    };
+
+    let (inner_type_vec, is_vec) = try_extract_inner_type(&field.ty, "Vec", wrappers);
+    let (inner_type_option, is_option) = try_extract_inner_type(&field.ty, "Option", wrappers);
+
+    Some(if is_vec {
+        let (element_type, convert) = view_of(&inner_type_vec);
+        syn::parse_quote! {
+            pub fn #method_ident(&self) -> impl Iterator<Item = #element_type> + #lifetime {
+                let source = self.source;
+                rust_sitter::views::elements(self.node.child_by_field_name(#field_name))
+                    .map(move |n| #convert)
+            }
+        }
+    } else if is_option {
+        let (element_type, convert) = view_of(&inner_type_option);
+        syn::parse_quote! {
+            pub fn #method_ident(&self) -> Option<#element_type> {
+                let source = self.source;
+                self.node.child_by_field_name(#field_name).map(|n| #convert)
+            }
+        }
+    } else {
+        let (element_type, convert) = view_of(&field.ty);
+        syn::parse_quote! {
+            pub fn #method_ident(&self) -> #element_type {
+                let source = self.source;
+                let n = self.node.child_by_field_name(#field_name).unwrap();
+                #convert
+            }
+        }
+    })
+}
+
+/// Generates a view struct wrapping the node of a struct or enum variant, along with its
+/// field accessors.
+fn gen_view_struct(
+    view_ident: &Ident,
+    fields: &Fields,
+    lifetime: &Lifetime,
+    wrappers: &HashSet<&str>,
+    extra_methods: Vec<ImplItem>,
+) -> Vec<Item> {
+    let accessors = fields
+        .iter()
+        .enumerate()
+        .filter_map(|(i, field)| gen_view_accessor(field, i, lifetime, wrappers));
+
+    vec![
+        syn::parse_quote! {
+            #[derive(Clone, Copy)]
+            pub struct #view_ident<#lifetime> {
+                node: rust_sitter::tree_sitter::Node<#lifetime>,
+                source: &#lifetime [u8],
+            }
+        },
+        syn::parse_quote! {
+            impl<#lifetime> rust_sitter::views::View<#lifetime> for #view_ident<#lifetime> {
+                fn from_node(node: rust_sitter::tree_sitter::Node<#lifetime>, source: &#lifetime [u8]) -> Self {
+                    #view_ident { node, source }
+                }
+
+                fn node(&self) -> rust_sitter::tree_sitter::Node<#lifetime> {
+                    self.node
                 }
-            } else {
-                None
+            }
+        },
+        syn::parse_quote! {
+            impl<#lifetime> #view_ident<#lifetime> {
+                #(#extra_methods)*
+                #(#accessors)*
+            }
+        },
+    ]
+}
+
+/// Generates the view types for a struct or enum in the grammar, which go in the `views` module.
+fn gen_views(item: &Item, wrappers: &HashSet<&str>) -> Vec<Item> {
+    let (ident, generics) = match item {
+        Item::Enum(e) => (&e.ident, &e.generics),
+        Item::Struct(s) => (&s.ident, &s.generics),
+        _ => return vec![],
+    };
+
+    let lifetime =
+        source_lifetime(generics).unwrap_or_else(|| Lifetime::new("'tree", Span::call_site()));
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let view_ident = Ident::new(&format!("{ident}Ref"), Span::call_site());
+
+    let has_view_impl: Item = syn::parse_quote! {
+        impl<#lifetime> rust_sitter::views::HasView<#lifetime> for #ident #ty_generics {
+            type View = #view_ident<#lifetime>;
+        }
+    };
+
+    match item {
+        Item::Struct(s) => {
+            let extract_method: ImplItem = syn::parse_quote! {
+                pub fn extract(&self) -> core::result::Result<#ident #ty_generics, rust_sitter::errors::ParseError> {
+                    <#ident #ty_generics as rust_sitter::Extract<_>>::try_extract(Some(self.node), self.source, self.node.start_byte(), None)
+                }
+            };
+
+            let mut out = gen_view_struct(
+                &view_ident,
+                &s.fields,
+                &lifetime,
+                wrappers,
+                vec![extract_method],
+            );
+            out.push(has_view_impl);
+            out
+        }
+        Item::Enum(e) => {
+            let mut out = vec![];
+            let mut variant_kinds = vec![];
+            let mut variants: Vec<Variant> = vec![];
+            let mut from_node_arms: Vec<Arm> = vec![];
+            let mut parts_arms: Vec<Arm> = vec![];
+            e.variants.iter().for_each(|v| {
+                let variant_ident = &v.ident;
+                let variant_view_ident =
+                    Ident::new(&format!("{ident}{variant_ident}Ref"), Span::call_site());
+                let variant_kind = format!("{ident}_{variant_ident}");
+
+                out.extend(gen_view_struct(
+                    &variant_view_ident,
+                    &v.fields,
+                    &lifetime,
+                    wrappers,
+                    vec![],
+                ));
+
+                variants.push(syn::parse_quote!(#variant_ident(#variant_view_ident<#lifetime>)));
+                from_node_arms.push(syn::parse_quote! {
+                    #variant_kind => #view_ident::#variant_ident(#variant_view_ident { node: variant, source })
+                });
+                parts_arms.push(syn::parse_quote! {
+                    #view_ident::#variant_ident(v) => (v.node, v.source)
+                });
+                variant_kinds.push(variant_kind);
+            });
+
+            out.push(syn::parse_quote! {
+                #[derive(Clone, Copy)]
+                pub enum #view_ident<#lifetime> {
+                    #(#variants),*
+                }
+            });
+
+            out.push(syn::parse_quote! {
+                impl<#lifetime> rust_sitter::views::View<#lifetime> for #view_ident<#lifetime> {
+                    fn from_node(node: rust_sitter::tree_sitter::Node<#lifetime>, source: &#lifetime [u8]) -> Self {
+                        let variant = rust_sitter::views::variant_node(node, &[#(#variant_kinds),*]);
+                        match variant.kind() {
+                            #(#from_node_arms,)*
+                            _ => unreachable!(),
+                        }
+                    }
+
+                    fn node(&self) -> rust_sitter::tree_sitter::Node<#lifetime> {
+                        self.variant_parts().0.parent().unwrap()
+                    }
+                }
+            });
+
+            out.push(syn::parse_quote! {
+                impl<#lifetime> #view_ident<#lifetime> {
+                    fn variant_parts(&self) -> (rust_sitter::tree_sitter::Node<#lifetime>, &#lifetime [u8]) {
+                        match self {
+                            #(#parts_arms),*
+                        }
+                    }
+
+                    pub fn extract(&self) -> core::result::Result<#ident #ty_generics, rust_sitter::errors::ParseError> {
+                        use rust_sitter::views::View;
+                        let node = self.node();
+                        <#ident #ty_generics as rust_sitter::Extract<_>>::try_extract(Some(node), self.variant_parts().1, node.start_byte(), None)
+                    }
+                }
+            });
+
+            out.push(has_view_impl);
+            out
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Implements `rust_sitter::Extra` for a type marked with `#[rust_sitter::extra]`.
+fn gen_extra_impl(attrs: &[Attribute], ident: &Ident, generics: &Generics) -> Option<Item> {
+    if attrs
+        .iter()
+        .any(|attr| attr.path == syn::parse_quote!(rust_sitter::extra))
+    {
+        let kind = ident.to_string();
+        let (impl_generics, ty_generics, _) = generics.split_for_impl();
+        Some(syn::parse_quote! {
+            impl #impl_generics rust_sitter::Extra for #ident #ty_generics {
+                const KIND: &'static str = #kind;
             }
         })
-        .expect("Each grammar must have a name");
+    } else {
+        None
+    }
+}
+
+pub fn expand_grammar(input: ItemMod) -> ItemMod {
+    let grammar_args = grammar_args(&input.attrs).expect("Each grammar must have a name");
+    let grammar_name = grammar_args.name.value();
 
     let (brace, new_contents) = input.content.unwrap();
 
-    let root_type = new_contents
+    let (root_type, root_generics) = new_contents
         .iter()
         .find_map(|item| match item {
-            Item::Enum(ItemEnum { ident, attrs, .. })
-            | Item::Struct(ItemStruct { ident, attrs, .. }) => {
+            Item::Enum(ItemEnum {
+                ident,
+                attrs,
+                generics,
+                ..
+            })
+            | Item::Struct(ItemStruct {
+                ident,
+                attrs,
+                generics,
+                ..
+            }) => {
                 if attrs
                     .iter()
                     .any(|attr| attr.path == syn::parse_quote!(rust_sitter::language))
                 {
-                    Some(ident.clone())
+                    Some((ident.clone(), generics.clone()))
                 } else {
                     None
                 }
@@ -264,6 +617,63 @@ pub fn expand_grammar(input: ItemMod) -> ItemMod {
         })
         .expect("Each parser must have the root type annotated with `#[rust_sitter::language]`");
 
+    let registered_wrappers = grammar_args.wrappers();
+    let mut wrappers: HashSet<&str> = BUILTIN_WRAPPERS.into_iter().collect();
+    wrappers.extend(registered_wrappers.iter().map(String::as_str));
+
+    // Typed queries return views of the captured nodes, so they need the views too.
+    let queries = grammar_args.flag("queries");
+    let views: Vec<Item> = if queries || grammar_args.flag("views") {
+        new_contents
+            .iter()
+            .flat_map(|item| gen_views(item, &wrappers))
+            .collect()
+    } else {
+        vec![]
+    };
+
+    let visitors = if grammar_args.flag("visit") {
+        gen_visitors(&new_contents)
+    } else {
+        vec![]
+    };
+
+    let query_schema = if queries {
+        Some(gen_query_schema(&new_contents, &root_type))
+    } else {
+        None
+    };
+
+    let arbitrary_impls: Vec<Item> = if grammar_args.flag("arbitrary") {
+        let depths = termination_depths(&new_contents, &wrappers);
+        new_contents
+            .iter()
+            .flat_map(|item| gen_arbitrary_impls(item, &depths, &wrappers))
+            .collect()
+    } else {
+        vec![]
+    };
+
+    let unparse_impls: Vec<Item> = if grammar_args.flag("unparse") {
+        new_contents
+            .iter()
+            .filter_map(|item| gen_unparse_impl(item, &wrappers))
+            .collect()
+    } else {
+        vec![]
+    };
+
+    let serde_attrs: Vec<Attribute> = if grammar_args.flag("serde") {
+        vec![
+            syn::parse_quote!(#[derive(rust_sitter::serde::Serialize, rust_sitter::serde::Deserialize)]),
+            syn::parse_quote!(#[serde(crate = "rust_sitter::serde")]),
+        ]
+    } else {
+        vec![]
+    };
+
+    let rule_names = rule_names(&new_contents, &root_type.to_string(), &wrappers);
+    let mut has_extras = false;
     let mut transformed: Vec<Item> = new_contents
         .iter()
         .cloned()
@@ -276,6 +686,8 @@ pub fn expand_grammar(input: ItemMod) -> ItemMod {
                         v.fields.clone(),
                         Some(v.ident.clone()),
                         e.ident.clone(),
+                        &e.generics,
+                        &wrappers,
                         &mut impl_body,
                     )
                 });
@@ -293,7 +705,11 @@ pub fn expand_grammar(input: ItemMod) -> ItemMod {
                     })
                     .collect();
 
+                let extra_impl = gen_extra_impl(&e.attrs, &e.ident, &e.generics);
+                has_extras |= extra_impl.is_some();
+
                 e.attrs.retain(|a| !is_sitter_attr(a));
+                e.attrs.extend(serde_attrs.iter().cloned());
                 e.variants.iter_mut().for_each(|v| {
                     v.attrs.retain(|a| !is_sitter_attr(a));
                     v.fields.iter_mut().for_each(|f| {
@@ -302,12 +718,20 @@ pub fn expand_grammar(input: ItemMod) -> ItemMod {
                 });
 
                 let enum_name = &e.ident;
+                let (impl_generics, ty_generics, _) = e.generics.split_for_impl();
+                let lifetime = extract_lifetime(&e.generics);
+                let source_type = source_type(&e.generics);
                 let extract_impl: Item = syn::parse_quote! {
-                    impl rust_sitter::Extract<#enum_name> for #enum_name {
+                    impl #impl_generics rust_sitter::Extract<#lifetime, #enum_name #ty_generics> for #enum_name #ty_generics {
                         type LeafFn = ();
 
+                        fn extract(node: Option<rust_sitter::tree_sitter::Node>, source: #source_type, last_idx: usize, leaf_fn: Option<&Self::LeafFn>) -> Self {
+                            Self::try_extract(node, source, last_idx, leaf_fn)
+                                .unwrap_or_else(|e| panic!("Could not convert leaf: {:?}", e.reason))
+                        }
+
                         #[allow(non_snake_case)]
-                        fn extract(node: Option<rust_sitter::tree_sitter::Node>, source: &[u8], _last_idx: usize, _leaf_fn: Option<&Self::LeafFn>) -> Self {
+                        fn try_extract(node: Option<rust_sitter::tree_sitter::Node>, source: #source_type, _last_idx: usize, _leaf_fn: Option<&Self::LeafFn>) -> core::result::Result<Self, rust_sitter::errors::ParseError> {
                             let node = node.unwrap();
                             #(#impl_body)*
 
@@ -326,7 +750,9 @@ pub fn expand_grammar(input: ItemMod) -> ItemMod {
                     }
                 };
 
-                vec![Item::Enum(e), extract_impl]
+                let mut items = vec![Item::Enum(e), extract_impl];
+                items.extend(extra_impl);
+                items
             }
 
             Item::Struct(mut s) => {
@@ -337,10 +763,16 @@ pub fn expand_grammar(input: ItemMod) -> ItemMod {
                     s.fields.clone(),
                     None,
                     s.ident.clone(),
+                    &s.generics,
+                    &wrappers,
                     &mut impl_body,
                 );
 
+                let extra_impl = gen_extra_impl(&s.attrs, &s.ident, &s.generics);
+                has_extras |= extra_impl.is_some();
+
                 s.attrs.retain(|a| !is_sitter_attr(a));
+                s.attrs.extend(serde_attrs.iter().cloned());
                 s.fields.iter_mut().for_each(|f| {
                     f.attrs.retain(|a| !is_sitter_attr(a));
                 });
@@ -349,12 +781,20 @@ pub fn expand_grammar(input: ItemMod) -> ItemMod {
                 let extract_ident =
                     Ident::new(&format!("extract_{struct_name}"), Span::call_site());
 
+                let (impl_generics, ty_generics, _) = s.generics.split_for_impl();
+                let lifetime = extract_lifetime(&s.generics);
+                let source_type = source_type(&s.generics);
                 let extract_impl: Item = syn::parse_quote! {
-                    impl rust_sitter::Extract<#struct_name> for #struct_name {
+                    impl #impl_generics rust_sitter::Extract<#lifetime, #struct_name #ty_generics> for #struct_name #ty_generics {
                         type LeafFn = ();
 
+                        fn extract(node: Option<rust_sitter::tree_sitter::Node>, source: #source_type, last_idx: usize, leaf_fn: Option<&Self::LeafFn>) -> Self {
+                            Self::try_extract(node, source, last_idx, leaf_fn)
+                                .unwrap_or_else(|e| panic!("Could not convert leaf: {:?}", e.reason))
+                        }
+
                         #[allow(non_snake_case)]
-                        fn extract(node: Option<rust_sitter::tree_sitter::Node>, source: &[u8], last_idx: usize, _leaf_fn: Option<&Self::LeafFn>) -> Self {
+                        fn try_extract(node: Option<rust_sitter::tree_sitter::Node>, source: #source_type, last_idx: usize, _leaf_fn: Option<&Self::LeafFn>) -> core::result::Result<Self, rust_sitter::errors::ParseError> {
                             let node = node.unwrap();
                             #(#impl_body)*
                             #extract_ident(node, source)
@@ -362,7 +802,9 @@ pub fn expand_grammar(input: ItemMod) -> ItemMod {
                     }
                 };
 
-                vec![Item::Struct(s), extract_impl]
+                let mut items = vec![Item::Struct(s), extract_impl];
+                items.extend(extra_impl);
+                items
             }
 
             o => vec![o],
@@ -383,8 +825,14 @@ pub fn expand_grammar(input: ItemMod) -> ItemMod {
         }
     });
 
+    let (_, root_ty_generics, _) = root_generics.split_for_impl();
+    let input_type: Type = match source_lifetime(&root_generics) {
+        Some(lifetime) => syn::parse_quote!(&#lifetime str),
+        None => syn::parse_quote!(&str),
+    };
+
     transformed.push(syn::parse_quote! {
-      pub fn parse(input: &str) -> core::result::Result<#root_type, Vec<rust_sitter::errors::ParseError>> {
+      pub fn parse_tree(input: &str) -> core::result::Result<rust_sitter::tree_sitter::Tree, Vec<rust_sitter::errors::ParseError>> {
           let mut parser = rust_sitter::tree_sitter::Parser::new();
           parser.set_language(language()).unwrap();
           let tree = parser.parse(input, None).unwrap();
@@ -400,12 +848,126 @@ pub fn expand_grammar(input: ItemMod) -> ItemMod {
 
               Err(errors)
           } else {
-              use rust_sitter::Extract;
-              Ok(<#root_type as rust_sitter::Extract<_>>::extract(Some(root_node), input.as_bytes(), 0, None))
+              Ok(tree)
           }
       }
   });
 
+    transformed.push(syn::parse_quote! {
+      pub fn parse #root_generics(input: #input_type) -> core::result::Result<#root_type #root_ty_generics, Vec<rust_sitter::errors::ParseError>> {
+          let tree = parse_tree(input)?;
+          use rust_sitter::Extract;
+          <#root_type as rust_sitter::Extract<_>>::try_extract(Some(tree.root_node()), input.as_bytes(), 0, None)
+              .map_err(|e| vec![e])
+      }
+  });
+
+    if !views.is_empty() {
+        let view_lifetime = source_lifetime(&root_generics)
+            .unwrap_or_else(|| Lifetime::new("'tree", Span::call_site()));
+        let root_view_ident = Ident::new(&format!("{root_type}Ref"), Span::call_site());
+        transformed.push(syn::parse_quote! {
+            pub mod views {
+                use super::*;
+
+                #(#views)*
+            }
+        });
+        transformed.push(syn::parse_quote! {
+            pub fn view<#view_lifetime>(tree: &#view_lifetime rust_sitter::tree_sitter::Tree, input: &#view_lifetime str) -> views::#root_view_ident<#view_lifetime> {
+                rust_sitter::views::View::from_node(tree.root_node(), input.as_bytes())
+            }
+        });
+    }
+
+    transformed.extend(query_schema);
+    transformed.extend(visitors);
+    transformed.extend(arbitrary_impls);
+
+    if !unparse_impls.is_empty() {
+        transformed.extend(unparse_impls);
+        transformed.push(syn::parse_quote! {
+            pub fn format #root_generics(value: &#root_type #root_ty_generics, width: usize) -> String {
+                rust_sitter::pretty::format(value, width)
+            }
+        });
+    }
+
+    let mut seen_kinds = HashSet::new();
+    let rule_name_arms = rule_names
+        .iter()
+        .filter(|(kind, _)| seen_kinds.insert(kind.clone()))
+        .map(|(kind, name)| -> Arm { syn::parse_quote!(#kind => #name) });
+    // Maps the kind of a Tree Sitter node to the Rust type or field it corresponds to.
+    transformed.push(syn::parse_quote! {
+        pub fn rule_name(kind: &'static str) -> &'static str {
+            match kind {
+                #(#rule_name_arms,)*
+                _ => kind,
+            }
+        }
+    });
+
+    transformed.push(syn::parse_quote! {
+        pub fn parse_cst(input: &str) -> rust_sitter::cst::SyntaxNode {
+            let mut parser = rust_sitter::tree_sitter::Parser::new();
+            parser.set_language(language()).unwrap();
+            let tree = parser.parse(input, None).unwrap();
+            rust_sitter::cst::SyntaxNode::new_root(&tree.root_node(), input, rule_name)
+        }
+    });
+
+    let highlights = highlight_patterns(&new_contents);
+    if !highlights.is_empty() {
+        let highlights_query = highlights_query(&new_contents);
+        let mut highlight_names: Vec<&String> = highlights.iter().map(|(_, name)| name).collect();
+        highlight_names.sort();
+        highlight_names.dedup();
+
+        transformed.push(syn::parse_quote! {
+            /// The highlights query generated from the `#[rust_sitter::highlight]` annotations.
+            pub const HIGHLIGHTS_QUERY: &str = #highlights_query;
+        });
+
+        transformed.push(syn::parse_quote! {
+            pub fn highlight(input: &str) -> Vec<(rust_sitter::highlight::Span, rust_sitter::highlight::HighlightName)> {
+                let mut parser = rust_sitter::tree_sitter::Parser::new();
+                parser.set_language(language()).unwrap();
+                let tree = parser.parse(input, None).unwrap();
+                static QUERY: std::sync::OnceLock<rust_sitter::tree_sitter::Query> = std::sync::OnceLock::new();
+                let query = QUERY.get_or_init(|| rust_sitter::tree_sitter::Query::new(language(), HIGHLIGHTS_QUERY).unwrap());
+                rust_sitter::highlight::highlight_node(query, &[#(#highlight_names),*], tree.root_node(), input.as_bytes())
+            }
+        });
+    }
+
+    if has_extras {
+        transformed.push(syn::parse_quote! {
+          pub fn extras<'a, T: rust_sitter::Extra + rust_sitter::Extract<'a, T>>(input: &'a str) -> core::result::Result<Vec<rust_sitter::Spanned<T>>, Vec<rust_sitter::errors::ParseError>> {
+              let mut parser = rust_sitter::tree_sitter::Parser::new();
+              parser.set_language(language()).unwrap();
+              let tree = parser.parse(input, None).unwrap();
+              let root_node = tree.root_node();
+
+              if root_node.has_error() {
+                  let mut errors = vec![];
+                  rust_sitter::errors::collect_parsing_errors(
+                      &root_node,
+                      input.as_bytes(),
+                      &mut errors,
+                  );
+
+                  Err(errors)
+              } else {
+                  let mut extras = vec![];
+                  rust_sitter::trivia::collect_extras(&root_node, input.as_bytes(), &mut extras)
+                      .map_err(|e| vec![e])?;
+                  Ok(extras)
+              }
+          }
+      });
+    }
+
     let mut filtered_attrs = input.attrs;
     filtered_attrs.retain(|a| !is_sitter_attr(a));
     ItemMod {
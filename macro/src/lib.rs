@@ -1,9 +1,14 @@
 use quote::ToTokens;
-use syn::{parse_macro_input, AttributeArgs, ItemMod};
+use syn::{parse_macro_input, ItemMod};
 
 mod expansion;
 use expansion::*;
 
+mod arbitrary;
+mod query;
+mod unparse;
+mod visit;
+
 #[proc_macro_attribute]
 /// Marks the top level AST node where parsing should start.
 ///
@@ -48,7 +53,14 @@ pub fn extra(
 ///
 /// If the resulting token needs to be converted into a richer type at runtime,
 /// such as a number, then the `transform` argument can be used to specify a function
-/// that will be called with the token's text.
+/// that will be called with the token's text. Without a `transform`, `String` and
+/// `Box<str>` fields hold the token's text, and other types are parsed with `FromStr`.
+/// Text that `FromStr` rejects is reported as a parsing error. Types with a lifetime
+/// parameter `'a` can also use `&'a str` fields, which borrow the text from the input.
+///
+/// In grammars with `unparse = true`, a leaf with a `pattern` is printed back with `ToString`,
+/// or with the function passed as `print`, which is called with a reference to the field. Leaves
+/// with a `transform` and `()` leaves with a `pattern` (outside of extras) must pass `print`.
 ///
 /// ## Example
 /// ```ignore
@@ -84,6 +96,77 @@ pub fn skip(
     item
 }
 
+#[proc_macro_attribute]
+/// Defines a field that collects the extras (such as comments) directly before the node,
+/// instead of matching anything itself. The field must be a `Vec` of an extra type, which can
+/// be wrapped (for example in `Spanned`) to keep more information. Passing `trailing = true`
+/// collects the extras directly after the node instead.
+///
+/// ## Example
+/// ```ignore
+/// struct Function {
+///    #[rust_sitter::trivia]
+///    doc_comments: Vec<Spanned<Comment>>,
+///    ...
+/// }
+/// ```
+pub fn trivia(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Describes how a type, enum variant or field is laid out by `format` in grammars with
+/// `unparse = true`. Takes a list of the following options:
+/// - `group` - the contents are laid out on a single line if they fit in the width
+/// - `indent` - the contents start on a new line and are indented, and the following token
+///   starts a new line
+/// - `line_break_before` / `line_break_after` - a line break before or after the contents
+/// - `no_space` - no space is written before the contents
+///
+/// Line breaks inside a group that fits on a single line are written as spaces.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::format(group)]
+/// struct Block {
+///     #[rust_sitter::leaf(text = "{")]
+///     _open: (),
+///     #[rust_sitter::format(indent)]
+///     statements: Vec<Statement>,
+///     #[rust_sitter::leaf(text = "}")]
+///     _close: (),
+/// }
+/// ```
+pub fn format(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+/// Assigns a highlight name, such as `keyword`, `number` or `comment`, to a type, enum variant or
+/// field (including the leaf inside `delimited`). The annotations are turned into a highlights
+/// query, which the tool can write to `queries/highlights.scm` and which backs the generated
+/// `highlight` function of the grammar. Leaves are highlighted as a whole, while other fields
+/// highlight the node they contain.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::highlight("keyword")]
+/// #[rust_sitter::leaf(text = "let")]
+/// _let: (),
+/// ```
+pub fn highlight(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    item
+}
+
 #[proc_macro_attribute]
 /// Defines a precedence level for a non-terminal that has no associativity.
 ///
@@ -188,20 +271,84 @@ pub fn repeat(
 /// Mark a module to be analyzed for a Rust Sitter grammar. Takes a single, unnamed argument, which
 /// specifies the name of the grammar. This name must be unique across all Rust Sitter grammars within
 /// a compilation unit.
+///
+/// A grammar can reuse the rules of another grammar by passing `extends = "<name>"`. Types from the
+/// base grammar are then brought into scope with a regular `use`, and types defined in the derived
+/// grammar override base rules of the same name.
+///
+/// Types implementing `rust_sitter::Wrapper` can wrap grammar values like `Box` does once they are
+/// registered with `wrappers = [...]`, as in `#[rust_sitter::grammar("expr", wrappers = [Node])]`.
+///
+/// Passing `unparse = true` implements `rust_sitter::unparse::Unparse` for each type, which prints
+/// a value back into source that parses to the same value, and generates a `format` function that
+/// lays out the root type according to its `#[rust_sitter::format]` annotations.
+///
+/// Passing `views = true` generates a `views` module with a lazy view over the Tree Sitter tree
+/// for each type, along with a `view` function that wraps the root of a parsed tree.
+///
+/// Passing `queries = true` generates the schema that `rust_sitter::query!` checks queries
+/// against, along with the views that matches return.
+///
+/// Passing `visit = true` generates `visit`, `visit_mut` and `fold` modules with traits that walk
+/// the values of the grammar.
+///
+/// Passing `serde = true` derives `Serialize` and `Deserialize` for each type, which requires the
+/// `serde` feature of the `rust-sitter` crate.
+///
+/// Passing `arbitrary = true` implements `arbitrary::Arbitrary` for each type, which requires the
+/// `arbitrary` feature of the `rust-sitter` crate.
+///
+/// ## Example
+/// ```ignore
+/// #[rust_sitter::grammar("query", extends = "expr")]
+/// mod grammar {
+///     use super::expr::grammar::Expression;
+///     ...
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn grammar(
     attr: proc_macro::TokenStream,
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-    let attrs: AttributeArgs = parse_macro_input!(attr);
+    let attrs = proc_macro2::TokenStream::from(attr);
     let module: ItemMod = parse_macro_input!(input);
     let expanded: ItemMod = expand_grammar(syn::parse_quote! {
-        #[rust_sitter::grammar[#(#attrs),*]]
+        #[rust_sitter::grammar[#attrs]]
         #module
     });
     proc_macro::TokenStream::from(expanded.to_token_stream())
 }
 
+/// Builds a Tree Sitter query written with the Rust names of a grammar. Takes the path to the
+/// grammar module and the query, where nodes are written as the type or variant they correspond
+/// to (such as `(Expression::Sub)`) and fields by their Rust name (such as `0:` or `value:`).
+/// The names are checked against the grammar at compile time, and the query itself is checked
+/// when it is created, which returns a `Result`.
+///
+/// Each match is returned as a struct with a field per capture, which holds the view of the
+/// captured node (or the node itself for `(_)` and string patterns). Captures under `*` or `+`
+/// are collected into a `Vec`, and captures that may be missing from a match are an `Option`.
+///
+/// ## Example
+/// ```ignore
+/// let query = rust_sitter::query!(
+///     grammar,
+///     "(Expression::Sub 0: (Expression::Number) @left 2: (_) @right)"
+/// )
+/// .unwrap();
+/// for m in query.matches(tree.root_node(), input) {
+///     println!("{:?} - {}", m.left.extract(), m.right.kind());
+/// }
+/// ```
+#[proc_macro]
+pub fn query(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as query::QueryInput);
+    proc_macro::TokenStream::from(
+        query::expand_query(input).unwrap_or_else(|e| e.to_compile_error()),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs::File;
@@ -489,4 +636,428 @@ mod tests {
             .to_string()
         ));
     }
+
+    #[test]
+    fn leaf_without_transform() {
+        insta::assert_display_snapshot!(rustfmt_code(
+            &expand_grammar(parse_quote! {
+                #[rust_sitter::grammar("test")]
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub struct Pair {
+                        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                        key: String,
+                        #[rust_sitter::leaf(pattern = r"\d+")]
+                        value: Option<u8>,
+                        #[rust_sitter::leaf(pattern = r"#[a-z]+")]
+                        tag: Box<str>,
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn borrowed_leaves() {
+        insta::assert_display_snapshot!(rustfmt_code(
+            &expand_grammar(parse_quote! {
+                #[rust_sitter::grammar("test")]
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub struct Call<'a> {
+                        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                        name: &'a str,
+                        #[rust_sitter::repeat]
+                        args: Vec<Arg<'a>>,
+                    }
+
+                    pub struct Arg<'a> {
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.len())]
+                        width: usize,
+                        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                        label: Option<&'a str>,
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn custom_wrappers() {
+        insta::assert_display_snapshot!(rustfmt_code(
+            &expand_grammar(parse_quote! {
+                #[rust_sitter::grammar("test", wrappers = [Node])]
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub struct NumberList {
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                        numbers: Vec<Node<i32>>,
+                        tail: Option<std::rc::Rc<Tail>>,
+                    }
+
+                    pub struct Tail {
+                        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                        name: Node<String>,
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn struct_trivia() {
+        insta::assert_display_snapshot!(rustfmt_code(
+            &expand_grammar(parse_quote! {
+                #[rust_sitter::grammar("test")]
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub struct Declaration {
+                        #[rust_sitter::trivia]
+                        doc_comments: Vec<Spanned<Comment>>,
+                        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                        name: String,
+                        #[rust_sitter::trivia(trailing = true)]
+                        trailing_comments: Vec<Comment>,
+                    }
+
+                    #[rust_sitter::extra]
+                    pub struct Comment {
+                        #[rust_sitter::leaf(pattern = r"//[^\n]*")]
+                        text: String,
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn grammar_unparse() {
+        insta::assert_display_snapshot!(rustfmt_code(
+            &expand_grammar(parse_quote! {
+                #[rust_sitter::grammar("test", unparse = true)]
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub enum Expression {
+                        Number(
+                            #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap(), print = |v: &i32| v.to_string())]
+                            i32
+                        ),
+                        Call(
+                            #[rust_sitter::leaf(pattern = r"[a-z]+", print = |v: &Name| v.0.clone(), transform = |v| Name(v.to_string()))]
+                            Name,
+                            #[rust_sitter::leaf(text = "(")]
+                            (),
+                            #[rust_sitter::delimited(
+                                #[rust_sitter::leaf(text = ",")]
+                                ()
+                            )]
+                            Vec<Box<Expression>>,
+                            #[rust_sitter::leaf(text = ")")]
+                            (),
+                        ),
+                        Negate(
+                            #[rust_sitter::leaf(text = "-")]
+                            (),
+                            Option<Box<Expression>>,
+                        ),
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn grammar_unparse_errors() {
+        let expanded = expand_grammar(parse_quote! {
+            #[rust_sitter::grammar("test", unparse = true)]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Statement {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    _keyword: (),
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    value: i32,
+                }
+
+                #[rust_sitter::extra]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s")]
+                    _whitespace: (),
+                }
+            }
+        })
+        .to_token_stream()
+        .to_string();
+
+        let errors: Vec<&str> = expanded
+            .split("compile_error !")
+            .skip(1)
+            .map(|e| e.split('"').nth(1).unwrap())
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "A `()` leaf with a `pattern` cannot be unparsed, since its text is not kept. Match it with `text` instead, or pass a `print` function",
+                "A leaf with a `transform` must pass a `print` function to be unparsed, since `ToString` does not necessarily undo the transform",
+            ]
+        );
+    }
+
+    #[test]
+    fn format_annotations() {
+        insta::assert_display_snapshot!(rustfmt_code(
+            &expand_grammar(parse_quote! {
+                #[rust_sitter::grammar("test", unparse = true)]
+                mod grammar {
+                    #[rust_sitter::language]
+                    #[rust_sitter::format(group)]
+                    pub struct Block {
+                        #[rust_sitter::leaf(text = "{")]
+                        _open: (),
+                        #[rust_sitter::format(indent)]
+                        statements: Vec<Statement>,
+                        #[rust_sitter::leaf(text = "}")]
+                        _close: (),
+                    }
+
+                    pub struct Statement {
+                        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                        name: String,
+                        #[rust_sitter::format(no_space, line_break_after)]
+                        #[rust_sitter::leaf(text = ";")]
+                        _semicolon: (),
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn grammar_serde() {
+        insta::assert_display_snapshot!(rustfmt_code(
+            &expand_grammar(parse_quote! {
+                #[rust_sitter::grammar("test", serde = true)]
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub struct Assignment<'a> {
+                        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                        name: &'a str,
+                        #[rust_sitter::leaf(text = "=")]
+                        _eq: (),
+                        value: Spanned<Value>,
+                    }
+
+                    pub enum Value {
+                        Number(#[rust_sitter::leaf(pattern = r"\d+")] u32),
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn grammar_views() {
+        insta::assert_display_snapshot!(rustfmt_code(
+            &expand_grammar(parse_quote! {
+                #[rust_sitter::grammar("test", views = true)]
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub struct Assignment {
+                        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                        name: String,
+                        #[rust_sitter::leaf(text = "=")]
+                        _eq: (),
+                        values: Vec<Value>,
+                    }
+
+                    pub enum Value {
+                        Number(#[rust_sitter::leaf(pattern = r"\d+")] u32),
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn grammar_queries() {
+        insta::assert_display_snapshot!(rustfmt_code(
+            &expand_grammar(parse_quote! {
+                #[rust_sitter::grammar("test", queries = true)]
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub struct Program {
+                        statements: Vec<Statement>,
+                    }
+
+                    pub enum Statement {
+                        Expression(Expression),
+                        Block(Box<Program>),
+                    }
+
+                    pub enum Expression {
+                        Number(#[rust_sitter::leaf(pattern = r"\d+")] u32),
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn grammar_visit() {
+        insta::assert_display_snapshot!(rustfmt_code(
+            &expand_grammar(parse_quote! {
+                #[rust_sitter::grammar("test", visit = true)]
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub enum Expression {
+                        Number(#[rust_sitter::leaf(pattern = r"\d+")] u32),
+                        Neg(#[rust_sitter::leaf(text = "-")] (), Box<Expression>),
+                        List(Vec<Spanned<Expression>>, Option<Box<Expression>>),
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn grammar_highlights() {
+        insta::assert_display_snapshot!(rustfmt_code(
+            &expand_grammar(parse_quote! {
+                #[rust_sitter::grammar("test")]
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub struct Assignment {
+                        #[rust_sitter::highlight("variable")]
+                        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                        name: String,
+                        #[rust_sitter::highlight("operator")]
+                        #[rust_sitter::leaf(text = "=")]
+                        _eq: (),
+                        value: Value,
+                    }
+
+                    pub enum Value {
+                        #[rust_sitter::highlight("number")]
+                        Number(#[rust_sitter::leaf(pattern = r"\d+")] u32),
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn typed_query() {
+        let query = crate::query::expand_query(parse_quote!(
+            grammar,
+            "(Expression::Sub 0: (Expression (Expression::Number) @left) !extra 2: (_)* @rest)"
+        ))
+        .unwrap();
+        insta::assert_display_snapshot!(rustfmt_code(&format!("fn query() {{ {query} }}")));
+    }
+
+    #[test]
+    fn typed_query_errors() {
+        let error = |query: &str| {
+            crate::query::expand_query(syn::parse_str(&format!("grammar, {query:?}")).unwrap())
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            error("(_ value: (Number))"),
+            "The field `value` must be inside a node with a grammar type"
+        );
+        assert_eq!(
+            error("[(Number) @n (Word) @n]"),
+            "The capture `@n` is used for nodes of different types"
+        );
+        assert_eq!(error("(Number"), "Unexpected end of query");
+    }
+
+    #[test]
+    fn grammar_arbitrary() {
+        insta::assert_display_snapshot!(rustfmt_code(
+            &expand_grammar(parse_quote! {
+                #[rust_sitter::grammar("test", arbitrary = true)]
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub enum Expression {
+                        Number(
+                            #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                            i32
+                        ),
+                        Variable(
+                            #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                            String
+                        ),
+                        List(
+                            #[rust_sitter::leaf(text = "[")]
+                            (),
+                            #[rust_sitter::repeat(non_empty = true)]
+                            Vec<Box<Expression>>,
+                            #[rust_sitter::leaf(text = "]")]
+                            (),
+                        ),
+                        Negate(
+                            #[rust_sitter::leaf(text = "-")]
+                            (),
+                            Option<Spanned<Box<Expression>>>,
+                        ),
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn grammar_arbitrary_recursion() {
+        insta::assert_display_snapshot!(rustfmt_code(
+            &expand_grammar(parse_quote! {
+                #[rust_sitter::grammar("test", arbitrary = true)]
+                mod grammar {
+                    #[rust_sitter::language]
+                    pub enum E {
+                        Paren(Box<E>),
+                        Pair(A, B),
+                    }
+
+                    pub struct A {
+                        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                        name: String,
+                    }
+
+                    pub struct B {
+                        #[rust_sitter::leaf(pattern = r"\d+")]
+                        value: u32,
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
 }
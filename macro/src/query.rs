@@ -0,0 +1,637 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::{parse::Parse, *};
+
+use rust_sitter_common::root_rule_is_reachable;
+
+use crate::expansion::source_lifetime;
+
+/// The name of the constant for a field in the query schema, which is `_0`, `_1`, ... for
+/// unnamed fields.
+fn field_const(name: &str) -> Ident {
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        Ident::new(&format!("_{name}"), Span::call_site())
+    } else {
+        Ident::new(name, Span::call_site())
+    }
+}
+
+fn gen_schema_fields(fields: &Fields) -> Vec<TokenStream> {
+    fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| {
+            !field.attrs.iter().any(|attr| {
+                attr.path == syn::parse_quote!(rust_sitter::skip)
+                    || attr.path == syn::parse_quote!(rust_sitter::trivia)
+            })
+        })
+        .map(|(i, field)| {
+            let name = field
+                .ident
+                .as_ref()
+                .map(|v| v.to_string())
+                .unwrap_or(format!("{i}"));
+            let ident = field_const(&name);
+            quote!(pub const #ident: &str = #name;)
+        })
+        .collect()
+}
+
+/// Generates the module describing a node kind, with the kind itself, the names of the fields
+/// its node can have and how captures of the node are converted into a view of `ident`.
+fn gen_schema_kind(
+    module: &str,
+    kind: &str,
+    ident: &Ident,
+    generics: &Generics,
+    fields: Vec<TokenStream>,
+    from_parent: bool,
+) -> TokenStream {
+    let module = Ident::new(module, Span::call_site());
+    let lifetime =
+        source_lifetime(generics).unwrap_or_else(|| Lifetime::new("'tree", Span::call_site()));
+    let view_ident = Ident::new(&format!("{ident}Ref"), Span::call_site());
+    let node = if from_parent {
+        quote!(node.parent().unwrap())
+    } else {
+        quote!(node)
+    };
+
+    quote! {
+        pub mod #module {
+            pub const KIND: &str = #kind;
+
+            pub struct Capture;
+
+            impl<#lifetime> rust_sitter::query::Capture<#lifetime> for Capture {
+                type Output = super::super::views::#view_ident<#lifetime>;
+
+                fn from_node(node: rust_sitter::tree_sitter::Node<#lifetime>, source: &#lifetime [u8]) -> Self::Output {
+                    rust_sitter::views::View::from_node(#node, source)
+                }
+            }
+
+            pub mod fields {
+                #(#fields)*
+            }
+        }
+    }
+}
+
+/// Generates the `query_schema` module, which lists the node kinds and fields of the grammar by
+/// their Rust names so that `rust_sitter::query!` can check queries against them.
+pub fn gen_query_schema(items: &[Item], root_type: &Ident) -> Item {
+    // When the root rule is only kept as `source_file`, the root type's name refers to it too.
+    let root_kind = if root_rule_is_reachable(items, &root_type.to_string()) {
+        root_type.to_string()
+    } else {
+        "source_file".to_string()
+    };
+
+    let mut kinds = vec![];
+    for item in items {
+        match item {
+            Item::Struct(s) => {
+                let fields = gen_schema_fields(&s.fields);
+                let ident = s.ident.to_string();
+                let kind = if s.ident == *root_type {
+                    kinds.push(gen_schema_kind(
+                        "source_file",
+                        "source_file",
+                        &s.ident,
+                        &s.generics,
+                        fields.clone(),
+                        false,
+                    ));
+                    &root_kind
+                } else {
+                    &ident
+                };
+
+                kinds.push(gen_schema_kind(
+                    &ident,
+                    kind,
+                    &s.ident,
+                    &s.generics,
+                    fields,
+                    false,
+                ));
+            }
+            Item::Enum(e) => {
+                let ident = e.ident.to_string();
+                let kind = if e.ident == *root_type {
+                    kinds.push(gen_schema_kind(
+                        "source_file",
+                        "source_file",
+                        &e.ident,
+                        &e.generics,
+                        vec![],
+                        false,
+                    ));
+                    &root_kind
+                } else {
+                    &ident
+                };
+
+                kinds.push(gen_schema_kind(
+                    &ident,
+                    kind,
+                    &e.ident,
+                    &e.generics,
+                    vec![],
+                    false,
+                ));
+
+                for v in &e.variants {
+                    let kind = format!("{}_{}", e.ident, v.ident);
+                    kinds.push(gen_schema_kind(
+                        &kind,
+                        &kind,
+                        &e.ident,
+                        &e.generics,
+                        gen_schema_fields(&v.fields),
+                        true,
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    syn::parse_quote! {
+        #[doc(hidden)]
+        #[allow(non_snake_case, non_upper_case_globals, dead_code)]
+        pub mod query_schema {
+            #(#kinds)*
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Anchor,
+    Quantifier(char),
+    String(String),
+    Capture(String),
+    Predicate(String),
+    Field(String),
+    NegatedField(String),
+    Name(String),
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn tokenize(query: &str) -> core::result::Result<Vec<Token>, String> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    let read_name = |i: &mut usize, allow_path: bool| {
+        let mut name = String::new();
+        while *i < chars.len() {
+            if is_name_char(chars[*i]) {
+                name.push(chars[*i]);
+                *i += 1;
+            } else if allow_path
+                && chars[*i] == ':'
+                && chars.get(*i + 1) == Some(&':')
+                && chars.get(*i + 2).is_some_and(|c| is_name_char(*c))
+            {
+                name.push_str("::");
+                *i += 2;
+            } else {
+                break;
+            }
+        }
+        name
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            _ if c.is_whitespace() => i += 1,
+            ';' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '(' | ')' | '[' | ']' | '.' => {
+                tokens.push(match c {
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    '[' => Token::LBracket,
+                    ']' => Token::RBracket,
+                    _ => Token::Anchor,
+                });
+                i += 1;
+            }
+            '*' | '+' | '?' => {
+                tokens.push(Token::Quantifier(c));
+                i += 1;
+            }
+            '"' => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    return Err("Unterminated string in query".to_string());
+                }
+                i += 1;
+                tokens.push(Token::String(chars[start..i].iter().collect()));
+            }
+            '@' => {
+                i += 1;
+                let name = read_name(&mut i, false);
+                if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+                    return Err("Expected an identifier after `@`".to_string());
+                }
+                tokens.push(Token::Capture(name));
+            }
+            '#' => {
+                let start = i;
+                while i < chars.len() && !chars[i].is_whitespace() && !"()".contains(chars[i]) {
+                    i += 1;
+                }
+                tokens.push(Token::Predicate(chars[start..i].iter().collect()));
+            }
+            '!' => {
+                i += 1;
+                let name = read_name(&mut i, false);
+                if name.is_empty() {
+                    return Err("Expected a field name after `!`".to_string());
+                }
+                tokens.push(Token::NegatedField(name));
+            }
+            _ if is_name_char(c) => {
+                let name = read_name(&mut i, true);
+                if chars.get(i) == Some(&':') {
+                    i += 1;
+                    tokens.push(Token::Field(name));
+                } else {
+                    tokens.push(Token::Name(name));
+                }
+            }
+            _ => return Err(format!("Unexpected character `{c}` in query")),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// How many times a capture can appear in a single match.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Cardinality {
+    One,
+    Optional,
+    Many,
+}
+
+/// The part of the query text that comes before a piece, which is either plain text or the
+/// name of a node kind or field looked up in the query schema.
+enum Piece {
+    Text(String),
+    Schema(TokenStream),
+}
+
+struct QueryCapture {
+    name: String,
+    capture: TokenStream,
+    cardinality: Cardinality,
+}
+
+/// Translates a query written with Rust names into one over the node kinds of the grammar.
+struct Translator {
+    tokens: Vec<Token>,
+    position: usize,
+    schema: TokenStream,
+    span: Span,
+    pieces: Vec<Piece>,
+    captures: Vec<QueryCapture>,
+}
+
+impl Translator {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> core::result::Result<&Token, String> {
+        self.position += 1;
+        self.tokens
+            .get(self.position - 1)
+            .ok_or_else(|| "Unexpected end of query".to_string())
+    }
+
+    fn text(&mut self, text: &str) {
+        self.pieces.push(Piece::Text(text.to_string()));
+    }
+
+    /// The path to the module of a node kind in the schema, such as `Expression_Sub` for
+    /// `Expression::Sub`.
+    fn kind_module(&self, name: &str) -> TokenStream {
+        let schema = &self.schema;
+        let module = Ident::new(&name.replace("::", "_"), self.span);
+        quote_spanned!(self.span=> #schema::#module)
+    }
+
+    /// Translates patterns until the closing token, for the children of a node whose schema
+    /// module is `parent` (if it has a grammar type).
+    fn patterns(
+        &mut self,
+        end: Token,
+        parent: Option<&TokenStream>,
+        cardinality: Cardinality,
+    ) -> core::result::Result<(), String> {
+        loop {
+            match self.peek() {
+                Some(t) if *t == end => {
+                    self.position += 1;
+                    return Ok(());
+                }
+                _ => self.pattern(parent, cardinality)?,
+            }
+        }
+    }
+
+    fn pattern(
+        &mut self,
+        parent: Option<&TokenStream>,
+        cardinality: Cardinality,
+    ) -> core::result::Result<(), String> {
+        let mut capture = quote!(rust_sitter::query::AnyNode);
+        match self.next()?.clone() {
+            Token::NegatedField(name) => {
+                let parent = parent.ok_or_else(|| {
+                    format!("The field `{name}` must be inside a node with a grammar type")
+                })?;
+                let field = Ident::new(&field_const(&name).to_string(), self.span);
+                self.text(" !");
+                self.pieces.push(Piece::Schema(
+                    quote_spanned!(self.span=> #parent::fields::#field),
+                ));
+                return Ok(());
+            }
+            Token::Field(name) => {
+                let parent = parent.ok_or_else(|| {
+                    format!("The field `{name}` must be inside a node with a grammar type")
+                })?;
+                let field = Ident::new(&field_const(&name).to_string(), self.span);
+                self.text(" ");
+                self.pieces.push(Piece::Schema(
+                    quote_spanned!(self.span=> #parent::fields::#field),
+                ));
+                self.text(":");
+                return self.pattern(Some(parent), cardinality);
+            }
+            Token::Anchor => {
+                self.text(" .");
+                return Ok(());
+            }
+            Token::String(s) => self.text(&format!(" {s}")),
+            Token::Name(name) if name == "_" => self.text(" _"),
+            Token::Name(name) => {
+                return Err(format!(
+                    "Expected `({name})` for a node, or `{name}:` for a field"
+                ))
+            }
+            Token::LBracket => {
+                self.text(" [");
+                self.patterns(
+                    Token::RBracket,
+                    parent,
+                    Cardinality::Optional.max(cardinality),
+                )?;
+                self.text("]");
+            }
+            Token::LParen => match self.peek() {
+                Some(Token::Predicate(_)) => return self.predicate(),
+                Some(Token::Name(name)) => {
+                    let name = name.clone();
+                    self.position += 1;
+                    self.text(" (");
+                    if name == "_" || name == "ERROR" || name == "MISSING" {
+                        self.text(&name);
+                        self.patterns(Token::RParen, None, cardinality)?;
+                    } else {
+                        let module = self.kind_module(&name);
+                        self.pieces
+                            .push(Piece::Schema(quote_spanned!(self.span=> #module::KIND)));
+                        self.patterns(Token::RParen, Some(&module), cardinality)?;
+                        capture = quote_spanned!(self.span=> #module::Capture);
+                    }
+                    self.text(")");
+                }
+                _ => {
+                    self.text(" (");
+                    self.patterns(Token::RParen, parent, cardinality)?;
+                    self.text(")");
+                }
+            },
+            other => return Err(format!("Unexpected {} in query", other.describe())),
+        }
+
+        let cardinality = match self.peek() {
+            Some(Token::Quantifier(q)) => {
+                let q = *q;
+                self.position += 1;
+                self.text(&q.to_string());
+                let own = if q == '?' {
+                    Cardinality::Optional
+                } else {
+                    Cardinality::Many
+                };
+                own.max(cardinality)
+            }
+            _ => cardinality,
+        };
+
+        while let Some(Token::Capture(name)) = self.peek() {
+            let name = name.clone();
+            self.position += 1;
+            self.text(&format!(" @{name}"));
+            self.capture(name, capture.clone(), cardinality)?;
+        }
+
+        Ok(())
+    }
+
+    fn predicate(&mut self) -> core::result::Result<(), String> {
+        self.text(" (");
+        loop {
+            match self.next()?.clone() {
+                Token::RParen => break,
+                Token::Predicate(p) => self.text(&p),
+                Token::Capture(name) => {
+                    if !self.captures.iter().any(|c| c.name == name) {
+                        return Err(format!("The capture `@{name}` is not defined"));
+                    }
+                    self.text(&format!(" @{name}"));
+                }
+                Token::String(s) | Token::Name(s) => self.text(&format!(" {s}")),
+                other => return Err(format!("Unexpected {} in predicate", other.describe())),
+            }
+        }
+        self.text(")");
+        Ok(())
+    }
+
+    fn capture(
+        &mut self,
+        name: String,
+        capture: TokenStream,
+        cardinality: Cardinality,
+    ) -> core::result::Result<(), String> {
+        if name == "_tree" {
+            return Err("The capture name `@_tree` is reserved".to_string());
+        }
+
+        match self.captures.iter_mut().find(|c| c.name == name) {
+            Some(existing) => {
+                if existing.capture.to_string() != capture.to_string() {
+                    return Err(format!(
+                        "The capture `@{name}` is used for nodes of different types"
+                    ));
+                }
+                existing.cardinality = existing.cardinality.max(cardinality);
+            }
+            None => self.captures.push(QueryCapture {
+                name,
+                capture,
+                cardinality,
+            }),
+        }
+
+        Ok(())
+    }
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::LParen => "`(`".to_string(),
+            Token::RParen => "`)`".to_string(),
+            Token::LBracket => "`[`".to_string(),
+            Token::RBracket => "`]`".to_string(),
+            Token::Anchor => "`.`".to_string(),
+            Token::Quantifier(q) => format!("`{q}`"),
+            Token::String(s) => format!("string {s}"),
+            Token::Capture(s) => format!("capture `@{s}`"),
+            Token::Predicate(s) => format!("predicate `{s}`"),
+            Token::Field(s) => format!("field `{s}:`"),
+            Token::NegatedField(s) => format!("negated field `!{s}`"),
+            Token::Name(s) => format!("`{s}`"),
+        }
+    }
+}
+
+/// The arguments of `rust_sitter::query!`: the path to a grammar module and the query.
+pub struct QueryInput {
+    grammar: Path,
+    query: LitStr,
+}
+
+impl Parse for QueryInput {
+    fn parse(input: parse::ParseStream) -> Result<Self> {
+        let grammar = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let query = input.parse()?;
+        input.parse::<Option<Token![,]>>()?;
+        Ok(QueryInput { grammar, query })
+    }
+}
+
+/// Expands `rust_sitter::query!` into an expression that builds the query over the node kinds
+/// of the grammar, along with the struct its matches are converted into.
+pub fn expand_query(input: QueryInput) -> Result<TokenStream> {
+    let grammar = &input.grammar;
+    let span = input.query.span();
+    let mut translator = Translator {
+        tokens: tokenize(&input.query.value()).map_err(|e| Error::new(span, e))?,
+        position: 0,
+        schema: quote_spanned!(span=> #grammar::query_schema),
+        span,
+        pieces: vec![],
+        captures: vec![],
+    };
+    let mut pattern_count = 0;
+    while translator.peek().is_some() {
+        translator
+            .pattern(None, Cardinality::One)
+            .map_err(|e| Error::new(span, e))?;
+        pattern_count += 1;
+    }
+
+    // Each match comes from a single top-level pattern, so with several patterns any capture
+    // can be missing from a match.
+    if pattern_count > 1 {
+        for capture in translator.captures.iter_mut() {
+            capture.cardinality = capture.cardinality.max(Cardinality::Optional);
+        }
+    }
+    let captures = translator.captures;
+    let pieces = translator.pieces.iter().map(|p| match p {
+        Piece::Text(text) => quote!(#text),
+        Piece::Schema(path) => path.clone(),
+    });
+
+    let names = captures.iter().map(|c| &c.name);
+    let (fields, values): (Vec<TokenStream>, Vec<TokenStream>) = captures
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let ident = Ident::new(&c.name, span);
+            let capture = &c.capture;
+            let output = quote!(<#capture as rust_sitter::query::Capture<'tree>>::Output);
+            let (ty, value) = match c.cardinality {
+                Cardinality::One => (
+                    output,
+                    quote!(rust_sitter::query::one::<#capture>(&nodes[#i], source)),
+                ),
+                Cardinality::Optional => (
+                    quote!(Option<#output>),
+                    quote!(rust_sitter::query::optional::<#capture>(&nodes[#i], source)),
+                ),
+                Cardinality::Many => (
+                    quote!(Vec<#output>),
+                    quote!(rust_sitter::query::many::<#capture>(&nodes[#i], source)),
+                ),
+            };
+            (quote!(#ident: #ty), quote!(#ident: #value))
+        })
+        .unzip();
+
+    Ok(quote! {
+        {
+            struct Captures<'tree> {
+                #(#fields,)*
+                _tree: core::marker::PhantomData<&'tree ()>,
+            }
+
+            struct Matches;
+
+            impl<'tree> rust_sitter::query::Captures<'tree> for Matches {
+                type Output = Captures<'tree>;
+                const NAMES: &'static [&'static str] = &[#(#names),*];
+
+                fn from_nodes(nodes: &[Vec<rust_sitter::tree_sitter::Node<'tree>>], source: &'tree [u8]) -> Captures<'tree> {
+                    Captures {
+                        #(#values,)*
+                        _tree: core::marker::PhantomData,
+                    }
+                }
+            }
+
+            rust_sitter::query::Query::<Matches>::new(#grammar::language(), &[#(#pieces),*].concat())
+        }
+    })
+}
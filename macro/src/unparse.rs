@@ -0,0 +1,369 @@
+use std::collections::HashSet;
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use rust_sitter_common::*;
+use syn::{parse::Parse, punctuated::Punctuated, *};
+
+/// How the text of a leaf is recovered from its value.
+enum LeafPrinter {
+    /// The leaf always matches this text.
+    Text(Expr),
+    /// The leaf is printed with a user-provided closure.
+    Print(Expr),
+    /// The leaf is printed with `ToString`, which must undo its `transform` (if any).
+    /// `checked` is false for extras, whose `()` leaves are dropped when unparsing.
+    Display {
+        attr: Attribute,
+        transformed: bool,
+        checked: bool,
+    },
+}
+
+/// The options given by a `#[rust_sitter::format(...)]` annotation.
+fn format_options(attrs: &[Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path == syn::parse_quote!(rust_sitter::format))
+        .flat_map(|attr| {
+            attr.parse_args_with(Punctuated::<Ident, Token![,]>::parse_terminated)
+                .expect("Expected a list of layout options for `format`")
+        })
+        .map(|option| {
+            let option = option.to_string();
+            if ![
+                "group",
+                "indent",
+                "line_break_before",
+                "line_break_after",
+                "no_space",
+            ]
+            .contains(&option.as_str())
+            {
+                panic!("Unknown layout option `{option}` for `format`");
+            }
+
+            option
+        })
+        .collect()
+}
+
+/// Surrounds the statements unparsing a type, field or delimiter with the layout calls for its
+/// `#[rust_sitter::format(...)]` annotations.
+fn gen_layout(attrs: &[Attribute], body: TokenStream) -> TokenStream {
+    let options = format_options(attrs);
+    let has = |name: &str| options.iter().any(|o| o == name);
+
+    let mut out = body;
+    if has("group") {
+        out = quote!(out.begin_group(); #out out.end(););
+    }
+    if has("indent") {
+        out = quote!(out.begin_indent(); #out out.end_indent(););
+    }
+    if has("no_space") {
+        out = quote!(out.no_space(); #out);
+    }
+    if has("line_break_before") {
+        out = quote!(out.soft_line_break(); #out);
+    }
+    if has("line_break_after") {
+        out = quote!(#out out.soft_line_break(););
+    }
+
+    out
+}
+
+fn leaf_printer(attrs: &[Attribute], checked: bool) -> Option<LeafPrinter> {
+    let leaf_attr = attrs
+        .iter()
+        .find(|attr| attr.path == syn::parse_quote!(rust_sitter::leaf))?;
+
+    let params = leaf_attr
+        .parse_args_with(Punctuated::<NameValueExpr, Token![,]>::parse_terminated)
+        .ok()?;
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|param| param.path == name)
+            .map(|p| p.expr.clone())
+    };
+
+    Some(if let Some(text) = param("text") {
+        LeafPrinter::Text(text)
+    } else if let Some(print) = param("print") {
+        LeafPrinter::Print(print)
+    } else {
+        LeafPrinter::Display {
+            attr: leaf_attr.clone(),
+            transformed: param("transform").is_some(),
+            checked,
+        }
+    })
+}
+
+/// Generates the statements that unparse the value referred to by `expr` of type `ty`, looking
+/// through `Vec`, `Option` and wrapper types down to the leaf or grammar type.
+fn gen_unparse_value(
+    ty: &Type,
+    expr: TokenStream,
+    leaf: Option<&LeafPrinter>,
+    delimiter: Option<&(LeafPrinter, Vec<Attribute>)>,
+    wrappers: &HashSet<&str>,
+    depth: usize,
+) -> TokenStream {
+    let item = Ident::new(&format!("it{depth}"), Span::call_site());
+    let (inner_type_vec, is_vec) = try_extract_inner_type(ty, "Vec", &HashSet::new());
+    let (inner_type_option, is_option) = try_extract_inner_type(ty, "Option", &HashSet::new());
+    let (inner_type_wrapped, is_wrapped) = if let Type::Path(p) = ty {
+        let ident = p.path.segments.last().unwrap().ident.to_string();
+        if wrappers.contains(ident.as_str()) {
+            (filter_inner_type(ty, &[ident.as_str()].into()), true)
+        } else {
+            (ty.clone(), false)
+        }
+    } else {
+        (ty.clone(), false)
+    };
+
+    if is_vec {
+        let element = gen_unparse_value(
+            &inner_type_vec,
+            quote!(#item),
+            leaf,
+            None,
+            wrappers,
+            depth + 1,
+        );
+        match delimiter {
+            Some((d, attrs)) => {
+                let delimiter = gen_layout(
+                    attrs,
+                    gen_unparse_leaf(&syn::parse_quote!(()), quote!(&()), d),
+                );
+                quote! {
+                    for (i, #item) in (#expr).iter().enumerate() {
+                        if i > 0 {
+                            #delimiter
+                        }
+                        #element
+                    }
+                }
+            }
+            None => quote! {
+                for #item in (#expr).iter() {
+                    #element
+                }
+            },
+        }
+    } else if is_option {
+        let inner = gen_unparse_value(
+            &inner_type_option,
+            quote!(#item),
+            leaf,
+            None,
+            wrappers,
+            depth + 1,
+        );
+        quote! {
+            if let Some(#item) = #expr {
+                #inner
+            }
+        }
+    } else if is_wrapped {
+        gen_unparse_value(
+            &inner_type_wrapped,
+            quote!(rust_sitter::Wrapper::inner(#expr)),
+            leaf,
+            None,
+            wrappers,
+            depth + 1,
+        )
+    } else if let Some(leaf) = leaf {
+        gen_unparse_leaf(ty, expr, leaf)
+    } else {
+        quote!(rust_sitter::unparse::Unparse::unparse(#expr, out);)
+    }
+}
+
+fn gen_unparse_leaf(ty: &Type, expr: TokenStream, leaf: &LeafPrinter) -> TokenStream {
+    match leaf {
+        LeafPrinter::Text(text) => quote!(out.token(#text);),
+        LeafPrinter::Print(print) => quote!(out.token(&(#print)(#expr));),
+        LeafPrinter::Display {
+            attr,
+            transformed,
+            checked,
+        } => {
+            if matches!(ty, Type::Tuple(t) if t.elems.is_empty()) {
+                if *checked {
+                    syn::Error::new_spanned(
+                        attr,
+                        "A `()` leaf with a `pattern` cannot be unparsed, since its text is not kept. Match it with `text` instead, or pass a `print` function",
+                    )
+                    .to_compile_error()
+                } else {
+                    quote!()
+                }
+            } else if *transformed && *checked {
+                syn::Error::new_spanned(
+                    attr,
+                    "A leaf with a `transform` must pass a `print` function to be unparsed, since `ToString` does not necessarily undo the transform",
+                )
+                .to_compile_error()
+            } else {
+                quote!(out.token(&ToString::to_string(#expr));)
+            }
+        }
+    }
+}
+
+fn field_binding(field: &Field, i: usize) -> Ident {
+    field
+        .ident
+        .clone()
+        .unwrap_or_else(|| Ident::new(&format!("_{i}"), Span::call_site()))
+}
+
+/// Generates the statements that unparse the fields (bound by reference) of a struct or variant.
+/// Skipped fields and trailing trivia are left out, since their contents are not in the source
+/// or are printed by the following node. Leading trivia is followed by a line break. Leaves that
+/// cannot be printed back are reported as errors, except in extras (`extra`), where dropping the
+/// text of `()` leaves such as whitespace is intended.
+pub fn gen_unparse_fields(
+    fields: &Fields,
+    wrappers: &HashSet<&str>,
+    extra: bool,
+) -> Vec<TokenStream> {
+    fields
+        .iter()
+        .enumerate()
+        .filter_map(|(i, field)| {
+            let binding = field_binding(field, i);
+
+            if field
+                .attrs
+                .iter()
+                .any(|attr| attr.path == syn::parse_quote!(rust_sitter::skip))
+            {
+                return None;
+            }
+
+            if let Some(trivia_attr) = field
+                .attrs
+                .iter()
+                .find(|attr| attr.path == syn::parse_quote!(rust_sitter::trivia))
+            {
+                if !trivia_attr.tokens.is_empty() {
+                    return None;
+                }
+
+                let (element_type, _) = try_extract_inner_type(&field.ty, "Vec", &HashSet::new());
+                let element =
+                    gen_unparse_value(&element_type, quote!(it0), None, None, wrappers, 1);
+                return Some(gen_layout(
+                    &field.attrs,
+                    quote! {
+                    for it0 in #binding.iter() {
+                        #element
+                        out.line_break();
+                    }
+                    },
+                ));
+            }
+
+            let delimiter = field
+                .attrs
+                .iter()
+                .find(|attr| attr.path == syn::parse_quote!(rust_sitter::delimited))
+                .and_then(|a| a.parse_args_with(FieldThenParams::parse).ok())
+                .and_then(|p| Some((leaf_printer(&p.field.attrs, true)?, p.field.attrs)));
+
+            Some(gen_layout(
+                &field.attrs,
+                gen_unparse_value(
+                    &field.ty,
+                    quote!(#binding),
+                    leaf_printer(&field.attrs, !extra).as_ref(),
+                    delimiter.as_ref(),
+                    wrappers,
+                    0,
+                ),
+            ))
+        })
+        .collect()
+}
+
+/// Generates a pattern binding every field of a struct or variant by name (or `_0`, `_1`, ...).
+pub fn gen_fields_pattern(path: TokenStream, fields: &Fields) -> TokenStream {
+    let bindings = fields.iter().enumerate().map(|(i, field)| {
+        let binding = field_binding(field, i);
+        match &field.ident {
+            Some(name) => quote!(#name),
+            None => quote!(#binding),
+        }
+    });
+
+    match fields {
+        Fields::Named(_) => quote!(#path { #(#bindings),* }),
+        Fields::Unnamed(_) => quote!(#path(#(#bindings),*)),
+        Fields::Unit => path,
+    }
+}
+
+/// Generates the `Unparse` implementation for a struct or enum in the grammar.
+pub fn gen_unparse_impl(item: &Item, wrappers: &HashSet<&str>) -> Option<Item> {
+    let (ident, generics, body) = match item {
+        Item::Struct(s) => {
+            let ident = &s.ident;
+            let pattern = gen_fields_pattern(quote!(#ident), &s.fields);
+            let extra = s
+                .attrs
+                .iter()
+                .any(|attr| attr.path == syn::parse_quote!(rust_sitter::extra));
+            let fields = gen_unparse_fields(&s.fields, wrappers, extra);
+            let body = gen_layout(&s.attrs, quote!(#(#fields)*));
+            (
+                ident,
+                &s.generics,
+                quote! {
+                    let #pattern = self;
+                    #body
+                },
+            )
+        }
+        Item::Enum(e) => {
+            let ident = &e.ident;
+            let arms = e.variants.iter().map(|v| {
+                let variant = &v.ident;
+                let pattern = gen_fields_pattern(quote!(#ident::#variant), &v.fields);
+                let fields = gen_unparse_fields(&v.fields, wrappers, false);
+                let body = gen_layout(&v.attrs, quote!(#(#fields)*));
+                quote!(#pattern => { #body })
+            });
+            (
+                ident,
+                &e.generics,
+                gen_layout(
+                    &e.attrs,
+                    quote! {
+                        match self {
+                            #(#arms)*
+                        }
+                    },
+                ),
+            )
+        }
+        _ => return None,
+    };
+
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+    Some(syn::parse_quote! {
+        impl #impl_generics rust_sitter::unparse::Unparse for #ident #ty_generics {
+            #[allow(unused_variables)]
+            fn unparse(&self, out: &mut rust_sitter::unparse::Unparser) {
+                #body
+            }
+        }
+    })
+}
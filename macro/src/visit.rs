@@ -0,0 +1,359 @@
+use std::collections::HashSet;
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::*;
+
+/// Converts the name of a grammar type into the suffix of its visitor methods,
+/// such as `NumberList` into `number_list`.
+fn snake_case(ident: &Ident) -> String {
+    let mut out = String::new();
+    for (i, c) in ident.to_string().chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+
+    out
+}
+
+fn method_ident(prefix: &str, ty: &Ident) -> Ident {
+    Ident::new(&format!("{prefix}_{}", snake_case(ty)), Span::call_site())
+}
+
+/// The grammar type a path type refers to, if it is one of the types in the grammar.
+fn grammar_type<'a>(ty: &Type, types: &'a HashSet<Ident>) -> Option<&'a Ident> {
+    if let Type::Path(p) = ty {
+        if p.qself.is_none() && p.path.segments.len() == 1 {
+            return types.get(&p.path.segments[0].ident);
+        }
+    }
+
+    None
+}
+
+/// Splits a type such as `Box<T>` into the name of the outer type and `T`.
+fn split_generic(ty: &Type) -> Option<(String, Type)> {
+    if let Type::Path(p) = ty {
+        let segment = p.path.segments.last().unwrap();
+        if let PathArguments::AngleBracketed(args) = &segment.arguments {
+            return args.args.iter().find_map(|a| match a {
+                GenericArgument::Type(t) => Some((segment.ident.to_string(), t.clone())),
+                _ => None,
+            });
+        }
+    }
+
+    None
+}
+
+/// Generates the statement that visits a value of type `ty` referred to by `expr`, recursing
+/// through `Box`, `Vec`, `Option` and `Spanned`. Returns `None` if there is nothing to visit.
+fn gen_visit_field(
+    ty: &Type,
+    expr: TokenStream,
+    mutable: bool,
+    depth: usize,
+    types: &HashSet<Ident>,
+) -> Option<TokenStream> {
+    let prefix = if mutable { "visit_mut" } else { "visit" };
+    let mut_token = if mutable { quote!(mut) } else { quote!() };
+    if let Some(ty_ident) = grammar_type(ty, types) {
+        let method = method_ident(prefix, ty_ident);
+        return Some(quote!(v.#method(#expr);));
+    }
+
+    let (outer, inner) = split_generic(ty)?;
+    let item = Ident::new(&format!("it{depth}"), Span::call_site());
+    match outer.as_str() {
+        "Box" => gen_visit_field(
+            &inner,
+            quote!(&#mut_token **#expr),
+            mutable,
+            depth + 1,
+            types,
+        ),
+        "Spanned" => gen_visit_field(
+            &inner,
+            quote!(&#mut_token (#expr).value),
+            mutable,
+            depth + 1,
+            types,
+        ),
+        "Option" => gen_visit_field(&inner, quote!(#item), mutable, depth + 1, types)
+            .map(|body| quote!(if let Some(#item) = #expr { #body })),
+        "Vec" => gen_visit_field(&inner, quote!(#item), mutable, depth + 1, types)
+            .map(|body| quote!(for #item in #expr { #body })),
+        _ => None,
+    }
+}
+
+/// Generates the expression that folds a value of type `ty` given by `expr`, recursing through
+/// `Box`, `Vec`, `Option` and `Spanned`.
+fn gen_fold_field(
+    ty: &Type,
+    expr: TokenStream,
+    depth: usize,
+    types: &HashSet<Ident>,
+) -> TokenStream {
+    if let Some(ty_ident) = grammar_type(ty, types) {
+        let method = method_ident("fold", ty_ident);
+        return quote!(f.#method(#expr));
+    }
+
+    let item = Ident::new(&format!("it{depth}"), Span::call_site());
+    match split_generic(ty) {
+        Some((outer, inner)) if outer == "Box" => {
+            let inner = gen_fold_field(&inner, quote!(*#expr), depth + 1, types);
+            quote!(Box::new(#inner))
+        }
+        Some((outer, inner)) if outer == "Spanned" => {
+            let value = gen_fold_field(&inner, quote!(#item.value), depth + 1, types);
+            quote!({
+                let #item = #expr;
+                rust_sitter::Spanned {
+                    value: #value,
+                    span: #item.span,
+                }
+            })
+        }
+        Some((outer, inner)) if outer == "Option" => {
+            let inner = gen_fold_field(&inner, quote!(#item), depth + 1, types);
+            quote!((#expr).map(|#item| #inner))
+        }
+        Some((outer, inner)) if outer == "Vec" => {
+            let inner = gen_fold_field(&inner, quote!(#item), depth + 1, types);
+            quote!((#expr).into_iter().map(|#item| #inner).collect())
+        }
+        _ => expr,
+    }
+}
+
+fn is_skipped(field: &Field) -> bool {
+    field.attrs.iter().any(|attr| {
+        attr.path == syn::parse_quote!(rust_sitter::skip)
+            || attr.path == syn::parse_quote!(rust_sitter::leaf)
+            || attr.path == syn::parse_quote!(rust_sitter::trivia)
+    })
+}
+
+fn field_binding(field: &Field, i: usize) -> Ident {
+    field
+        .ident
+        .clone()
+        .unwrap_or_else(|| Ident::new(&format!("_{i}"), Span::call_site()))
+}
+
+/// Generates a pattern destructuring the given fields. When `all` is false, only the fields
+/// that are visited are bound.
+fn gen_fields_pattern(path: TokenStream, fields: &Fields, all: bool) -> TokenStream {
+    let bindings = fields.iter().enumerate().map(|(i, field)| {
+        let binding = field_binding(field, i);
+        if all || !is_skipped(field) {
+            match &field.ident {
+                Some(name) => quote!(#name),
+                None => quote!(#binding),
+            }
+        } else {
+            match &field.ident {
+                Some(name) => quote!(#name: _),
+                None => quote!(_),
+            }
+        }
+    });
+
+    match fields {
+        Fields::Named(_) => quote!(#path { #(#bindings),* }),
+        Fields::Unnamed(_) => quote!(#path(#(#bindings),*)),
+        Fields::Unit => path,
+    }
+}
+
+fn gen_visit_body(fields: &Fields, mutable: bool, types: &HashSet<Ident>) -> Vec<TokenStream> {
+    fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| !is_skipped(field))
+        .filter_map(|(i, field)| {
+            let binding = field_binding(field, i);
+            gen_visit_field(&field.ty, quote!(#binding), mutable, 0, types)
+        })
+        .collect()
+}
+
+fn gen_fold_construct(path: TokenStream, fields: &Fields, types: &HashSet<Ident>) -> TokenStream {
+    let values = fields.iter().enumerate().map(|(i, field)| {
+        let binding = field_binding(field, i);
+        if is_skipped(field) {
+            return quote!(#binding);
+        }
+
+        let value = gen_fold_field(&field.ty, quote!(#binding), 0, types);
+        match &field.ident {
+            Some(name) => quote!(#name: #value),
+            None => value,
+        }
+    });
+
+    match fields {
+        Fields::Named(_) => quote!(#path { #(#values),* }),
+        Fields::Unnamed(_) => quote!(#path(#(#values),*)),
+        Fields::Unit => path,
+    }
+}
+
+/// Generates the `visit`, `visit_mut` and `fold` modules for the grammar types, which contain
+/// the `Visit`, `VisitMut` and `Fold` traits along with the functions implementing their
+/// default behavior of recursing into every child.
+pub fn gen_visitors(items: &[Item]) -> Vec<Item> {
+    let types: HashSet<Ident> = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Enum(e) => Some(e.ident.clone()),
+            Item::Struct(s) => Some(s.ident.clone()),
+            _ => None,
+        })
+        .collect();
+
+    let mut visit_methods = vec![];
+    let mut visit_fns = vec![];
+    let mut visit_mut_methods = vec![];
+    let mut visit_mut_fns = vec![];
+    let mut fold_methods = vec![];
+    let mut fold_fns = vec![];
+
+    items.iter().for_each(|item| {
+        let (ident, generics) = match item {
+            Item::Enum(e) => (&e.ident, &e.generics),
+            Item::Struct(s) => (&s.ident, &s.generics),
+            _ => return,
+        };
+        let (_, ty_generics, _) = generics.split_for_impl();
+        let params = generics.params.iter().collect::<Vec<_>>();
+
+        let (visit_body, visit_mut_body, fold_body) = match item {
+            Item::Struct(s) => {
+                let pattern = gen_fields_pattern(quote!(#ident), &s.fields, false);
+                let visit = gen_visit_body(&s.fields, false, &types);
+                let visit_mut = gen_visit_body(&s.fields, true, &types);
+                let fold_pattern = gen_fields_pattern(quote!(#ident), &s.fields, true);
+                let construct = gen_fold_construct(quote!(#ident), &s.fields, &types);
+                (
+                    quote!(let #pattern = node; #(#visit)*),
+                    quote!(let #pattern = node; #(#visit_mut)*),
+                    quote!(let #fold_pattern = node; #construct),
+                )
+            }
+            Item::Enum(e) => {
+                let mut visit_arms = vec![];
+                let mut visit_mut_arms = vec![];
+                let mut fold_arms = vec![];
+                e.variants.iter().for_each(|v| {
+                    let variant = &v.ident;
+                    let pattern = gen_fields_pattern(quote!(#ident::#variant), &v.fields, false);
+                    let visit = gen_visit_body(&v.fields, false, &types);
+                    let visit_mut = gen_visit_body(&v.fields, true, &types);
+                    let fold_pattern = gen_fields_pattern(quote!(#ident::#variant), &v.fields, true);
+                    let construct =
+                        gen_fold_construct(quote!(#ident::#variant), &v.fields, &types);
+                    visit_arms.push(quote!(#pattern => { #(#visit)* }));
+                    visit_mut_arms.push(quote!(#pattern => { #(#visit_mut)* }));
+                    fold_arms.push(quote!(#fold_pattern => #construct));
+                });
+                (
+                    quote!(match node { #(#visit_arms)* }),
+                    quote!(match node { #(#visit_mut_arms)* }),
+                    quote!(match node { #(#fold_arms),* }),
+                )
+            }
+            _ => unreachable!(),
+        };
+
+        let visit = method_ident("visit", ident);
+        let visit_mut = method_ident("visit_mut", ident);
+        let fold = method_ident("fold", ident);
+
+        visit_methods.push(quote! {
+            fn #visit<#(#params),*>(&mut self, node: &#ident #ty_generics) {
+                #visit(self, node)
+            }
+        });
+        visit_fns.push(quote! {
+            #[allow(unused_variables)]
+            pub fn #visit<#(#params,)* V: Visit + ?Sized>(v: &mut V, node: &#ident #ty_generics) {
+                #visit_body
+            }
+        });
+
+        visit_mut_methods.push(quote! {
+            fn #visit_mut<#(#params),*>(&mut self, node: &mut #ident #ty_generics) {
+                #visit_mut(self, node)
+            }
+        });
+        visit_mut_fns.push(quote! {
+            #[allow(unused_variables)]
+            pub fn #visit_mut<#(#params,)* V: VisitMut + ?Sized>(v: &mut V, node: &mut #ident #ty_generics) {
+                #visit_mut_body
+            }
+        });
+
+        fold_methods.push(quote! {
+            fn #fold<#(#params),*>(&mut self, node: #ident #ty_generics) -> #ident #ty_generics {
+                #fold(self, node)
+            }
+        });
+        fold_fns.push(quote! {
+            #[allow(unused_variables)]
+            pub fn #fold<#(#params,)* F: Fold + ?Sized>(f: &mut F, node: #ident #ty_generics) -> #ident #ty_generics {
+                #fold_body
+            }
+        });
+    });
+
+    vec![
+        syn::parse_quote! {
+            /// Traverses borrowed grammar values, calling the method for each type that is
+            /// reached. By default, each method visits all children of the value.
+            pub mod visit {
+                use super::*;
+
+                pub trait Visit {
+                    #(#visit_methods)*
+                }
+
+                #(#visit_fns)*
+            }
+        },
+        syn::parse_quote! {
+            /// Traverses mutably borrowed grammar values, calling the method for each type that
+            /// is reached. By default, each method visits all children of the value.
+            pub mod visit_mut {
+                use super::*;
+
+                pub trait VisitMut {
+                    #(#visit_mut_methods)*
+                }
+
+                #(#visit_mut_fns)*
+            }
+        },
+        syn::parse_quote! {
+            /// Rebuilds owned grammar values, calling the method for each type that is reached.
+            /// By default, each method folds all children of the value and reassembles it.
+            pub mod fold {
+                use super::*;
+
+                pub trait Fold {
+                    #(#fold_methods)*
+                }
+
+                #(#fold_fns)*
+            }
+        },
+    ]
+}
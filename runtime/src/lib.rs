@@ -1,7 +1,15 @@
+use std::borrow::Cow;
+use std::fmt::Display;
 use std::ops::Deref;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::Arc;
 
 pub use rust_sitter_macro::*;
 
+#[cfg(feature = "serde")]
+pub use serde;
+
 #[cfg(feature = "tree-sitter-standard")]
 pub use tree_sitter_runtime_standard as tree_sitter;
 
@@ -9,27 +17,39 @@ pub use tree_sitter_runtime_standard as tree_sitter;
 pub use tree_sitter_runtime_c2rust as tree_sitter;
 
 /// Defines the logic used to convert a node in a Tree Sitter tree to
-/// the corresponding Rust type.
-pub trait Extract<Output> {
+/// the corresponding Rust type. The lifetime `'a` is the lifetime of the
+/// source being parsed, which allows extracted values to borrow from it.
+pub trait Extract<'a, Output> {
     type LeafFn: ?Sized;
     fn extract(
         node: Option<tree_sitter::Node>,
-        source: &[u8],
+        source: &'a [u8],
         last_idx: usize,
         leaf_fn: Option<&Self::LeafFn>,
     ) -> Output;
+
+    /// Like [`Extract::extract`], but returns an error for leaves whose text cannot be
+    /// converted into their Rust type instead of panicking.
+    fn try_extract(
+        node: Option<tree_sitter::Node>,
+        source: &'a [u8],
+        last_idx: usize,
+        leaf_fn: Option<&Self::LeafFn>,
+    ) -> Result<Output, errors::ParseError> {
+        Ok(Self::extract(node, source, last_idx, leaf_fn))
+    }
 }
 
 pub struct WithLeaf<L> {
     _phantom: std::marker::PhantomData<L>,
 }
 
-impl<L> Extract<L> for WithLeaf<L> {
-    type LeafFn = dyn Fn(&str) -> L;
+impl<'a, L> Extract<'a, L> for WithLeaf<L> {
+    type LeafFn = dyn Fn(&'a str) -> L;
 
     fn extract(
         node: Option<tree_sitter::Node>,
-        source: &[u8],
+        source: &'a [u8],
         _last_idx: usize,
         leaf_fn: Option<&Self::LeafFn>,
     ) -> L {
@@ -39,7 +59,46 @@ impl<L> Extract<L> for WithLeaf<L> {
     }
 }
 
-impl Extract<()> for () {
+/// Extracts a leaf without a transform by parsing its text with [`FromStr`].
+pub struct FromStrLeaf<L> {
+    _phantom: std::marker::PhantomData<L>,
+}
+
+impl<'a, L: FromStr> Extract<'a, L> for FromStrLeaf<L>
+where
+    L::Err: Display,
+{
+    type LeafFn = ();
+
+    fn extract(
+        node: Option<tree_sitter::Node>,
+        source: &'a [u8],
+        last_idx: usize,
+        leaf_fn: Option<&Self::LeafFn>,
+    ) -> L {
+        Self::try_extract(node, source, last_idx, leaf_fn)
+            .unwrap_or_else(|e| panic!("Could not convert leaf: {:?}", e.reason))
+    }
+
+    fn try_extract(
+        node: Option<tree_sitter::Node>,
+        source: &'a [u8],
+        _last_idx: usize,
+        _leaf_fn: Option<&Self::LeafFn>,
+    ) -> Result<L, errors::ParseError> {
+        let node = node.unwrap();
+        node.utf8_text(source)
+            .unwrap()
+            .parse()
+            .map_err(|e: L::Err| errors::ParseError {
+                reason: errors::ParseErrorReason::InvalidLeaf(e.to_string()),
+                start: node.start_byte(),
+                end: node.end_byte(),
+            })
+    }
+}
+
+impl Extract<'_, ()> for () {
     type LeafFn = ();
     fn extract(
         _node: Option<tree_sitter::Node>,
@@ -50,35 +109,104 @@ impl Extract<()> for () {
     }
 }
 
-impl<T: Extract<U>, U> Extract<Option<U>> for Option<T> {
-    type LeafFn = T::LeafFn;
+impl Extract<'_, String> for String {
+    type LeafFn = ();
     fn extract(
         node: Option<tree_sitter::Node>,
         source: &[u8],
+        _last_idx: usize,
+        _leaf_fn: Option<&Self::LeafFn>,
+    ) -> String {
+        node.and_then(|n| n.utf8_text(source).ok())
+            .unwrap()
+            .to_string()
+    }
+}
+
+impl Extract<'_, Box<str>> for Box<str> {
+    type LeafFn = ();
+    fn extract(
+        node: Option<tree_sitter::Node>,
+        source: &[u8],
+        _last_idx: usize,
+        _leaf_fn: Option<&Self::LeafFn>,
+    ) -> Box<str> {
+        node.and_then(|n| n.utf8_text(source).ok()).unwrap().into()
+    }
+}
+
+impl<'a> Extract<'a, &'a str> for &'a str {
+    type LeafFn = ();
+    fn extract(
+        node: Option<tree_sitter::Node>,
+        source: &'a [u8],
+        _last_idx: usize,
+        _leaf_fn: Option<&Self::LeafFn>,
+    ) -> &'a str {
+        node.and_then(|n| n.utf8_text(source).ok()).unwrap()
+    }
+}
+
+impl<'a> Extract<'a, Cow<'a, str>> for Cow<'a, str> {
+    type LeafFn = ();
+    fn extract(
+        node: Option<tree_sitter::Node>,
+        source: &'a [u8],
+        _last_idx: usize,
+        _leaf_fn: Option<&Self::LeafFn>,
+    ) -> Cow<'a, str> {
+        Cow::Borrowed(node.and_then(|n| n.utf8_text(source).ok()).unwrap())
+    }
+}
+
+impl<'a, T: Extract<'a, U>, U> Extract<'a, Option<U>> for Option<T> {
+    type LeafFn = T::LeafFn;
+    fn extract(
+        node: Option<tree_sitter::Node>,
+        source: &'a [u8],
         last_idx: usize,
         leaf_fn: Option<&Self::LeafFn>,
     ) -> Option<U> {
         node.map(|n| T::extract(Some(n), source, last_idx, leaf_fn))
     }
+
+    fn try_extract(
+        node: Option<tree_sitter::Node>,
+        source: &'a [u8],
+        last_idx: usize,
+        leaf_fn: Option<&Self::LeafFn>,
+    ) -> Result<Option<U>, errors::ParseError> {
+        node.map(|n| T::try_extract(Some(n), source, last_idx, leaf_fn))
+            .transpose()
+    }
 }
 
-impl<T: Extract<U>, U> Extract<Box<U>> for Box<T> {
+impl<'a, T: Extract<'a, U>, U> Extract<'a, Box<U>> for Box<T> {
     type LeafFn = T::LeafFn;
     fn extract(
         node: Option<tree_sitter::Node>,
-        source: &[u8],
+        source: &'a [u8],
         last_idx: usize,
         leaf_fn: Option<&Self::LeafFn>,
     ) -> Box<U> {
         Box::new(T::extract(node, source, last_idx, leaf_fn))
     }
+
+    fn try_extract(
+        node: Option<tree_sitter::Node>,
+        source: &'a [u8],
+        last_idx: usize,
+        leaf_fn: Option<&Self::LeafFn>,
+    ) -> Result<Box<U>, errors::ParseError> {
+        T::try_extract(node, source, last_idx, leaf_fn).map(Box::new)
+    }
 }
 
-impl<T: Extract<U>, U> Extract<Vec<U>> for Vec<T> {
+impl<'a, T: Extract<'a, U>, U> Extract<'a, Vec<U>> for Vec<T> {
     type LeafFn = T::LeafFn;
     fn extract(
         node: Option<tree_sitter::Node>,
-        source: &[u8],
+        source: &'a [u8],
         mut last_idx: usize,
         leaf_fn: Option<&Self::LeafFn>,
     ) -> Vec<U> {
@@ -104,9 +232,38 @@ impl<T: Extract<U>, U> Extract<Vec<U>> for Vec<T> {
         })
         .unwrap_or_default()
     }
+
+    fn try_extract(
+        node: Option<tree_sitter::Node>,
+        source: &'a [u8],
+        mut last_idx: usize,
+        leaf_fn: Option<&Self::LeafFn>,
+    ) -> Result<Vec<U>, errors::ParseError> {
+        let mut out = vec![];
+        if let Some(node) = node {
+            let mut cursor = node.walk();
+            if cursor.goto_first_child() {
+                loop {
+                    let n = cursor.node();
+                    if cursor.field_name().is_some() {
+                        out.push(T::try_extract(Some(n), source, last_idx, leaf_fn)?);
+                    }
+
+                    last_idx = n.end_byte();
+
+                    if !cursor.goto_next_sibling() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(out)
+    }
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A wrapper around a value that also contains the span of the value in the source.
 pub struct Spanned<T> {
     /// The underlying parsed node.
@@ -124,11 +281,11 @@ impl<T> Deref for Spanned<T> {
     }
 }
 
-impl<T: Extract<U>, U> Extract<Spanned<U>> for Spanned<T> {
+impl<'a, T: Extract<'a, U>, U> Extract<'a, Spanned<U>> for Spanned<T> {
     type LeafFn = T::LeafFn;
     fn extract(
         node: Option<tree_sitter::Node>,
-        source: &[u8],
+        source: &'a [u8],
         last_idx: usize,
         leaf_fn: Option<&Self::LeafFn>,
     ) -> Spanned<U> {
@@ -139,83 +296,1701 @@ impl<T: Extract<U>, U> Extract<Spanned<U>> for Spanned<T> {
                 .unwrap_or((last_idx, last_idx)),
         }
     }
+
+    fn try_extract(
+        node: Option<tree_sitter::Node>,
+        source: &'a [u8],
+        last_idx: usize,
+        leaf_fn: Option<&Self::LeafFn>,
+    ) -> Result<Spanned<U>, errors::ParseError> {
+        Ok(Spanned {
+            value: T::try_extract(node, source, last_idx, leaf_fn)?,
+            span: node
+                .map(|n| (n.start_byte(), n.end_byte()))
+                .unwrap_or((last_idx, last_idx)),
+        })
+    }
 }
 
-pub mod errors {
+/// A type that wraps a single value in a grammar without affecting how it is parsed.
+/// `Box`, `Rc`, `Arc` and [`Spanned`] are wrappers out of the box; other wrappers must be
+/// registered with the grammar, as in `#[rust_sitter::grammar("name", wrappers = [Node])]`.
+pub trait Wrapper {
+    /// The type of the wrapped value.
+    type Inner;
+    /// Wraps an extracted value, given the span of its node in the source.
+    fn wrap(inner: Self::Inner, span: (usize, usize)) -> Self;
+    /// The wrapped value, which is used when printing the wrapper back out.
+    fn inner(&self) -> &Self::Inner;
+}
+
+impl<T> Wrapper for Box<T> {
+    type Inner = T;
+    fn wrap(inner: T, _span: (usize, usize)) -> Self {
+        Box::new(inner)
+    }
+
+    fn inner(&self) -> &T {
+        self
+    }
+}
+
+impl<T> Wrapper for Rc<T> {
+    type Inner = T;
+    fn wrap(inner: T, _span: (usize, usize)) -> Self {
+        Rc::new(inner)
+    }
+
+    fn inner(&self) -> &T {
+        self
+    }
+}
+
+impl<T> Wrapper for Arc<T> {
+    type Inner = T;
+    fn wrap(inner: T, _span: (usize, usize)) -> Self {
+        Arc::new(inner)
+    }
+
+    fn inner(&self) -> &T {
+        self
+    }
+}
+
+impl<T> Wrapper for Spanned<T> {
+    type Inner = T;
+    fn wrap(value: T, span: (usize, usize)) -> Self {
+        Spanned { value, span }
+    }
+
+    fn inner(&self) -> &T {
+        &self.value
+    }
+}
+
+/// Extracts the wrapper `W` by extracting its inner value with `T` and then wrapping it.
+pub struct Wrapped<W, T> {
+    _phantom: std::marker::PhantomData<(W, T)>,
+}
+
+impl<'a, W: Wrapper<Inner = U>, T: Extract<'a, U>, U> Extract<'a, W> for Wrapped<W, T> {
+    type LeafFn = T::LeafFn;
+    fn extract(
+        node: Option<tree_sitter::Node>,
+        source: &'a [u8],
+        last_idx: usize,
+        leaf_fn: Option<&Self::LeafFn>,
+    ) -> W {
+        W::wrap(
+            T::extract(node, source, last_idx, leaf_fn),
+            node.map(|n| (n.start_byte(), n.end_byte()))
+                .unwrap_or((last_idx, last_idx)),
+        )
+    }
+
+    fn try_extract(
+        node: Option<tree_sitter::Node>,
+        source: &'a [u8],
+        last_idx: usize,
+        leaf_fn: Option<&Self::LeafFn>,
+    ) -> Result<W, errors::ParseError> {
+        Ok(W::wrap(
+            T::try_extract(node, source, last_idx, leaf_fn)?,
+            node.map(|n| (n.start_byte(), n.end_byte()))
+                .unwrap_or((last_idx, last_idx)),
+        ))
+    }
+}
+
+/// A type marked with `#[rust_sitter::extra]`, whose nodes can appear between any other nodes.
+pub trait Extra {
+    /// The kind of the Tree Sitter nodes corresponding to this type.
+    const KIND: &'static str;
+}
+
+pub mod trivia {
+    use crate::errors::ParseError;
+    use crate::{Extra, Extract, Spanned};
+
     #[cfg(feature = "tree-sitter-standard")]
     use tree_sitter_runtime_standard as tree_sitter;
 
     #[cfg(feature = "tree-sitter-c2rust")]
     use tree_sitter_runtime_c2rust as tree_sitter;
 
-    #[derive(Debug)]
-    /// An explanation for an error that occurred during parsing.
-    pub enum ParseErrorReason {
-        /// The parser did not expect to see some token.
-        UnexpectedToken(String),
-        /// Tree Sitter failed to parse a specific intermediate node.
-        /// The underlying failures are in the vector.
-        FailedNode(Vec<ParseError>),
-        /// The parser expected a specific token, but it was not found.
-        MissingToken(String),
+    /// Collects the extra nodes between `node` and the previous non-extra node, in source order.
+    /// Extras are never the first or last child of a node, so they are found among the previous
+    /// siblings of `node` or of the ancestors that `node` starts.
+    pub fn leading_extras<'t>(node: tree_sitter::Node<'t>) -> Vec<tree_sitter::Node<'t>> {
+        let mut out = vec![];
+        let mut current = node;
+        loop {
+            match current.prev_sibling() {
+                Some(prev) if prev.is_extra() => {
+                    out.push(prev);
+                    current = prev;
+                }
+                Some(_) => break,
+                None => match current.parent() {
+                    Some(parent) => current = parent,
+                    None => break,
+                },
+            }
+        }
+
+        out.reverse();
+        out
     }
 
-    #[derive(Debug)]
-    /// An error that occurred during parsing.
-    pub struct ParseError {
-        pub reason: ParseErrorReason,
-        /// Inclusive start of the error.
-        pub start: usize,
-        /// Exclusive end of the error.
-        pub end: usize,
+    /// Collects the extra nodes between `node` and the next non-extra node, in source order.
+    pub fn trailing_extras<'t>(node: tree_sitter::Node<'t>) -> Vec<tree_sitter::Node<'t>> {
+        let mut out = vec![];
+        let mut current = node;
+        loop {
+            match current.next_sibling() {
+                Some(next) if next.is_extra() => {
+                    out.push(next);
+                    current = next;
+                }
+                Some(_) => break,
+                None => match current.parent() {
+                    Some(parent) => current = parent,
+                    None => break,
+                },
+            }
+        }
+
+        out
     }
 
-    /// Given the root node of a Tree Sitter parsing result, accumulates all
-    /// errors that were emitted.
-    pub fn collect_parsing_errors(
+    fn extract_all<'a, T: Extract<'a, U>, U>(
+        nodes: Vec<tree_sitter::Node>,
+        source: &'a [u8],
+        kind: &str,
+    ) -> Result<Vec<U>, ParseError> {
+        nodes
+            .into_iter()
+            .filter(|n| n.kind() == kind)
+            .map(|n| T::try_extract(Some(n), source, n.start_byte(), None))
+            .collect()
+    }
+
+    /// Extracts the extras of the given kind that come directly before `node`.
+    pub fn leading<'a, T: Extract<'a, U>, U>(
+        node: tree_sitter::Node,
+        source: &'a [u8],
+        kind: &str,
+    ) -> Result<Vec<U>, ParseError> {
+        extract_all::<T, U>(leading_extras(node), source, kind)
+    }
+
+    /// Extracts the extras of the given kind that come directly after `node`.
+    pub fn trailing<'a, T: Extract<'a, U>, U>(
+        node: tree_sitter::Node,
+        source: &'a [u8],
+        kind: &str,
+    ) -> Result<Vec<U>, ParseError> {
+        extract_all::<T, U>(trailing_extras(node), source, kind)
+    }
+
+    /// Extracts every extra of type `T` in the tree under `node`, along with their spans.
+    pub fn collect_extras<'a, T: Extra + Extract<'a, T>>(
         node: &tree_sitter::Node,
-        source: &[u8],
-        errors: &mut Vec<ParseError>,
-    ) {
-        if node.is_error() {
-            if node.child(0).is_some() {
-                // we managed to parse some children, so collect underlying errors for this node
-                let mut inner_errors = vec![];
-                let mut cursor = node.walk();
-                node.children(&mut cursor)
-                    .for_each(|c| collect_parsing_errors(&c, source, &mut inner_errors));
+        source: &'a [u8],
+        out: &mut Vec<Spanned<T>>,
+    ) -> Result<(), ParseError> {
+        if node.is_extra() && node.kind() == T::KIND {
+            out.push(Spanned {
+                value: T::try_extract(Some(*node), source, node.start_byte(), None)?,
+                span: (node.start_byte(), node.end_byte()),
+            });
+        } else {
+            let mut cursor = node.walk();
+            for c in node.children(&mut cursor) {
+                collect_extras(&c, source, out)?;
+            }
+        }
 
-                errors.push(ParseError {
-                    reason: ParseErrorReason::FailedNode(inner_errors),
-                    start: node.start_byte(),
-                    end: node.end_byte(),
-                })
+        Ok(())
+    }
+}
+
+impl<T> Spanned<T> {
+    /// Finds the node in a concrete syntax tree that this value was parsed from.
+    pub fn syntax(&self, root: &cst::SyntaxNode) -> Option<cst::SyntaxNode> {
+        root.covering(self.span)
+    }
+}
+
+/// A lossless concrete syntax tree, which keeps every token, extra and gap in the source
+/// so that the exact source text can be recovered. Green nodes are immutable and do not know
+/// their position, while the `Syntax*` (red) types add the absolute offset and parent of each
+/// element on top of them.
+pub mod cst {
+    use std::fmt;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    #[cfg(feature = "tree-sitter-standard")]
+    use tree_sitter_runtime_standard as tree_sitter;
+
+    #[cfg(feature = "tree-sitter-c2rust")]
+    use tree_sitter_runtime_c2rust as tree_sitter;
+
+    /// The kind of tokens covering source text that Tree Sitter skipped without producing a node.
+    pub const GAP: &str = "gap";
+
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    /// A leaf of the tree along with its text.
+    pub struct GreenToken {
+        pub kind: &'static str,
+        pub text: Box<str>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    /// An inner node of the tree, which knows its width but not its position.
+    pub struct GreenNode {
+        pub kind: &'static str,
+        pub width: usize,
+        pub children: Vec<GreenElement>,
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+    pub enum GreenElement {
+        Node(Arc<GreenNode>),
+        Token(Arc<GreenToken>),
+    }
+
+    impl GreenElement {
+        pub fn width(&self) -> usize {
+            match self {
+                GreenElement::Node(n) => n.width,
+                GreenElement::Token(t) => t.text.len(),
+            }
+        }
+    }
+
+    fn gap(source: &str, start: usize, end: usize, out: &mut Vec<GreenElement>) {
+        if start < end {
+            out.push(GreenElement::Token(Arc::new(GreenToken {
+                kind: GAP,
+                text: source[start..end].into(),
+            })));
+        }
+    }
+
+    fn build_green(
+        node: &tree_sitter::Node,
+        source: &str,
+        start: usize,
+        end: usize,
+        rule_name: fn(&'static str) -> &'static str,
+    ) -> GreenNode {
+        let mut children = vec![];
+        let mut position = start;
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            gap(source, position, child.start_byte(), &mut children);
+            if child.child_count() == 0 {
+                children.push(GreenElement::Token(Arc::new(GreenToken {
+                    kind: rule_name(child.kind()),
+                    text: source[child.start_byte()..child.end_byte()].into(),
+                })));
             } else {
-                let contents = node.utf8_text(source).unwrap();
-                if !contents.is_empty() {
-                    errors.push(ParseError {
-                        reason: ParseErrorReason::UnexpectedToken(contents.to_string()),
-                        start: node.start_byte(),
-                        end: node.end_byte(),
-                    })
-                } else {
-                    errors.push(ParseError {
-                        reason: ParseErrorReason::FailedNode(vec![]),
-                        start: node.start_byte(),
-                        end: node.end_byte(),
-                    })
-                }
+                children.push(GreenElement::Node(Arc::new(build_green(
+                    &child,
+                    source,
+                    child.start_byte(),
+                    child.end_byte(),
+                    rule_name,
+                ))));
             }
-        } else if node.is_missing() {
-            errors.push(ParseError {
-                reason: ParseErrorReason::MissingToken(node.kind().to_string()),
-                start: node.start_byte(),
-                end: node.end_byte(),
-            })
-        } else if node.has_error() {
+            position = child.end_byte();
+        }
+        gap(source, position, end, &mut children);
+
+        GreenNode {
+            kind: rule_name(node.kind()),
+            width: end - start,
+            children,
+        }
+    }
+
+    struct NodeData {
+        green: Arc<GreenNode>,
+        offset: usize,
+        parent: Option<SyntaxNode>,
+    }
+
+    #[derive(Clone)]
+    /// A node in the concrete syntax tree, which knows its position and parent.
+    pub struct SyntaxNode(Rc<NodeData>);
+
+    #[derive(Clone)]
+    /// A token in the concrete syntax tree, which knows its position and parent.
+    pub struct SyntaxToken {
+        green: Arc<GreenToken>,
+        offset: usize,
+        parent: SyntaxNode,
+    }
+
+    #[derive(Clone)]
+    pub enum SyntaxElement {
+        Node(SyntaxNode),
+        Token(SyntaxToken),
+    }
+
+    impl SyntaxNode {
+        /// Builds the concrete syntax tree for a Tree Sitter tree over `source`, naming nodes with
+        /// `rule_name` (usually the `rule_name` function generated for the grammar). The root
+        /// always covers the whole source, including any leading or trailing gaps.
+        pub fn new_root(
+            root: &tree_sitter::Node,
+            source: &str,
+            rule_name: fn(&'static str) -> &'static str,
+        ) -> SyntaxNode {
+            SyntaxNode(Rc::new(NodeData {
+                green: Arc::new(build_green(root, source, 0, source.len(), rule_name)),
+                offset: 0,
+                parent: None,
+            }))
+        }
+
+        pub fn green(&self) -> &Arc<GreenNode> {
+            &self.0.green
+        }
+
+        /// The name of the Rust rule this node corresponds to.
+        pub fn kind(&self) -> &'static str {
+            self.0.green.kind
+        }
+
+        /// The span of the node in the source. The first value is the inclusive start
+        /// of the span, and the second value is the exclusive end of the span.
+        pub fn span(&self) -> (usize, usize) {
+            (self.0.offset, self.0.offset + self.0.green.width)
+        }
+
+        pub fn parent(&self) -> Option<SyntaxNode> {
+            self.0.parent.clone()
+        }
+
+        /// The child nodes and tokens of this node, in source order.
+        pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+            let mut offset = self.0.offset;
+            self.0
+                .green
+                .children
+                .iter()
+                .map(|child| {
+                    let element = match child {
+                        GreenElement::Node(n) => {
+                            SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                                green: n.clone(),
+                                offset,
+                                parent: Some(self.clone()),
+                            })))
+                        }
+                        GreenElement::Token(t) => SyntaxElement::Token(SyntaxToken {
+                            green: t.clone(),
+                            offset,
+                            parent: self.clone(),
+                        }),
+                    };
+                    offset += child.width();
+                    element
+                })
+                .collect()
+        }
+
+        /// The child nodes of this node, in source order.
+        pub fn children(&self) -> Vec<SyntaxNode> {
+            self.children_with_tokens()
+                .into_iter()
+                .filter_map(|c| match c {
+                    SyntaxElement::Node(n) => Some(n),
+                    SyntaxElement::Token(_) => None,
+                })
+                .collect()
+        }
+
+        /// This node and all nodes under it, in pre-order.
+        pub fn descendants(&self) -> Vec<SyntaxNode> {
+            let mut out = vec![self.clone()];
+            self.children()
+                .iter()
+                .for_each(|c| out.extend(c.descendants()));
+            out
+        }
+
+        /// All tokens under this node, in source order.
+        pub fn tokens(&self) -> Vec<SyntaxToken> {
+            self.children_with_tokens()
+                .into_iter()
+                .flat_map(|c| match c {
+                    SyntaxElement::Node(n) => n.tokens(),
+                    SyntaxElement::Token(t) => vec![t],
+                })
+                .collect()
+        }
+
+        /// Finds the outermost node under this one that spans exactly `span`.
+        pub fn covering(&self, span: (usize, usize)) -> Option<SyntaxNode> {
+            if self.span() == span {
+                Some(self.clone())
+            } else {
+                self.children()
+                    .into_iter()
+                    .find(|c| c.span().0 <= span.0 && span.1 <= c.span().1)
+                    .and_then(|c| c.covering(span))
+            }
+        }
+
+        /// The source text covered by this node.
+        pub fn text(&self) -> String {
+            self.to_string()
+        }
+    }
+
+    impl SyntaxToken {
+        pub fn green(&self) -> &Arc<GreenToken> {
+            &self.green
+        }
+
+        /// The name of the Rust rule this token corresponds to, or [`GAP`].
+        pub fn kind(&self) -> &'static str {
+            self.green.kind
+        }
+
+        pub fn text(&self) -> &str {
+            &self.green.text
+        }
+
+        /// The span of the token in the source. The first value is the inclusive start
+        /// of the span, and the second value is the exclusive end of the span.
+        pub fn span(&self) -> (usize, usize) {
+            (self.offset, self.offset + self.green.text.len())
+        }
+
+        pub fn parent(&self) -> SyntaxNode {
+            self.parent.clone()
+        }
+    }
+
+    impl fmt::Display for SyntaxNode {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.tokens().iter().try_for_each(|t| f.write_str(t.text()))
+        }
+    }
+
+    impl fmt::Display for SyntaxToken {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.text())
+        }
+    }
+
+    impl fmt::Debug for SyntaxNode {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let (start, end) = self.span();
+            writeln!(f, "{}@{}..{}", self.kind(), start, end)?;
+            for child in self.children_with_tokens() {
+                let child = format!("{child:?}");
+                for line in child.lines() {
+                    writeln!(f, "  {line}")?;
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    impl fmt::Debug for SyntaxElement {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                SyntaxElement::Node(n) => n.fmt(f),
+                SyntaxElement::Token(t) => t.fmt(f),
+            }
+        }
+    }
+
+    impl fmt::Debug for SyntaxToken {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let (start, end) = self.span();
+            write!(f, "{}@{}..{} {:?}", self.kind(), start, end, self.text())
+        }
+    }
+}
+
+/// Lazy, typed views over a Tree Sitter tree, which are generated for each grammar type in the
+/// `views` module of the grammar. Views only wrap a node, and their accessors look up children
+/// on demand instead of building the whole AST up front.
+pub mod views {
+    #[cfg(feature = "tree-sitter-standard")]
+    use tree_sitter_runtime_standard as tree_sitter;
+
+    #[cfg(feature = "tree-sitter-c2rust")]
+    use tree_sitter_runtime_c2rust as tree_sitter;
+
+    /// A typed view over a node in a tree that lives for `'tree`.
+    pub trait View<'tree>: Sized {
+        fn from_node(node: tree_sitter::Node<'tree>, source: &'tree [u8]) -> Self;
+        /// The node this view wraps.
+        fn node(&self) -> tree_sitter::Node<'tree>;
+    }
+
+    /// Links a grammar type to its generated view type.
+    pub trait HasView<'tree> {
+        type View: View<'tree>;
+    }
+
+    /// Creates the view of type `T` for a node.
+    pub fn view<'tree, T: HasView<'tree>>(
+        node: tree_sitter::Node<'tree>,
+        source: &'tree [u8],
+    ) -> T::View {
+        T::View::from_node(node, source)
+    }
+
+    /// Finds the child of an enum node that corresponds to one of its variants.
+    pub fn variant_node<'tree>(
+        node: tree_sitter::Node<'tree>,
+        variant_kinds: &[&str],
+    ) -> tree_sitter::Node<'tree> {
+        let mut cursor = node.walk();
+        let found = node
+            .children(&mut cursor)
+            .find(|c| variant_kinds.contains(&c.kind()));
+        found.expect("Could not find a child corresponding to any enum branch")
+    }
+
+    /// An iterator over the elements of a `Vec` field, given the node containing them.
+    pub struct Elements<'tree> {
+        cursor: Option<tree_sitter::TreeCursor<'tree>>,
+    }
+
+    /// Iterates over the elements of a `Vec` field, which is empty if the field is missing.
+    pub fn elements(node: Option<tree_sitter::Node>) -> Elements {
+        Elements {
+            cursor: node.and_then(|n| {
+                let mut cursor = n.walk();
+                if cursor.goto_first_child() {
+                    Some(cursor)
+                } else {
+                    None
+                }
+            }),
+        }
+    }
+
+    impl<'tree> Iterator for Elements<'tree> {
+        type Item = tree_sitter::Node<'tree>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            loop {
+                let cursor = self.cursor.as_mut()?;
+                let node = cursor.node();
+                let is_element = cursor.field_name().is_some();
+                if !cursor.goto_next_sibling() {
+                    self.cursor = None;
+                }
+
+                if is_element {
+                    return Some(node);
+                }
+            }
+        }
+    }
+}
+
+/// Tree Sitter queries written with the Rust names of a grammar, which are built with
+/// [`query!`](crate::query!) and return their captures as views.
+pub mod query {
+    use std::marker::PhantomData;
+
+    #[cfg(feature = "tree-sitter-standard")]
+    use tree_sitter_runtime_standard as tree_sitter;
+
+    #[cfg(feature = "tree-sitter-c2rust")]
+    use tree_sitter_runtime_c2rust as tree_sitter;
+
+    /// Converts a captured node into a typed value, which is generated for each node kind of a
+    /// grammar (converting into its view) and implemented by [`AnyNode`] for untyped patterns.
+    pub trait Capture<'tree> {
+        type Output;
+        fn from_node(node: tree_sitter::Node<'tree>, source: &'tree [u8]) -> Self::Output;
+    }
+
+    /// Captures a pattern without a grammar type, such as `(_)` or `"-"`, as the node itself.
+    pub struct AnyNode;
+
+    impl<'tree> Capture<'tree> for AnyNode {
+        type Output = tree_sitter::Node<'tree>;
+
+        fn from_node(node: tree_sitter::Node<'tree>, _source: &'tree [u8]) -> Self::Output {
+            node
+        }
+    }
+
+    /// Converts the nodes captured by a match into the struct generated for a query.
+    pub trait Captures<'tree> {
+        type Output;
+        /// The names of the captures, in the order their nodes are passed to `from_nodes`.
+        const NAMES: &'static [&'static str];
+        fn from_nodes(nodes: &[Vec<tree_sitter::Node<'tree>>], source: &'tree [u8])
+            -> Self::Output;
+    }
+
+    /// Converts the node of a capture that appears exactly once in each match.
+    pub fn one<'tree, C: Capture<'tree>>(
+        nodes: &[tree_sitter::Node<'tree>],
+        source: &'tree [u8],
+    ) -> C::Output {
+        C::from_node(nodes[0], source)
+    }
+
+    /// Converts the node of a capture that may be missing from a match.
+    pub fn optional<'tree, C: Capture<'tree>>(
+        nodes: &[tree_sitter::Node<'tree>],
+        source: &'tree [u8],
+    ) -> Option<C::Output> {
+        nodes.first().map(|n| C::from_node(*n, source))
+    }
+
+    /// Converts the nodes of a capture under a `*` or `+` quantifier.
+    pub fn many<'tree, C: Capture<'tree>>(
+        nodes: &[tree_sitter::Node<'tree>],
+        source: &'tree [u8],
+    ) -> Vec<C::Output> {
+        nodes.iter().map(|n| C::from_node(*n, source)).collect()
+    }
+
+    /// A query over the node kinds of a grammar whose matches are converted with `C`.
+    pub struct Query<C> {
+        query: tree_sitter::Query,
+        capture_indices: Vec<Option<u32>>,
+        _captures: PhantomData<C>,
+    }
+
+    impl<C> Query<C> {
+        pub fn new(
+            language: tree_sitter::Language,
+            source: &str,
+        ) -> Result<Query<C>, tree_sitter::QueryError>
+        where
+            C: Captures<'static>,
+        {
+            let query = tree_sitter::Query::new(language, source)?;
+            let capture_indices = C::NAMES
+                .iter()
+                .map(|name| query.capture_index_for_name(name))
+                .collect();
+            Ok(Query {
+                query,
+                capture_indices,
+                _captures: PhantomData,
+            })
+        }
+
+        /// The underlying Tree Sitter query, which uses the node kinds of the grammar.
+        pub fn query(&self) -> &tree_sitter::Query {
+            &self.query
+        }
+
+        /// Runs the query on the tree below `node`, returning the captures of each match.
+        pub fn matches<'tree>(
+            &self,
+            node: tree_sitter::Node<'tree>,
+            source: &'tree str,
+        ) -> Vec<C::Output>
+        where
+            C: Captures<'tree>,
+        {
+            let mut cursor = tree_sitter::QueryCursor::new();
+            cursor
+                .matches(&self.query, node, source.as_bytes())
+                .map(|m| {
+                    let mut nodes = vec![vec![]; self.capture_indices.len()];
+                    for capture in m.captures {
+                        if let Some(i) = self
+                            .capture_indices
+                            .iter()
+                            .position(|index| *index == Some(capture.index))
+                        {
+                            nodes[i].push(capture.node);
+                        }
+                    }
+
+                    C::from_nodes(&nodes, source.as_bytes())
+                })
+                .collect()
+        }
+    }
+}
+
+/// Syntax highlighting with the highlights query generated from `#[rust_sitter::highlight]`
+/// annotations, which backs the `highlight` function of grammars that have them.
+pub mod highlight {
+    #[cfg(feature = "tree-sitter-standard")]
+    use tree_sitter_runtime_standard as tree_sitter;
+
+    #[cfg(feature = "tree-sitter-c2rust")]
+    use tree_sitter_runtime_c2rust as tree_sitter;
+
+    /// The start and end byte offsets of a highlighted range, as in [`Spanned`](crate::Spanned).
+    pub type Span = (usize, usize);
+
+    /// The name of a highlight, such as `keyword` or `comment`.
+    pub type HighlightName = &'static str;
+
+    /// Highlights the tree below `node` with a highlights query, whose capture names must be
+    /// among `names`. The result covers each highlighted range once, in order: when highlights
+    /// are nested, the innermost one is used, and when a node is captured more than once, the
+    /// first pattern wins, as with `tree-sitter highlight`.
+    pub fn highlight_node(
+        query: &tree_sitter::Query,
+        names: &[HighlightName],
+        node: tree_sitter::Node,
+        source: &[u8],
+    ) -> Vec<(Span, HighlightName)> {
+        let capture_names: Vec<HighlightName> = query
+            .capture_names()
+            .iter()
+            .map(|name| {
+                *names
+                    .iter()
+                    .find(|n| *n == name)
+                    .unwrap_or_else(|| panic!("Unknown highlight name `{name}`"))
+            })
+            .collect();
+
+        let mut captures: Vec<(usize, usize, usize, HighlightName)> = vec![];
+        let mut cursor = tree_sitter::QueryCursor::new();
+        for m in cursor.matches(query, node, source) {
+            for capture in m.captures {
+                captures.push((
+                    capture.node.start_byte(),
+                    capture.node.end_byte(),
+                    m.pattern_index,
+                    capture_names[capture.index as usize],
+                ));
+            }
+        }
+
+        // Outer ranges come before the ranges they contain, and for the same range the first
+        // pattern comes first.
+        captures
+            .sort_by_key(|(start, end, pattern, _)| (*start, std::cmp::Reverse(*end), *pattern));
+
+        let mut out = vec![];
+        let mut stack: Vec<(usize, usize, HighlightName)> = vec![];
+        let mut position = 0;
+        for (start, end, _, name) in captures {
+            while let Some((_, top_end, top_name)) = stack.last().copied() {
+                if top_end > start {
+                    break;
+                }
+                push_highlight(&mut out, &mut position, top_end, top_name);
+                stack.pop();
+            }
+
+            if let Some((top_start, top_end, top_name)) = stack.last().copied() {
+                if (top_start, top_end) == (start, end) || end > top_end {
+                    continue;
+                }
+                push_highlight(&mut out, &mut position, start, top_name);
+            }
+
+            position = start;
+            stack.push((start, end, name));
+        }
+
+        while let Some((_, end, name)) = stack.pop() {
+            push_highlight(&mut out, &mut position, end, name);
+        }
+
+        out
+    }
+
+    /// Highlights the source from the current position up to `end`, if it is not empty.
+    fn push_highlight(
+        out: &mut Vec<(Span, HighlightName)>,
+        position: &mut usize,
+        end: usize,
+        name: HighlightName,
+    ) {
+        if *position < end {
+            out.push(((*position, end), name));
+            *position = end;
+        }
+    }
+}
+
+/// Typed parsing for Tree Sitter languages that are not defined with Rust Sitter, using the
+/// types and [`Extract`](crate::Extract) implementations generated from their `node-types.json`
+/// by `rust_sitter_tool::generate_node_types_bindings`.
+pub mod external {
+    use crate::errors::{collect_parsing_errors, ParseError};
+    use crate::Extract;
+
+    #[cfg(feature = "tree-sitter-standard")]
+    use tree_sitter_runtime_standard as tree_sitter;
+
+    #[cfg(feature = "tree-sitter-c2rust")]
+    use tree_sitter_runtime_c2rust as tree_sitter;
+
+    /// Parses `input` with the given language, converting the root node into `T`.
+    pub fn parse<'a, T: Extract<'a, T>>(
+        language: tree_sitter::Language,
+        input: &'a str,
+    ) -> Result<T, Vec<ParseError>> {
+        let mut parser = tree_sitter::Parser::new();
+        parser.set_language(language).unwrap();
+        let tree = parser.parse(input, None).unwrap();
+        let root_node = tree.root_node();
+
+        if root_node.has_error() {
+            let mut errors = vec![];
+            collect_parsing_errors(&root_node, input.as_bytes(), &mut errors);
+            Err(errors)
+        } else {
+            T::try_extract(Some(root_node), input.as_bytes(), 0, None).map_err(|e| vec![e])
+        }
+    }
+
+    /// Converts the child in the field `name`, which may be missing if `T` is an `Option`.
+    pub fn field<'a, T: Extract<'a, U>, U>(
+        node: tree_sitter::Node,
+        name: &str,
+        source: &'a [u8],
+    ) -> Result<U, ParseError> {
+        T::try_extract(
+            node.child_by_field_name(name),
+            source,
+            node.start_byte(),
+            None,
+        )
+    }
+
+    /// Converts all the children in the field `name`.
+    pub fn fields<'a, T: Extract<'a, U>, U>(
+        node: tree_sitter::Node,
+        name: &str,
+        source: &'a [u8],
+    ) -> Result<Vec<U>, ParseError> {
+        let mut cursor = node.walk();
+        let children: Vec<_> = node.children_by_field_name(name, &mut cursor).collect();
+        children
+            .into_iter()
+            .map(|child| T::try_extract(Some(child), source, node.start_byte(), None))
+            .collect()
+    }
+
+    /// The named children that are not in a field (and are not extras).
+    fn unnamed_children(node: tree_sitter::Node) -> Vec<tree_sitter::Node> {
+        let mut cursor = node.walk();
+        let mut out = vec![];
+        if cursor.goto_first_child() {
+            loop {
+                let child = cursor.node();
+                if cursor.field_name().is_none() && child.is_named() && !child.is_extra() {
+                    out.push(child);
+                }
+
+                if !cursor.goto_next_sibling() {
+                    break;
+                }
+            }
+        }
+        out
+    }
+
+    /// Converts the first child that is not in a field, which may be missing if `T` is an
+    /// `Option`.
+    pub fn child<'a, T: Extract<'a, U>, U>(
+        node: tree_sitter::Node,
+        source: &'a [u8],
+    ) -> Result<U, ParseError> {
+        T::try_extract(
+            unnamed_children(node).first().copied(),
+            source,
+            node.start_byte(),
+            None,
+        )
+    }
+
+    /// Converts all the children that are not in a field.
+    pub fn children<'a, T: Extract<'a, U>, U>(
+        node: tree_sitter::Node,
+        source: &'a [u8],
+    ) -> Result<Vec<U>, ParseError> {
+        unnamed_children(node)
+            .into_iter()
+            .map(|child| T::try_extract(Some(child), source, node.start_byte(), None))
+            .collect()
+    }
+}
+
+/// Turning grammar values back into source text. Implementations of [`Unparse`](unparse::Unparse)
+/// are generated for grammars with `unparse = true`.
+pub mod unparse {
+    use crate::pretty::{self, Doc};
+
+    /// A break requested before the next token.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Break {
+        None,
+        Soft,
+        Hard,
+    }
+
+    /// Content that is being unparsed, and how it is wrapped once it ends.
+    struct Frame {
+        wrap: Option<fn(Doc) -> Doc>,
+        docs: Vec<Doc>,
+    }
+
+    /// Accumulates the tokens of unparsed values, along with the layout given by
+    /// `#[rust_sitter::format]` annotations, inserting a separator between tokens.
+    pub struct Unparser {
+        frames: Vec<Frame>,
+        has_tokens: bool,
+        pending: Break,
+        no_space: bool,
+        separator: String,
+        width: Option<usize>,
+    }
+
+    impl Unparser {
+        pub fn new(separator: &str) -> Unparser {
+            Unparser {
+                frames: vec![Frame {
+                    wrap: None,
+                    docs: vec![],
+                }],
+                has_tokens: false,
+                pending: Break::None,
+                no_space: false,
+                separator: separator.to_string(),
+                width: None,
+            }
+        }
+
+        /// Lays out the finished source within `width` columns where possible, following the
+        /// `#[rust_sitter::format]` annotations, instead of on as few lines as possible.
+        pub fn width(mut self, width: usize) -> Unparser {
+            self.width = Some(width);
+            self
+        }
+
+        fn push(&mut self, doc: Doc) {
+            self.frames.last_mut().unwrap().docs.push(doc);
+        }
+
+        /// Writes a token, preceded by any requested line break, or otherwise by a space
+        /// unless it is the first token.
+        pub fn token(&mut self, text: &str) {
+            if !text.is_empty() {
+                match self.pending {
+                    Break::Hard => self.push(Doc::HardLine),
+                    Break::Soft => self.push(Doc::Line),
+                    Break::None if self.has_tokens && !self.no_space => self.push(Doc::Space),
+                    Break::None => {}
+                }
+
+                self.push(Doc::Text(text.to_string()));
+                self.has_tokens = true;
+                self.pending = Break::None;
+                self.no_space = false;
+            }
+        }
+
+        /// Always separates the next token with a newline, which ends line comments written as
+        /// trivia.
+        pub fn line_break(&mut self) {
+            if self.has_tokens {
+                self.pending = Break::Hard;
+            }
+        }
+
+        /// Separates the next token with a newline, unless the enclosing group fits on one line.
+        pub fn soft_line_break(&mut self) {
+            if self.has_tokens && self.pending == Break::None {
+                self.pending = Break::Soft;
+            }
+        }
+
+        /// Writes the next token directly after the previous one, without a space.
+        pub fn no_space(&mut self) {
+            self.no_space = true;
+        }
+
+        /// Starts a group, which is laid out on a single line if it fits.
+        pub fn begin_group(&mut self) {
+            self.frames.push(Frame {
+                wrap: Some(Doc::group),
+                docs: vec![],
+            });
+        }
+
+        /// Starts content that is indented on its own lines, unless the enclosing group fits on
+        /// one line.
+        pub fn begin_indent(&mut self) {
+            self.soft_line_break();
+            self.frames.push(Frame {
+                wrap: Some(Doc::indent),
+                docs: vec![],
+            });
+        }
+
+        /// Ends the innermost group or indented content.
+        pub fn end(&mut self) {
+            let frame = self.frames.pop().unwrap();
+            self.push(frame.wrap.unwrap()(Doc::Concat(frame.docs)));
+        }
+
+        /// Ends indented content, so that the next token starts a new line.
+        pub fn end_indent(&mut self) {
+            // The break before the next token belongs to the indented content, so that the
+            // text after it is not indented.
+            let pending = std::mem::replace(&mut self.pending, Break::None);
+            self.end();
+            self.pending = pending;
+            self.soft_line_break();
+        }
+
+        /// The document describing the layout of the tokens written so far.
+        pub fn into_doc(mut self) -> Doc {
+            while self.frames.len() > 1 {
+                self.end();
+            }
+
+            Doc::Concat(self.frames.pop().unwrap().docs)
+        }
+
+        /// Renders the tokens written so far into source text.
+        pub fn finish(self) -> String {
+            let separator = self.separator.clone();
+            let width = self.width;
+            let doc = self.into_doc();
+            match width {
+                Some(width) => pretty::render_separated(&doc, width, &separator),
+                None => pretty::render_flat(&doc, &separator),
+            }
+        }
+    }
+
+    /// A grammar value that can be written back as source text.
+    pub trait Unparse {
+        fn unparse(&self, out: &mut Unparser);
+    }
+
+    /// Unparses a value into a document that can be rendered with [`pretty::render`].
+    pub fn to_doc<T: Unparse + ?Sized>(value: &T) -> Doc {
+        let mut out = Unparser::new(" ");
+        value.unparse(&mut out);
+        out.into_doc()
+    }
+
+    /// Unparses a value into source text, with `separator` between each pair of tokens.
+    /// Only the line breaks needed to end line comments are kept.
+    pub fn to_string<T: Unparse + ?Sized>(value: &T, separator: &str) -> String {
+        let mut out = Unparser::new(separator);
+        value.unparse(&mut out);
+        out.finish()
+    }
+}
+
+/// A Wadler-style pretty printer, which lays out a [`Doc`](pretty::Doc) in a given width.
+pub mod pretty {
+    use crate::unparse::{Unparse, Unparser};
+
+    /// The number of spaces added for each level of indentation.
+    pub const INDENT_WIDTH: usize = 4;
+
+    /// A document describing the possible layouts of some text.
+    #[derive(Clone, Debug)]
+    pub enum Doc {
+        /// Text that does not contain newlines.
+        Text(String),
+        /// A space between two tokens.
+        Space,
+        /// A space if the enclosing group fits on one line, or a newline otherwise.
+        Line,
+        /// A newline, which also prevents the enclosing groups from fitting on one line.
+        HardLine,
+        Concat(Vec<Doc>),
+        /// Content whose lines are indented by one more level.
+        Indent(Box<Doc>),
+        /// Content that is laid out on one line if it fits.
+        Group(Box<Doc>),
+    }
+
+    impl Doc {
+        pub fn group(doc: Doc) -> Doc {
+            Doc::Group(Box::new(doc))
+        }
+
+        pub fn indent(doc: Doc) -> Doc {
+            Doc::Indent(Box::new(doc))
+        }
+
+        fn has_hard_line(&self) -> bool {
+            match self {
+                Doc::HardLine => true,
+                Doc::Concat(docs) => docs.iter().any(Doc::has_hard_line),
+                Doc::Indent(doc) | Doc::Group(doc) => doc.has_hard_line(),
+                _ => false,
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Mode {
+        Flat,
+        Break,
+    }
+
+    /// Whether the commands, followed by `rest` up to its next newline, fit in `remaining` columns.
+    fn fits<'d>(
+        mut remaining: usize,
+        mut cmds: Vec<(Mode, &'d Doc)>,
+        rest: &[(usize, Mode, &'d Doc)],
+        separator: &str,
+    ) -> bool {
+        let mut rest = rest.iter().rev();
+        loop {
+            let (mode, doc) = match cmds.pop() {
+                Some(cmd) => cmd,
+                None => match rest.next() {
+                    Some((_, mode, doc)) => (*mode, *doc),
+                    None => return true,
+                },
+            };
+
+            let width = match doc {
+                Doc::Text(text) => text.chars().count(),
+                Doc::Space => separator.chars().count(),
+                Doc::Line if mode == Mode::Flat => separator.chars().count(),
+                Doc::Line | Doc::HardLine => return true,
+                Doc::Concat(docs) => {
+                    cmds.extend(docs.iter().rev().map(|d| (mode, d)));
+                    0
+                }
+                Doc::Indent(doc) | Doc::Group(doc) => {
+                    cmds.push((mode, doc));
+                    0
+                }
+            };
+
+            remaining = match remaining.checked_sub(width) {
+                Some(remaining) => remaining,
+                None => return false,
+            };
+        }
+    }
+
+    fn render_in(doc: &Doc, width: usize, root: Mode, separator: &str) -> String {
+        let mut out = String::new();
+        let mut column = 0;
+        let mut cmds = vec![(0, root, doc)];
+        while let Some((indent, mode, doc)) = cmds.pop() {
+            match doc {
+                Doc::Text(text) => {
+                    out.push_str(text);
+                    column += text.chars().count();
+                }
+                Doc::Space => {
+                    out.push_str(separator);
+                    column += separator.chars().count();
+                }
+                Doc::Line if mode == Mode::Flat => {
+                    out.push_str(separator);
+                    column += separator.chars().count();
+                }
+                Doc::Line | Doc::HardLine => {
+                    out.push('\n');
+                    out.push_str(&" ".repeat(indent));
+                    column = indent;
+                }
+                Doc::Concat(docs) => cmds.extend(docs.iter().rev().map(|d| (indent, mode, d))),
+                Doc::Indent(doc) => cmds.push((indent + INDENT_WIDTH, mode, doc)),
+                Doc::Group(doc) => {
+                    let flat = mode == Mode::Flat
+                        || (!doc.has_hard_line()
+                            && fits(
+                                width.saturating_sub(column),
+                                vec![(Mode::Flat, doc)],
+                                &cmds,
+                                separator,
+                            ));
+                    cmds.push((indent, if flat { Mode::Flat } else { Mode::Break }, doc));
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Lays out a document so that lines stay within `width` columns where possible.
+    pub fn render(doc: &Doc, width: usize) -> String {
+        render_separated(doc, width, " ")
+    }
+
+    /// Like [`render`], with `separator` between tokens that are on the same line.
+    pub fn render_separated(doc: &Doc, width: usize, separator: &str) -> String {
+        render_in(doc, width, Mode::Break, separator)
+    }
+
+    /// Lays out a document on as few lines as possible, with `separator` between tokens.
+    pub fn render_flat(doc: &Doc, separator: &str) -> String {
+        render_in(doc, usize::MAX, Mode::Flat, separator)
+    }
+
+    /// Formats a value with the layout given by its `#[rust_sitter::format]` annotations,
+    /// keeping lines within `width` columns where possible.
+    pub fn format<T: Unparse + ?Sized>(value: &T, width: usize) -> String {
+        let mut out = Unparser::new(" ").width(width);
+        value.unparse(&mut out);
+        out.finish()
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+/// Support for generating random grammar values with the `arbitrary` crate, which is re-exported
+/// here. With the `arbitrary` feature, each grammar type implements [`Arbitrary`](::arbitrary::Arbitrary).
+pub mod arbitrary {
+    pub use ::arbitrary::*;
+
+    use regex_syntax::hir::{Class, Hir, HirKind, Literal, RepetitionKind, RepetitionRange};
+
+    /// How deeply grammar values are nested before only the shallowest alternatives are
+    /// generated, which keeps recursion through `Box` bounded.
+    pub const MAX_DEPTH: usize = 8;
+
+    /// The most repetitions generated for unbounded regex repetitions.
+    const MAX_REPETITIONS: u32 = 4;
+
+    /// How many matches of a pattern are generated for a leaf before giving up on finding one
+    /// that converts to the leaf type.
+    const MAX_LEAF_ATTEMPTS: usize = 16;
+
+    /// A grammar type that can be generated at a given depth in a tree. Implemented by the
+    /// `grammar` macro alongside `Arbitrary`, which starts at a depth of zero.
+    pub trait ArbitraryNode<'a>: Sized {
+        fn arbitrary_node(u: &mut Unstructured<'a>, depth: usize) -> Result<Self>;
+    }
+
+    /// Generates a `Vec` with at least `min` elements, and exactly `min` once `limited` is set.
+    pub fn arbitrary_vec<'a, T>(
+        u: &mut Unstructured<'a>,
+        min: usize,
+        limited: bool,
+        mut element: impl FnMut(&mut Unstructured<'a>) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let mut out = vec![];
+        while out.len() < min || (!limited && !u.is_empty() && u.arbitrary::<bool>()?) {
+            out.push(element(u)?);
+        }
+
+        Ok(out)
+    }
+
+    /// Generates a string that matches the regex `pattern`.
+    pub fn arbitrary_match(u: &mut Unstructured, pattern: &str) -> Result<String> {
+        let hir = regex_syntax::Parser::new()
+            .parse(pattern)
+            .map_err(|_| Error::IncorrectFormat)?;
+        let mut out = String::new();
+        gen_hir(u, &hir, &mut out)?;
+        Ok(out)
+    }
+
+    /// Generates a value of a leaf from random matches of the regex `pattern`, resampling matches
+    /// that `convert` rejects (such as numbers that overflow a narrow integer type).
+    pub fn arbitrary_leaf<T>(
+        u: &mut Unstructured,
+        pattern: &str,
+        convert: impl Fn(&str) -> Option<T>,
+    ) -> Result<T> {
+        for _ in 0..MAX_LEAF_ATTEMPTS {
+            if let Some(value) = convert(&arbitrary_match(u, pattern)?) {
+                return Ok(value);
+            }
+        }
+
+        Err(Error::IncorrectFormat)
+    }
+
+    fn gen_hir(u: &mut Unstructured, hir: &Hir, out: &mut String) -> Result<()> {
+        match hir.kind() {
+            HirKind::Empty | HirKind::Anchor(_) | HirKind::WordBoundary(_) => {}
+            HirKind::Literal(Literal::Unicode(c)) => out.push(*c),
+            HirKind::Literal(Literal::Byte(b)) => out.push(*b as char),
+            HirKind::Class(class) => out.extend(gen_class(u, class)?),
+            HirKind::Repetition(repetition) => {
+                let (min, max) = match &repetition.kind {
+                    RepetitionKind::ZeroOrOne => (0, 1),
+                    RepetitionKind::ZeroOrMore => (0, MAX_REPETITIONS),
+                    RepetitionKind::OneOrMore => (1, MAX_REPETITIONS),
+                    RepetitionKind::Range(RepetitionRange::Exactly(n)) => (*n, *n),
+                    RepetitionKind::Range(RepetitionRange::AtLeast(n)) => (*n, n + MAX_REPETITIONS),
+                    RepetitionKind::Range(RepetitionRange::Bounded(m, n)) => (*m, *n),
+                };
+
+                for _ in 0..u.int_in_range(min..=max)? {
+                    gen_hir(u, &repetition.hir, out)?;
+                }
+            }
+            HirKind::Group(group) => gen_hir(u, &group.hir, out)?,
+            HirKind::Concat(hirs) => {
+                for hir in hirs {
+                    gen_hir(u, hir, out)?;
+                }
+            }
+            HirKind::Alternation(hirs) => {
+                let index = u.choose_index(hirs.len())?;
+                gen_hir(u, &hirs[index], out)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Picks a character from a class, preferring printable ASCII characters when the class
+    /// has any.
+    fn gen_class(u: &mut Unstructured, class: &Class) -> Result<Option<char>> {
+        let ranges: Vec<(u32, u32)> = match class {
+            Class::Unicode(class) => class
+                .iter()
+                .map(|r| (r.start() as u32, r.end() as u32))
+                .collect(),
+            Class::Bytes(class) => class
+                .iter()
+                .map(|r| (r.start() as u32, r.end() as u32))
+                .collect(),
+        };
+
+        let printable: Vec<(u32, u32)> = ranges
+            .iter()
+            .map(|(start, end)| ((*start).max(' ' as u32), (*end).min('~' as u32)))
+            .filter(|(start, end)| start <= end)
+            .collect();
+        let ranges = if printable.is_empty() {
+            ranges
+        } else {
+            printable
+        };
+
+        let total: u32 = ranges.iter().map(|(start, end)| end - start + 1).sum();
+        if total == 0 {
+            return Ok(None);
+        }
+
+        let mut index = u.int_in_range(0..=total - 1)?;
+        for (start, end) in ranges {
+            if index <= end - start {
+                return Ok(char::from_u32(start + index).or(char::from_u32(start)));
+            }
+            index -= end - start + 1;
+        }
+
+        Ok(None)
+    }
+}
+
+pub mod errors {
+    #[cfg(feature = "tree-sitter-standard")]
+    use tree_sitter_runtime_standard as tree_sitter;
+
+    #[cfg(feature = "tree-sitter-c2rust")]
+    use tree_sitter_runtime_c2rust as tree_sitter;
+
+    #[derive(Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    /// An explanation for an error that occurred during parsing.
+    pub enum ParseErrorReason {
+        /// The parser did not expect to see some token.
+        UnexpectedToken(String),
+        /// Tree Sitter failed to parse a specific intermediate node.
+        /// The underlying failures are in the vector.
+        FailedNode(Vec<ParseError>),
+        /// The parser expected a specific token, but it was not found.
+        MissingToken(String),
+        /// The text of a leaf could not be converted into its Rust type with `FromStr`.
+        /// Contains the message of the conversion error.
+        InvalidLeaf(String),
+    }
+
+    #[derive(Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    /// An error that occurred during parsing.
+    pub struct ParseError {
+        pub reason: ParseErrorReason,
+        /// Inclusive start of the error.
+        pub start: usize,
+        /// Exclusive end of the error.
+        pub end: usize,
+    }
+
+    /// Given the root node of a Tree Sitter parsing result, accumulates all
+    /// errors that were emitted.
+    pub fn collect_parsing_errors(
+        node: &tree_sitter::Node,
+        source: &[u8],
+        errors: &mut Vec<ParseError>,
+    ) {
+        if node.is_error() {
+            if node.child(0).is_some() {
+                // we managed to parse some children, so collect underlying errors for this node
+                let mut inner_errors = vec![];
+                let mut cursor = node.walk();
+                node.children(&mut cursor)
+                    .for_each(|c| collect_parsing_errors(&c, source, &mut inner_errors));
+
+                errors.push(ParseError {
+                    reason: ParseErrorReason::FailedNode(inner_errors),
+                    start: node.start_byte(),
+                    end: node.end_byte(),
+                })
+            } else {
+                let contents = node.utf8_text(source).unwrap();
+                if !contents.is_empty() {
+                    errors.push(ParseError {
+                        reason: ParseErrorReason::UnexpectedToken(contents.to_string()),
+                        start: node.start_byte(),
+                        end: node.end_byte(),
+                    })
+                } else {
+                    errors.push(ParseError {
+                        reason: ParseErrorReason::FailedNode(vec![]),
+                        start: node.start_byte(),
+                        end: node.end_byte(),
+                    })
+                }
+            }
+        } else if node.is_missing() {
+            errors.push(ParseError {
+                reason: ParseErrorReason::MissingToken(node.kind().to_string()),
+                start: node.start_byte(),
+                end: node.end_byte(),
+            })
+        } else if node.has_error() {
             let mut cursor = node.walk();
             node.children(&mut cursor)
                 .for_each(|c| collect_parsing_errors(&c, source, errors));
         }
     }
 }
+
+/// Corpus tests for grammars, in the style of the `test/corpus` files of Tree Sitter grammars.
+///
+/// A corpus file contains a list of tests, each with a name between two lines of `=`, followed
+/// by an input, a line of `-` and the expected output. As in Tree Sitter, the last line of `-`
+/// in a test separates the input from the output, so inputs can contain lines of `-` too:
+///
+/// ```text
+/// ==================
+/// Subtraction
+/// ==================
+///
+/// 1 - 2
+///
+/// ---
+///
+/// Sub(
+///     Number(
+///         1,
+///     ),
+///     (),
+///     Number(
+///         2,
+///     ),
+/// )
+/// ```
+///
+/// The expected output is the pretty-printed `Debug` representation of the parsed value. Tests
+/// whose name is followed by an `:error` line expect the input to fail to parse instead, and
+/// their expected output is the `Debug` representation of the errors.
+pub mod testing {
+    use std::fmt::Debug;
+    use std::path::{Path, PathBuf};
+
+    /// The environment variable that makes [`run_corpus`] rewrite the expected outputs of the
+    /// corpus files with the actual ones, instead of failing, when it is set to `1`.
+    pub const UPDATE_ENV_VAR: &str = "RUST_SITTER_UPDATE_CORPUS";
+
+    /// The attribute marking tests whose input is expected to fail to parse.
+    const ERROR_ATTRIBUTE: &str = ":error";
+
+    struct CorpusTest {
+        /// The line of `=` around the name, kept to rewrite the file as it was.
+        header_rule: String,
+        name: String,
+        attributes: Vec<String>,
+        input: String,
+        /// The last line of `-` in the test, between the input and the expected output.
+        divider: String,
+        expected: String,
+    }
+
+    fn is_rule(line: &str, c: char) -> bool {
+        line.len() >= 3 && line.chars().all(|l| l == c)
+    }
+
+    fn parse_corpus(path: &Path, contents: &str) -> Vec<CorpusTest> {
+        let lines: Vec<&str> = contents.lines().collect();
+        let mut tests = vec![];
+        let mut i = 0;
+        while i < lines.len() {
+            if !is_rule(lines[i], '=') {
+                i += 1;
+                continue;
+            }
+
+            let header_rule = lines[i].to_string();
+            let name = lines
+                .get(i + 1)
+                .unwrap_or_else(|| panic!("Expected a test name in {}", path.display()))
+                .to_string();
+            i += 2;
+
+            let mut attributes = vec![];
+            while i < lines.len() && !is_rule(lines[i], '=') {
+                if !lines[i].trim().is_empty() {
+                    attributes.push(lines[i].trim().to_string());
+                }
+                i += 1;
+            }
+            i += 1;
+
+            let body_end = (i..lines.len())
+                .find(|j| is_rule(lines[*j], '='))
+                .unwrap_or(lines.len());
+            // Inputs can contain lines of `-` themselves, so the divider is the last one.
+            let divider = (i..body_end)
+                .rev()
+                .find(|j| is_rule(lines[*j], '-'))
+                .unwrap_or_else(|| {
+                    panic!(
+                        "Expected a line of `-` after the input of `{name}` in {}",
+                        path.display()
+                    )
+                });
+
+            tests.push(CorpusTest {
+                header_rule,
+                name,
+                attributes,
+                input: lines[i..divider].join("\n").trim_matches('\n').to_string(),
+                divider: lines[divider].to_string(),
+                expected: lines[divider + 1..body_end].join("\n").trim().to_string(),
+            });
+            i = body_end;
+        }
+
+        tests
+    }
+
+    fn write_corpus(tests: &[CorpusTest]) -> String {
+        tests
+            .iter()
+            .map(|test| {
+                let mut header = vec![test.header_rule.as_str(), test.name.as_str()];
+                header.extend(test.attributes.iter().map(String::as_str));
+                header.push(&test.header_rule);
+                format!(
+                    "{}\n\n{}\n\n{}\n\n{}\n",
+                    header.join("\n"),
+                    test.input,
+                    test.divider,
+                    test.expected
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Runs the tests of every `.txt` corpus file in `dir`, parsing each input with `parse`
+    /// (such as the `parse` function of a grammar) and comparing the result with the expected
+    /// output. All the tests are run before panicking with the list of failures.
+    ///
+    /// When the [`UPDATE_ENV_VAR`] environment variable is set to `1`, the corpus files are
+    /// rewritten with the actual outputs (adding or removing `:error` as needed) instead.
+    pub fn run_corpus<T: Debug, E: Debug>(
+        dir: impl AsRef<Path>,
+        parse: impl Fn(&str) -> Result<T, E>,
+    ) {
+        let dir = dir.as_ref();
+        let update = std::env::var(UPDATE_ENV_VAR).is_ok_and(|v| v == "1");
+
+        let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+            .unwrap_or_else(|e| panic!("Could not read the corpus {}: {e}", dir.display()))
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|e| e == "txt"))
+            .collect();
+        files.sort();
+        if files.is_empty() {
+            panic!("Could not find any corpus files in {}", dir.display());
+        }
+
+        let mut failures = vec![];
+        for path in files {
+            let contents = std::fs::read_to_string(&path).unwrap();
+            let mut tests = parse_corpus(&path, &contents);
+
+            for test in &mut tests {
+                let expects_error = test.attributes.iter().any(|a| a == ERROR_ATTRIBUTE);
+                let (is_error, actual) = match parse(&test.input) {
+                    Ok(value) => (false, format!("{value:#?}")),
+                    Err(errors) => (true, format!("{errors:#?}")),
+                };
+
+                if update {
+                    test.attributes.retain(|a| a != ERROR_ATTRIBUTE);
+                    if is_error {
+                        test.attributes.push(ERROR_ATTRIBUTE.to_string());
+                    }
+                    test.expected = actual;
+                } else if is_error != expects_error {
+                    let outcome = if is_error {
+                        "failed to parse with"
+                    } else {
+                        "parsed into"
+                    };
+                    failures.push(format!(
+                        "{} `{}`: expected the input to {}, but it {outcome}:\n{actual}",
+                        path.display(),
+                        test.name,
+                        if expects_error {
+                            "fail to parse"
+                        } else {
+                            "parse"
+                        },
+                    ));
+                } else if actual != test.expected {
+                    failures.push(format!(
+                        "{} `{}`: expected\n{}\nbut got\n{actual}",
+                        path.display(),
+                        test.name,
+                        test.expected
+                    ));
+                }
+            }
+
+            if update {
+                let updated = write_corpus(&tests);
+                if updated != contents {
+                    std::fs::write(&path, updated).unwrap();
+                }
+            }
+        }
+
+        if !failures.is_empty() {
+            panic!(
+                "{} corpus tests failed (set {UPDATE_ENV_VAR}=1 to update them):\n\n{}",
+                failures.len(),
+                failures.join("\n\n")
+            );
+        }
+    }
+}
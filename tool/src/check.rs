@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use syn::{parse_quote, Field, GenericArgument, Item, ItemEnum, ItemStruct, PathArguments, Type};
+use tree_sitter_cli::generate;
+
+use crate::{
+    collect_grammar_modules, generate_grammars_for_items, generate_rule_names, imported_grammars,
+    root_type,
+};
+
+/// Checks every Rust Sitter grammar found in the given module and recursive submodules,
+/// returning problems paired with the name of their grammar. This reports the conflicts that
+/// prevent Tree Sitter from generating a parser (with node kinds replaced by the Rust names
+/// they correspond to), and types that are never parsed because they are neither reachable
+/// from the root type nor extras.
+pub fn check_grammars(root_file: &Path) -> Vec<(String, String)> {
+    let items = syn_inline_mod::parse_and_inline_modules(root_file).items;
+    let mut modules = vec![];
+    items
+        .iter()
+        .for_each(|i| collect_grammar_modules(i, &mut modules));
+    let grammars = generate_grammars_for_items(&items, &imported_grammars());
+    let rule_names = generate_rule_names(root_file);
+
+    let mut out = vec![];
+    for ((module, grammar), (name, rule_names)) in modules.iter().zip(grammars).zip(rule_names) {
+        if let Err(e) = generate::generate_parser_for_grammar(&grammar.to_string()) {
+            out.push((name.clone(), rename_kinds(&e.to_string(), &rule_names)));
+        }
+
+        for ty in unreachable_types(&module.content.as_ref().unwrap().1) {
+            out.push((
+                name.clone(),
+                format!(
+                    "The type `{ty}` is not reachable from the root type, so it is never parsed"
+                ),
+            ));
+        }
+    }
+
+    out
+}
+
+/// Replaces the node kinds in a message from Tree Sitter with the Rust names they correspond to.
+fn rename_kinds(message: &str, rule_names: &[(String, String)]) -> String {
+    let mut out = String::new();
+    let mut word = String::new();
+    for c in message.chars().chain(std::iter::once('\n')) {
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+            continue;
+        }
+
+        match rule_names.iter().find(|(kind, _)| *kind == word) {
+            Some((_, rust_name)) => out.push_str(rust_name),
+            None => out.push_str(&word),
+        }
+        word.clear();
+        out.push(c);
+    }
+
+    out.pop();
+    out
+}
+
+/// Collects the names in a type that may refer to other types of the grammar, such as `Vec`
+/// and `Expression` in `Vec<Box<Expression>>`.
+fn type_names(ty: &Type, out: &mut Vec<String>) {
+    match ty {
+        Type::Path(path) => path.path.segments.iter().for_each(|segment| {
+            out.push(segment.ident.to_string());
+            if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                args.args.iter().for_each(|arg| {
+                    if let GenericArgument::Type(ty) = arg {
+                        type_names(ty, out);
+                    }
+                });
+            }
+        }),
+        Type::Reference(reference) => type_names(&reference.elem, out),
+        Type::Tuple(tuple) => tuple.elems.iter().for_each(|ty| type_names(ty, out)),
+        Type::Array(array) => type_names(&array.elem, out),
+        Type::Paren(paren) => type_names(&paren.elem, out),
+        _ => {}
+    }
+}
+
+/// Finds the types of a grammar that are neither reachable from the root type nor extras, and
+/// so are never parsed.
+fn unreachable_types(items: &[Item]) -> Vec<String> {
+    let mut references: HashMap<String, Vec<String>> = HashMap::new();
+    let mut reachable = vec![root_type(items)];
+    items.iter().for_each(|item| {
+        let (ident, attrs, fields): (_, _, Vec<&Field>) = match item {
+            Item::Struct(s) => (&s.ident, &s.attrs, s.fields.iter().collect()),
+            Item::Enum(e) => (
+                &e.ident,
+                &e.attrs,
+                e.variants.iter().flat_map(|v| &v.fields).collect(),
+            ),
+            _ => return,
+        };
+
+        if attrs
+            .iter()
+            .any(|attr| attr.path == parse_quote!(rust_sitter::extra))
+        {
+            reachable.push(ident.to_string());
+        }
+
+        let mut names = vec![];
+        fields
+            .iter()
+            .filter(|field| {
+                !field
+                    .attrs
+                    .iter()
+                    .any(|attr| attr.path == parse_quote!(rust_sitter::skip))
+            })
+            .for_each(|field| type_names(&field.ty, &mut names));
+        references.insert(ident.to_string(), names);
+    });
+
+    let mut stack = reachable.clone();
+    while let Some(current) = stack.pop() {
+        for name in references.get(&current).into_iter().flatten() {
+            if references.contains_key(name) && !reachable.contains(name) {
+                reachable.push(name.clone());
+                stack.push(name.clone());
+            }
+        }
+    }
+
+    items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Struct(ItemStruct { ident, .. }) | Item::Enum(ItemEnum { ident, .. }) => {
+                let name = ident.to_string();
+                (!reachable.contains(&name)).then_some(name)
+            }
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,624 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+use syn::{Attribute, Fields, Item, ItemMod, Lit, Meta};
+
+use crate::grammar_js::js_regex;
+use crate::{collect_grammar_modules, generate_extended_grammar, imported_grammars, TYPES_KEY};
+
+/// The documentation of a Rust Sitter grammar, as produced by [`generate_grammar_docs`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrammarDocs {
+    /// The name of the grammar.
+    pub name: String,
+    /// The EBNF definition of every type in the grammar, preceded by its doc comment.
+    pub ebnf: String,
+    /// The railroad diagram of every type in the grammar as an SVG document, paired with the
+    /// name of the type.
+    pub diagrams: Vec<(String, String)>,
+    /// A Markdown reference page with the doc comment, EBNF definition and railroad diagram of
+    /// every type. Diagrams are linked as `diagrams/<type>.svg`.
+    pub reference: String,
+}
+
+/// Generates the documentation of every Rust Sitter grammar found in the given module and
+/// recursive submodules: its definition in EBNF, a railroad diagram of each type and a
+/// Markdown reference page combining them with the doc comments of the grammar items.
+pub fn generate_grammar_docs(root_file: &Path) -> Vec<GrammarDocs> {
+    let root_file = syn_inline_mod::parse_and_inline_modules(root_file).items;
+    let mut modules = vec![];
+    root_file
+        .iter()
+        .for_each(|i| collect_grammar_modules(i, &mut modules));
+
+    let imported = imported_grammars();
+    modules
+        .iter()
+        .map(|m| {
+            let grammar = generate_extended_grammar(m, &modules, &imported, &mut vec![]);
+            grammar_docs(m, &grammar)
+        })
+        .collect()
+}
+
+/// Writes the documentation of every Rust Sitter grammar found in the given module and
+/// recursive submodules to `<out_dir>/<grammar name>`, as `grammar.ebnf`, `reference.md`
+/// and `diagrams/<type>.svg`. Returns the documentation directories.
+pub fn write_grammar_docs(root_file: &Path, out_dir: &Path) -> Vec<PathBuf> {
+    generate_grammar_docs(root_file)
+        .iter()
+        .map(|docs| {
+            let docs_dir = out_dir.join(&docs.name);
+            let diagrams_dir = docs_dir.join("diagrams");
+            std::fs::create_dir_all(&diagrams_dir).unwrap();
+
+            std::fs::write(docs_dir.join("grammar.ebnf"), &docs.ebnf).unwrap();
+            std::fs::write(docs_dir.join("reference.md"), &docs.reference).unwrap();
+            for (name, svg) in &docs.diagrams {
+                std::fs::write(diagrams_dir.join(format!("{name}.svg")), svg).unwrap();
+            }
+
+            docs_dir
+        })
+        .collect()
+}
+
+/// Generates the documentation of a grammar module, given the grammar generated for it by
+/// `generate_extended_grammar`, which lists the types of the grammar and of its bases.
+pub(crate) fn grammar_docs(module: &ItemMod, grammar: &Value) -> GrammarDocs {
+    let name = grammar["name"].as_str().unwrap().to_string();
+    let rules = grammar["rules"].as_object().unwrap();
+    let items = &module.content.as_ref().unwrap().1;
+
+    let types: HashSet<String> = grammar[TYPES_KEY]
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect();
+    let converter = Converter {
+        rules,
+        types: &types,
+    };
+
+    let definitions: Vec<Definition> = items
+        .iter()
+        .filter_map(|item| converter.definition(item))
+        .collect();
+
+    let ebnf = definitions
+        .iter()
+        .map(|d| match &d.doc {
+            Some(doc) => format!("/* {} */\n{}", doc.replace("*/", "* /"), d.ebnf()),
+            None => d.ebnf(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let mut reference = format!("# `{name}`\n");
+    if let Some(doc) = doc_comment(&module.attrs) {
+        reference.push_str(&format!("\n{doc}\n"));
+    }
+    for definition in &definitions {
+        reference.push_str(&format!("\n## `{}`\n", definition.name));
+        if let Some(doc) = &definition.doc {
+            reference.push_str(&format!("\n{doc}\n"));
+        }
+        reference.push_str(&format!("\n```ebnf\n{}\n```\n", definition.ebnf()));
+        reference.push_str(&format!("\n![`{0}`](diagrams/{0}.svg)\n", definition.name));
+        if !definition.member_docs.is_empty() {
+            reference.push('\n');
+            for (member, doc) in &definition.member_docs {
+                reference.push_str(&format!("- `{member}`: {}\n", doc.replace('\n', "\n  ")));
+            }
+        }
+    }
+
+    GrammarDocs {
+        name,
+        ebnf: format!("{ebnf}\n"),
+        diagrams: definitions
+            .iter()
+            .map(|d| (d.name.clone(), railroad_diagram(&d.diagram_rule())))
+            .collect(),
+        reference,
+    }
+}
+
+/// The text of the `///` doc comments in a list of attributes, if there are any.
+fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("doc"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::NameValue(meta)) => match meta.lit {
+                Lit::Str(s) => Some(s.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .map(|line| line.strip_prefix(' ').map_or(line.clone(), str::to_string))
+        .collect();
+
+    let doc = lines.join("\n").trim().to_string();
+    (!doc.is_empty()).then_some(doc)
+}
+
+/// A grammar rule reduced to the constructs of EBNF and railroad diagrams, with the rules
+/// generated for leaves, variants and repeats inlined.
+#[derive(Debug, Clone)]
+enum Rule {
+    Text(String),
+    Pattern(String),
+    Type(String),
+    Seq(Vec<Rule>),
+    Choice(Vec<Rule>),
+    Optional(Box<Rule>),
+    Repeat(Box<Rule>),
+    Repeat1(Box<Rule>),
+}
+
+/// The definition of a grammar type: a single rule for structs, or one alternative per
+/// variant for enums.
+struct Definition {
+    name: String,
+    doc: Option<String>,
+    alternatives: Vec<(Option<String>, Rule)>,
+    /// The doc comments of the fields or variants of the type.
+    member_docs: Vec<(String, String)>,
+}
+
+impl Definition {
+    /// The EBNF production of the type, with each variant of an enum on its own line,
+    /// followed by a comment with its name.
+    fn ebnf(&self) -> String {
+        let alternatives: Vec<String> = self
+            .alternatives
+            .iter()
+            .map(|(_, rule)| ebnf(rule, false))
+            .collect();
+        let width = alternatives.iter().map(String::len).max().unwrap_or(0);
+        let indent = " ".repeat(self.name.len() + 3);
+
+        alternatives
+            .iter()
+            .zip(&self.alternatives)
+            .enumerate()
+            .map(|(i, (text, (variant, _)))| {
+                let prefix = if i == 0 {
+                    format!("{} ::= ", self.name)
+                } else {
+                    format!("{indent}| ")
+                };
+                match variant {
+                    Some(variant) if self.alternatives.len() > 1 => {
+                        format!("{prefix}{text:width$}  /* {variant} */")
+                    }
+                    _ => format!("{prefix}{text}"),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn diagram_rule(&self) -> Rule {
+        if self.alternatives.len() == 1 {
+            self.alternatives[0].1.clone()
+        } else {
+            Rule::Choice(self.alternatives.iter().map(|(_, r)| r.clone()).collect())
+        }
+    }
+}
+
+struct Converter<'a> {
+    rules: &'a Map<String, Value>,
+    types: &'a HashSet<String>,
+}
+
+impl Converter<'_> {
+    fn definition(&self, item: &Item) -> Option<Definition> {
+        match item {
+            Item::Struct(s) => {
+                let name = s.ident.to_string();
+                let rule = self.rules.get(&name)?;
+                Some(Definition {
+                    doc: doc_comment(&s.attrs),
+                    alternatives: vec![(None, self.convert(rule))],
+                    member_docs: field_docs(&s.fields, ""),
+                    name,
+                })
+            }
+            Item::Enum(e) => {
+                let name = e.ident.to_string();
+                self.rules.get(&name)?;
+                let alternatives = e
+                    .variants
+                    .iter()
+                    .filter_map(|v| {
+                        let rule = self.rules.get(&format!("{name}_{}", v.ident))?;
+                        Some((Some(v.ident.to_string()), self.convert(rule)))
+                    })
+                    .collect();
+                let member_docs = e
+                    .variants
+                    .iter()
+                    .flat_map(|v| {
+                        let variant = v.ident.to_string();
+                        doc_comment(&v.attrs)
+                            .map(|doc| (variant.clone(), doc))
+                            .into_iter()
+                            .chain(field_docs(&v.fields, &format!("{variant}::")))
+                    })
+                    .collect();
+                Some(Definition {
+                    doc: doc_comment(&e.attrs),
+                    alternatives,
+                    member_docs,
+                    name,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether a symbol refers to a type of the grammar or of its bases, which is referenced
+    /// by name rather than inlined.
+    fn is_type(&self, name: &str) -> bool {
+        self.types.contains(name)
+    }
+
+    fn convert(&self, rule: &Value) -> Rule {
+        match rule["type"].as_str().unwrap() {
+            "BLANK" => Rule::Seq(vec![]),
+            "STRING" => Rule::Text(rule["value"].as_str().unwrap().to_string()),
+            "PATTERN" => Rule::Pattern(rule["value"].as_str().unwrap().to_string()),
+            "SYMBOL" => {
+                let name = rule["name"].as_str().unwrap();
+                match self.rules.get(name) {
+                    Some(inlined) if !self.is_type(name) => self.convert(inlined),
+                    _ => Rule::Type(name.to_string()),
+                }
+            }
+            "SEQ" => {
+                let mut members = vec![];
+                for member in rule["members"].as_array().unwrap() {
+                    match self.convert(member) {
+                        Rule::Seq(inner) => members.extend(inner),
+                        other => members.push(other),
+                    }
+                }
+                if members.len() == 1 {
+                    members.pop().unwrap()
+                } else {
+                    Rule::Seq(members)
+                }
+            }
+            "CHOICE" => {
+                let all = rule["members"].as_array().unwrap();
+                let mut members: Vec<Rule> = all
+                    .iter()
+                    .filter(|m| m["type"] != "BLANK")
+                    .map(|m| self.convert(m))
+                    .collect();
+                let choice = if members.len() == 1 {
+                    members.pop().unwrap()
+                } else {
+                    Rule::Choice(members)
+                };
+
+                if all.iter().any(|m| m["type"] == "BLANK") {
+                    match choice {
+                        Rule::Repeat1(content) => Rule::Repeat(content),
+                        other => Rule::Optional(Box::new(other)),
+                    }
+                } else {
+                    choice
+                }
+            }
+            "REPEAT" => Rule::Repeat(Box::new(self.convert(&rule["content"]))),
+            "REPEAT1" => Rule::Repeat1(Box::new(self.convert(&rule["content"]))),
+            "FIELD" | "PREC" | "PREC_LEFT" | "PREC_RIGHT" | "PREC_DYNAMIC" | "TOKEN"
+            | "IMMEDIATE_TOKEN" | "ALIAS" => self.convert(&rule["content"]),
+            other => panic!("Unsupported rule type `{other}`"),
+        }
+    }
+}
+
+/// The doc comments of the documented fields, named `{prefix}{field}`.
+fn field_docs(fields: &Fields, prefix: &str) -> Vec<(String, String)> {
+    fields
+        .iter()
+        .enumerate()
+        .filter_map(|(i, field)| {
+            let name = field
+                .ident
+                .as_ref()
+                .map_or(i.to_string(), |ident| ident.to_string());
+            doc_comment(&field.attrs).map(|doc| (format!("{prefix}{name}"), doc))
+        })
+        .collect()
+}
+
+/// Renders a rule in EBNF, parenthesizing choices that are `nested` in a sequence.
+fn ebnf(rule: &Rule, nested: bool) -> String {
+    let postfix = |content: &Rule, operator: &str| {
+        let content = match content {
+            Rule::Seq(members) if members.len() > 1 => format!("({})", ebnf(content, false)),
+            Rule::Choice(_) | Rule::Optional(_) | Rule::Repeat(_) | Rule::Repeat1(_) => {
+                format!("({})", ebnf(content, false))
+            }
+            _ => ebnf(content, true),
+        };
+        format!("{content}{operator}")
+    };
+
+    match rule {
+        Rule::Text(text) if text.contains('"') => format!("'{text}'"),
+        Rule::Text(text) => format!("\"{text}\""),
+        Rule::Pattern(pattern) => js_regex(pattern),
+        Rule::Type(name) => name.clone(),
+        Rule::Seq(members) if members.is_empty() => "()".to_string(),
+        Rule::Seq(members) => members
+            .iter()
+            .map(|m| ebnf(m, true))
+            .collect::<Vec<_>>()
+            .join(" "),
+        Rule::Choice(members) => {
+            let choice = members
+                .iter()
+                .map(|m| ebnf(m, false))
+                .collect::<Vec<_>>()
+                .join(" | ");
+            if nested {
+                format!("({choice})")
+            } else {
+                choice
+            }
+        }
+        Rule::Optional(content) => postfix(content, "?"),
+        Rule::Repeat(content) => postfix(content, "*"),
+        Rule::Repeat1(content) => postfix(content, "+"),
+    }
+}
+
+/// The radius of the arcs joining the lines of railroad diagrams.
+const ARC: i32 = 10;
+/// The vertical space between the branches of a choice.
+const BRANCH_GAP: i32 = 10;
+/// The length of the lines between the items of a sequence.
+const SEQ_GAP: i32 = 10;
+const BOX_HEIGHT: i32 = 22;
+const CHAR_WIDTH: i32 = 8;
+const PADDING: i32 = 20;
+
+/// The extent of a rendered rule: its width and how far it reaches above and below the line
+/// it is drawn on.
+#[derive(Clone, Copy)]
+struct Extent {
+    width: i32,
+    up: i32,
+    down: i32,
+}
+
+fn label(rule: &Rule) -> Option<(String, &'static str)> {
+    match rule {
+        Rule::Text(text) => Some((text.clone(), "terminal")),
+        Rule::Pattern(pattern) => Some((js_regex(pattern), "pattern")),
+        Rule::Type(name) => Some((name.clone(), "nonterminal")),
+        _ => None,
+    }
+}
+
+/// The branches of an optional rule: a line skipping it, then the rule itself.
+fn optional_branches(content: &Rule) -> Vec<Option<&Rule>> {
+    vec![None, Some(content)]
+}
+
+fn extent(rule: &Rule) -> Extent {
+    match rule {
+        Rule::Text(_) | Rule::Pattern(_) | Rule::Type(_) => {
+            let (text, _) = label(rule).unwrap();
+            Extent {
+                width: text.chars().count() as i32 * CHAR_WIDTH + 2 * ARC,
+                up: BOX_HEIGHT / 2,
+                down: BOX_HEIGHT / 2,
+            }
+        }
+        Rule::Seq(members) => {
+            let extents: Vec<Extent> = members.iter().map(extent).collect();
+            Extent {
+                width: extents.iter().map(|e| e.width).sum::<i32>()
+                    + SEQ_GAP * (members.len() as i32 - 1).max(0),
+                up: extents.iter().map(|e| e.up).max().unwrap_or(0),
+                down: extents.iter().map(|e| e.down).max().unwrap_or(0),
+            }
+        }
+        Rule::Choice(members) => branches_extent(&members.iter().map(Some).collect::<Vec<_>>()).0,
+        Rule::Optional(content) => branches_extent(&optional_branches(content)).0,
+        Rule::Repeat(content) => extent(&Rule::Optional(Box::new(Rule::Repeat1(content.clone())))),
+        Rule::Repeat1(content) => {
+            let inner = extent(content);
+            Extent {
+                width: inner.width + 2 * ARC,
+                up: inner.up,
+                down: (inner.down + BRANCH_GAP).max(2 * ARC),
+            }
+        }
+    }
+}
+
+/// The extent of a choice between branches (where `None` is an empty branch), and the
+/// offset of each branch below the line of the first one.
+fn branches_extent(branches: &[Option<&Rule>]) -> (Extent, Vec<i32>) {
+    let extents: Vec<Extent> = branches
+        .iter()
+        .map(|b| {
+            b.map_or(
+                Extent {
+                    width: 0,
+                    up: 0,
+                    down: 0,
+                },
+                extent,
+            )
+        })
+        .collect();
+
+    let mut offsets = vec![0];
+    let mut bottom = extents[0].down;
+    for e in &extents[1..] {
+        let offset = (bottom + BRANCH_GAP + e.up).max(2 * ARC);
+        offsets.push(offset);
+        bottom = offset + e.down;
+    }
+
+    (
+        Extent {
+            width: extents.iter().map(|e| e.width).max().unwrap() + 4 * ARC,
+            up: extents[0].up,
+            down: bottom,
+        },
+        offsets,
+    )
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn line(out: &mut String, x: i32, y: i32, length: i32) {
+    if length > 0 {
+        out.push_str(&format!("<path d=\"M{x} {y}h{length}\"/>\n"));
+    }
+}
+
+/// Draws a rule from `x` to `x + width` on the line at `y`.
+fn render(rule: &Rule, x: i32, y: i32, out: &mut String) {
+    match rule {
+        Rule::Text(_) | Rule::Pattern(_) | Rule::Type(_) => {
+            let (text, class) = label(rule).unwrap();
+            let width = extent(rule).width;
+            let radius = if class == "nonterminal" { 0 } else { ARC };
+            out.push_str(&format!(
+                "<rect class=\"{class}\" x=\"{x}\" y=\"{}\" width=\"{width}\" height=\"{BOX_HEIGHT}\" rx=\"{radius}\"/>\n",
+                y - BOX_HEIGHT / 2
+            ));
+            out.push_str(&format!(
+                "<text class=\"{class}\" x=\"{}\" y=\"{}\">{}</text>\n",
+                x + width / 2,
+                y + 4,
+                escape_xml(&text)
+            ));
+        }
+        Rule::Seq(members) => {
+            let mut x = x;
+            for (i, member) in members.iter().enumerate() {
+                if i > 0 {
+                    line(out, x, y, SEQ_GAP);
+                    x += SEQ_GAP;
+                }
+                render(member, x, y, out);
+                x += extent(member).width;
+            }
+        }
+        Rule::Choice(members) => {
+            render_branches(&members.iter().map(Some).collect::<Vec<_>>(), x, y, out)
+        }
+        Rule::Optional(content) => render_branches(&optional_branches(content), x, y, out),
+        Rule::Repeat(content) => render(
+            &Rule::Optional(Box::new(Rule::Repeat1(content.clone()))),
+            x,
+            y,
+            out,
+        ),
+        Rule::Repeat1(content) => {
+            let inner = extent(content);
+            let down = extent(rule).down;
+            line(out, x, y, ARC);
+            render(content, x + ARC, y, out);
+            line(out, x + ARC + inner.width, y, ARC);
+            out.push_str(&format!(
+                "<path d=\"M{} {y}a{ARC} {ARC} 0 0 1 {ARC} {ARC}v{}a{ARC} {ARC} 0 0 1 -{ARC} {ARC}h-{}a{ARC} {ARC} 0 0 1 -{ARC} -{ARC}v-{}a{ARC} {ARC} 0 0 1 {ARC} -{ARC}\"/>\n",
+                x + ARC + inner.width,
+                down - 2 * ARC,
+                inner.width,
+                down - 2 * ARC,
+            ));
+        }
+    }
+}
+
+fn render_branches(branches: &[Option<&Rule>], x: i32, y: i32, out: &mut String) {
+    let (total, offsets) = branches_extent(branches);
+    let inner_x = x + 2 * ARC;
+    let end_x = x + total.width;
+
+    for (branch, offset) in branches.iter().zip(offsets) {
+        let width = branch.map_or(0, |b| extent(b).width);
+        if offset == 0 {
+            line(out, x, y, 2 * ARC);
+            line(out, inner_x + width, y, end_x - inner_x - width);
+        } else {
+            let branch_y = y + offset;
+            let vertical = offset - 2 * ARC;
+            out.push_str(&format!(
+                "<path d=\"M{x} {y}a{ARC} {ARC} 0 0 1 {ARC} {ARC}v{vertical}a{ARC} {ARC} 0 0 0 {ARC} {ARC}\"/>\n"
+            ));
+            line(
+                out,
+                inner_x + width,
+                branch_y,
+                end_x - 2 * ARC - inner_x - width,
+            );
+            out.push_str(&format!(
+                "<path d=\"M{} {branch_y}a{ARC} {ARC} 0 0 0 {ARC} -{ARC}v-{vertical}a{ARC} {ARC} 0 0 1 {ARC} -{ARC}\"/>\n",
+                end_x - 2 * ARC
+            ));
+        }
+
+        if let Some(branch) = branch {
+            render(branch, inner_x, y + offset, out);
+        }
+    }
+}
+
+/// Renders a rule as an SVG railroad diagram, with a bar marking its start and end.
+fn railroad_diagram(rule: &Rule) -> String {
+    let rule_extent = extent(rule);
+    let width = rule_extent.width + 2 * SEQ_GAP + 2 * PADDING;
+    let height = rule_extent.up + rule_extent.down + 2 * PADDING;
+    let y = PADDING + rule_extent.up;
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n"
+    );
+    out.push_str(concat!(
+        "<style>\n",
+        "path { fill: none; stroke: #333; stroke-width: 1.5; }\n",
+        "rect { fill: #f4f4f4; stroke: #333; stroke-width: 1.5; }\n",
+        "rect.nonterminal { fill: #e4ecf7; }\n",
+        "text { font-family: monospace; font-size: 13px; text-anchor: middle; }\n",
+        "text.pattern { font-style: italic; }\n",
+        "</style>\n",
+    ));
+
+    out.push_str(&format!(
+        "<path d=\"M{PADDING} {}v{BOX_HEIGHT}\"/>\n",
+        y - BOX_HEIGHT / 2
+    ));
+    line(&mut out, PADDING, y, SEQ_GAP);
+    render(rule, PADDING + SEQ_GAP, y, &mut out);
+    let end_x = PADDING + SEQ_GAP + rule_extent.width;
+    line(&mut out, end_x, y, SEQ_GAP);
+    out.push_str(&format!(
+        "<path d=\"M{} {}v{BOX_HEIGHT}\"/>\n",
+        end_x + SEQ_GAP,
+        y - BOX_HEIGHT / 2
+    ));
+    out.push_str("</svg>\n");
+    out
+}
@@ -8,6 +8,7 @@ fn gen_field(
     path: String,
     leaf_type: Type,
     leaf_attrs: Vec<Attribute>,
+    wrappers: &HashSet<&str>,
     word_rule: &mut Option<String>,
     out: &mut Map<String, Value>,
 ) -> (Value, bool) {
@@ -43,12 +44,8 @@ fn gen_field(
             .map(|p| p.expr.clone())
     });
 
-    let mut skip_over = HashSet::new();
-    skip_over.insert("Spanned");
-    skip_over.insert("Box");
-
-    let (inner_type_vec, is_vec) = try_extract_inner_type(&leaf_type, "Vec", &skip_over);
-    let (inner_type_option, is_option) = try_extract_inner_type(&leaf_type, "Option", &skip_over);
+    let (inner_type_vec, is_vec) = try_extract_inner_type(&leaf_type, "Vec", wrappers);
+    let (inner_type_option, is_option) = try_extract_inner_type(&leaf_type, "Option", wrappers);
 
     if !is_vec && !is_option {
         if let Some(Expr::Lit(lit)) = pattern_param {
@@ -92,7 +89,7 @@ fn gen_field(
                 panic!("Expected string literal for text");
             }
         } else {
-            let symbol_name = if let Type::Path(p) = filter_inner_type(&leaf_type, &skip_over) {
+            let symbol_name = if let Type::Path(p) = filter_inner_type(&leaf_type, wrappers) {
                 if p.path.segments.len() == 1 {
                     p.path.segments[0].ident.to_string()
                 } else {
@@ -115,6 +112,7 @@ fn gen_field(
             path.clone(),
             inner_type_vec,
             leaf_attr.iter().cloned().cloned().collect(),
+            wrappers,
             word_rule,
             out,
         );
@@ -131,6 +129,7 @@ fn gen_field(
                 format!("{path}_vec_delimiter"),
                 p.field.ty,
                 p.field.attrs,
+                wrappers,
                 word_rule,
                 out,
             )
@@ -228,8 +227,14 @@ fn gen_field(
         )
     } else {
         // is_option
-        let (field_json, field_optional) =
-            gen_field(path, inner_type_option, leaf_attrs, word_rule, out);
+        let (field_json, field_optional) = gen_field(
+            path,
+            inner_type_option,
+            leaf_attrs,
+            wrappers,
+            word_rule,
+            out,
+        );
 
         if field_optional {
             panic!("Option<Option<_>> is not supported");
@@ -243,6 +248,7 @@ fn gen_struct_or_variant(
     path: String,
     attrs: Vec<Attribute>,
     fields: Fields,
+    wrappers: &HashSet<&str>,
     out: &mut Map<String, Value>,
     word_rule: &mut Option<String>,
 ) {
@@ -250,11 +256,10 @@ fn gen_struct_or_variant(
         .iter()
         .enumerate()
         .filter_map(|(i, field)| {
-            if field
-                .attrs
-                .iter()
-                .any(|attr| attr.path == syn::parse_quote!(rust_sitter::skip))
-            {
+            if field.attrs.iter().any(|attr| {
+                attr.path == syn::parse_quote!(rust_sitter::skip)
+                    || attr.path == syn::parse_quote!(rust_sitter::trivia)
+            }) {
                 None
             } else {
                 let ident_str = field
@@ -267,6 +272,7 @@ fn gen_struct_or_variant(
                     format!("{}_{}", path.clone(), ident_str),
                     field.ty.clone(),
                     field.attrs.clone(),
+                    wrappers,
                     word_rule,
                     out,
                 );
@@ -369,26 +375,12 @@ pub fn generate_grammar(module: &ItemMod) -> Value {
 
     let mut extras_list = vec![];
 
-    let grammar_name = module
-        .attrs
-        .iter()
-        .find_map(|a| {
-            if a.path == syn::parse_quote!(rust_sitter::grammar) {
-                let grammar_name_expr = a.parse_args_with(Expr::parse).ok();
-                if let Some(Expr::Lit(ExprLit {
-                    attrs: _,
-                    lit: Lit::Str(s),
-                })) = grammar_name_expr
-                {
-                    Some(s.value())
-                } else {
-                    panic!("Expected string literal for grammar name");
-                }
-            } else {
-                None
-            }
-        })
-        .expect("Each grammar must have a name");
+    let grammar_args = grammar_args(&module.attrs).expect("Each grammar must have a name");
+    let grammar_name = grammar_args.name.value();
+
+    let registered_wrappers = grammar_args.wrappers();
+    let mut wrappers: HashSet<&str> = BUILTIN_WRAPPERS.into_iter().collect();
+    wrappers.extend(registered_wrappers.iter().map(String::as_str));
 
     let (_, contents) = module.content.as_ref().unwrap();
 
@@ -421,6 +413,7 @@ pub fn generate_grammar(module: &ItemMod) -> Value {
                         format!("{}_{}", e.ident, v.ident),
                         v.attrs.clone(),
                         v.fields.clone(),
+                        &wrappers,
                         &mut rules_map,
                         &mut word_rule,
                     )
@@ -450,6 +443,7 @@ pub fn generate_grammar(module: &ItemMod) -> Value {
                     s.ident.to_string(),
                     s.attrs.clone(),
                     s.fields.clone(),
+                    &wrappers,
                     &mut rules_map,
                     &mut word_rule,
                 );
@@ -483,3 +477,47 @@ pub fn generate_grammar(module: &ItemMod) -> Value {
         "extras": extras_list
     })
 }
+
+/// Merges the rules of a base grammar into a grammar that extends it. Rules defined in the
+/// derived grammar take precedence over base rules with the same name, which allows individual
+/// rules to be overridden. Extras of both grammars are kept, and the base `word` rule is used
+/// if the derived grammar does not specify one.
+pub fn extend_grammar(base: &Value, derived: Value) -> Value {
+    let derived_rules = derived["rules"].as_object().unwrap();
+    let mut rules_map = Map::new();
+    // source_file must remain the first key, and always comes from the derived grammar
+    rules_map.insert(
+        "source_file".to_string(),
+        derived_rules["source_file"].clone(),
+    );
+
+    derived_rules
+        .iter()
+        .chain(base["rules"].as_object().unwrap().iter())
+        .filter(|(name, _)| name.as_str() != "source_file")
+        .for_each(|(name, rule)| {
+            if !rules_map.contains_key(name) {
+                rules_map.insert(name.clone(), rule.clone());
+            }
+        });
+
+    let mut extras_list = derived["extras"].as_array().unwrap().clone();
+    base["extras"].as_array().unwrap().iter().for_each(|extra| {
+        if !extras_list.contains(extra) {
+            extras_list.push(extra.clone());
+        }
+    });
+
+    let word_rule = if derived["word"].is_null() {
+        base["word"].clone()
+    } else {
+        derived["word"].clone()
+    };
+
+    json!({
+        "name": derived["name"],
+        "word": word_rule,
+        "rules": rules_map,
+        "extras": extras_list
+    })
+}
@@ -0,0 +1,236 @@
+use serde_json::Value;
+
+/// Rules whose single-line form is longer than this are split over several lines.
+const MAX_LINE_WIDTH: usize = 80;
+
+const INDENT: &str = "  ";
+
+/// Converts a string into a single-quoted JavaScript string literal.
+fn js_string(s: &str) -> String {
+    let mut out = String::from("'");
+    for c in s.chars() {
+        match c {
+            '\'' => out.push_str("\\'"),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            _ => out.push(c),
+        }
+    }
+    out.push('\'');
+    out
+}
+
+/// Converts a regex into a JavaScript regex literal, escaping the slashes that would end it.
+pub(crate) fn js_regex(pattern: &str) -> String {
+    let mut out = String::from("/");
+    let mut escaped = false;
+    let mut in_class = false;
+    for c in pattern.chars() {
+        match c {
+            '/' if !escaped && !in_class => out.push_str("\\/"),
+            _ => out.push(c),
+        }
+
+        if !escaped {
+            match c {
+                '[' => in_class = true,
+                ']' => in_class = false,
+                _ => {}
+            }
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    out.push('/');
+    out
+}
+
+/// A rule converted into a JavaScript expression, kept as a call and its arguments so that it
+/// can be laid out on one or several lines.
+enum Expr {
+    Atom(String),
+    Call(String, Vec<Expr>),
+}
+
+impl Expr {
+    fn call(function: &str, args: Vec<Expr>) -> Expr {
+        Expr::Call(function.to_string(), args)
+    }
+
+    fn flat(&self) -> String {
+        match self {
+            Expr::Atom(atom) => atom.clone(),
+            Expr::Call(function, args) => format!(
+                "{function}({})",
+                args.iter().map(Expr::flat).collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+
+    /// Renders the expression starting at the given indentation level, splitting calls over
+    /// several lines (one argument per line) when they do not fit.
+    fn render(&self, indent: usize) -> String {
+        let flat = self.flat();
+        match self {
+            Expr::Call(function, args) if flat.len() + indent * INDENT.len() > MAX_LINE_WIDTH => {
+                let inner = INDENT.repeat(indent + 1);
+                let args: String = args
+                    .iter()
+                    .map(|arg| format!("{inner}{},\n", arg.render(indent + 1)))
+                    .collect();
+                format!("{function}(\n{args}{})", INDENT.repeat(indent))
+            }
+            _ => flat,
+        }
+    }
+}
+
+fn convert_rule(rule: &Value) -> Expr {
+    let content = || convert_rule(&rule["content"]);
+    let members = || {
+        rule["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(convert_rule)
+            .collect::<Vec<_>>()
+    };
+    let precedence = || Expr::Atom(rule["value"].to_string());
+
+    match rule["type"].as_str().unwrap() {
+        "BLANK" => Expr::call("blank", vec![]),
+        "STRING" => Expr::Atom(js_string(rule["value"].as_str().unwrap())),
+        "PATTERN" => Expr::Atom(js_regex(rule["value"].as_str().unwrap())),
+        "SYMBOL" => Expr::Atom(format!("$.{}", rule["name"].as_str().unwrap())),
+        "SEQ" => Expr::call("seq", members()),
+        "CHOICE" => {
+            let all = rule["members"].as_array().unwrap();
+            let non_blank: Vec<&Value> = all.iter().filter(|m| m["type"] != "BLANK").collect();
+            if non_blank.len() < all.len() {
+                let inner = if non_blank.len() == 1 {
+                    convert_rule(non_blank[0])
+                } else {
+                    Expr::call("choice", non_blank.into_iter().map(convert_rule).collect())
+                };
+                Expr::call("optional", vec![inner])
+            } else {
+                Expr::call("choice", members())
+            }
+        }
+        "REPEAT" => Expr::call("repeat", vec![content()]),
+        "REPEAT1" => Expr::call("repeat1", vec![content()]),
+        "FIELD" => Expr::call(
+            "field",
+            vec![
+                Expr::Atom(js_string(rule["name"].as_str().unwrap())),
+                content(),
+            ],
+        ),
+        "PREC" => Expr::call("prec", vec![precedence(), content()]),
+        "PREC_LEFT" => Expr::call("prec.left", vec![precedence(), content()]),
+        "PREC_RIGHT" => Expr::call("prec.right", vec![precedence(), content()]),
+        "PREC_DYNAMIC" => Expr::call("prec.dynamic", vec![precedence(), content()]),
+        "TOKEN" => Expr::call("token", vec![content()]),
+        "IMMEDIATE_TOKEN" => Expr::call("token.immediate", vec![content()]),
+        "ALIAS" => {
+            let value = rule["value"].as_str().unwrap();
+            let alias = if rule["named"].as_bool().unwrap_or(false) {
+                format!("$.{value}")
+            } else {
+                js_string(value)
+            };
+            Expr::call("alias", vec![content(), Expr::Atom(alias)])
+        }
+        other => panic!("Unsupported rule type `{other}`"),
+    }
+}
+
+/// Renders a list of rules (such as the extras) as a function of `$` returning an array.
+fn rule_list(rules: &[Value]) -> String {
+    let items: Vec<String> = rules.iter().map(|r| convert_rule(r).render(2)).collect();
+    let flat = format!("$ => [{}]", items.join(", "));
+    if flat.len() + 2 * INDENT.len() <= MAX_LINE_WIDTH && !flat.contains('\n') {
+        flat
+    } else {
+        let inner = INDENT.repeat(3);
+        let items: String = items.iter().map(|i| format!("{inner}{i},\n")).collect();
+        format!("$ => [\n{items}{}]", INDENT.repeat(2))
+    }
+}
+
+/// Renders a list of lists of rule names (such as the conflicts) as a function of `$`.
+fn name_lists(lists: &[Value]) -> String {
+    let lists: Vec<String> = lists
+        .iter()
+        .map(|list| {
+            let names: Vec<String> = list
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|n| format!("$.{}", n.as_str().unwrap()))
+                .collect();
+            format!("[{}]", names.join(", "))
+        })
+        .collect();
+    format!("$ => [{}]", lists.join(", "))
+}
+
+/// Converts a grammar in Tree Sitter's JSON format (as produced by
+/// [`generate_grammars`](crate::generate_grammars)) into the equivalent `grammar.js`, for use
+/// with the `tree-sitter` CLI and playground. Rules keep their names and order, so that the
+/// output can be compared with a hand-written grammar.
+pub fn generate_grammar_js(grammar: &Value) -> String {
+    let mut out = String::from("module.exports = grammar({\n");
+    out.push_str(&format!(
+        "{INDENT}name: {},\n",
+        js_string(grammar["name"].as_str().unwrap())
+    ));
+
+    if let Some(word) = grammar["word"].as_str() {
+        out.push_str(&format!("\n{INDENT}word: $ => $.{word},\n"));
+    }
+
+    if let Some(extras) = grammar["extras"].as_array() {
+        out.push_str(&format!("\n{INDENT}extras: {},\n", rule_list(extras)));
+    }
+
+    if let Some(externals) = grammar["externals"].as_array() {
+        out.push_str(&format!("\n{INDENT}externals: {},\n", rule_list(externals)));
+    }
+
+    for key in ["inline", "supertypes"] {
+        if let Some(names) = grammar[key].as_array() {
+            let names: Vec<String> = names
+                .iter()
+                .map(|n| format!("$.{}", n.as_str().unwrap()))
+                .collect();
+            out.push_str(&format!("\n{INDENT}{key}: $ => [{}],\n", names.join(", ")));
+        }
+    }
+
+    if let Some(conflicts) = grammar["conflicts"].as_array() {
+        out.push_str(&format!(
+            "\n{INDENT}conflicts: {},\n",
+            name_lists(conflicts)
+        ));
+    }
+
+    out.push_str(&format!("\n{INDENT}rules: {{\n"));
+    let rules = grammar["rules"]
+        .as_object()
+        .expect("Expected the grammar to have rules");
+    let rules: Vec<String> = rules
+        .iter()
+        .map(|(name, rule)| {
+            format!(
+                "{INDENT}{INDENT}{name}: $ => {},\n",
+                convert_rule(rule).render(2)
+            )
+        })
+        .collect();
+    out.push_str(&rules.join("\n"));
+    out.push_str(&format!("{INDENT}}},\n}});\n"));
+
+    out
+}
@@ -0,0 +1,836 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_json::{Map, Value};
+
+const INDENT: &str = "    ";
+
+/// Keywords and reserved words, which cannot be used as identifiers in generated code. `Self` is
+/// the only one that starts with a capital letter, so it is the only one that type and variant
+/// names can collide with.
+const RUST_KEYWORDS: [&str; 52] = [
+    "Self", "abstract", "as", "async", "await", "become", "box", "break", "const", "continue",
+    "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield", "gen",
+];
+
+/// Appends `_` to a generated identifier that is a Rust keyword, such as `type_` for `type`.
+pub(crate) fn escape_keyword(name: String) -> String {
+    if RUST_KEYWORDS.contains(&name.as_str()) {
+        format!("{name}_")
+    } else {
+        name
+    }
+}
+
+/// Names for the variants and fields of common punctuation tokens.
+const PUNCTUATION_NAMES: [(&str, &str); 27] = [
+    ("+", "plus"),
+    ("-", "minus"),
+    ("*", "star"),
+    ("/", "slash"),
+    ("%", "percent"),
+    ("=", "eq"),
+    ("==", "eq_eq"),
+    ("!=", "not_eq"),
+    ("<", "lt"),
+    (">", "gt"),
+    ("<=", "le"),
+    (">=", "ge"),
+    ("!", "bang"),
+    ("&", "and"),
+    ("&&", "and_and"),
+    ("|", "or"),
+    ("||", "or_or"),
+    (",", "comma"),
+    (";", "semicolon"),
+    (":", "colon"),
+    (".", "dot"),
+    ("(", "open_paren"),
+    (")", "close_paren"),
+    ("[", "open_bracket"),
+    ("]", "close_bracket"),
+    ("{", "open_brace"),
+    ("}", "close_brace"),
+];
+
+/// Converts a rule name such as `binary_expression` into a type name such as
+/// `BinaryExpression`.
+pub(crate) fn camel_case(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            let first = chars.next().unwrap().to_ascii_uppercase();
+            std::iter::once(first).chain(chars).collect::<String>()
+        })
+        .collect()
+}
+
+/// Converts a name into a snake case identifier, such as `binaryExpression` into
+/// `binary_expression`.
+pub(crate) fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 && !out.ends_with('_') {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            out.push(c);
+        } else if !out.ends_with('_') {
+            out.push('_');
+        }
+    }
+
+    if out.starts_with(|c: char| c.is_ascii_digit()) {
+        out.insert(0, '_');
+    }
+    out
+}
+
+/// A name for the token with the given text, such as `let` for `"let"` and `plus` for `"+"`.
+pub(crate) fn token_name(text: &str) -> Option<String> {
+    if !text.is_empty() && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        Some(snake_case(text))
+    } else {
+        PUNCTUATION_NAMES
+            .iter()
+            .find(|(t, _)| *t == text)
+            .map(|(_, name)| name.to_string())
+    }
+}
+
+/// A Rust raw string literal containing `s`, with enough `#`s to hold any quotes in it.
+fn raw_string(s: &str) -> String {
+    let mut hashes = String::new();
+    while s.contains(&format!("\"{hashes}")) {
+        hashes.push('#');
+    }
+    format!("r{hashes}\"{s}\"{hashes}")
+}
+
+/// The type of a field, before `Box`es are added to break up recursive types.
+#[derive(Clone)]
+enum FieldType {
+    Unit,
+    String,
+    Named(String),
+    Vec(Box<FieldType>),
+    Option(Box<FieldType>),
+}
+
+struct FieldDef {
+    name: String,
+    ty: FieldType,
+    attrs: Vec<String>,
+    todos: Vec<String>,
+}
+
+struct VariantDef {
+    name: String,
+    attrs: Vec<String>,
+    todos: Vec<String>,
+    fields: Vec<FieldDef>,
+    /// Whether the fields are unnamed, as in `Variant(Type)`.
+    tuple: bool,
+}
+
+enum TypeBody {
+    Struct(Vec<FieldDef>),
+    Enum(Vec<VariantDef>),
+}
+
+struct TypeDef {
+    name: String,
+    attrs: Vec<String>,
+    todos: Vec<String>,
+    body: TypeBody,
+}
+
+/// Converts the rules of a grammar in Tree Sitter's JSON format into Rust types.
+struct Importer<'a> {
+    rules: &'a Map<String, Value>,
+    type_names: HashMap<String, String>,
+    types: Vec<TypeDef>,
+}
+
+impl<'a> Importer<'a> {
+    fn type_name(&self, rule: &str) -> String {
+        self.type_names
+            .get(rule)
+            .cloned()
+            .unwrap_or_else(|| panic!("Could not find the rule `{rule}`"))
+    }
+
+    /// A type name for a rule nested inside another, which is not unique yet.
+    fn fresh_type_name(&self, name: String) -> String {
+        let name = escape_keyword(name);
+        let taken = |n: &String| {
+            self.type_names.values().any(|t| t == n) || self.types.iter().any(|t| t.name == *n)
+        };
+        if !taken(&name) {
+            return name;
+        }
+
+        (2..)
+            .map(|i| format!("{name}{i}"))
+            .find(|n| !taken(n))
+            .unwrap()
+    }
+
+    /// Converts a rule used as the type of a field into the field type, the attributes of the
+    /// field and any parts of the rule that could not be mapped. Rules that need their own
+    /// types (such as choices) are turned into new types named after `owner` and `hint`.
+    fn element(
+        &mut self,
+        owner: &str,
+        hint: &str,
+        rule: &Value,
+    ) -> (FieldType, Vec<String>, Vec<String>) {
+        match rule["type"].as_str().unwrap() {
+            "STRING" => (
+                FieldType::Unit,
+                vec![format!(
+                    "#[rust_sitter::leaf(text = {:?})]",
+                    rule["value"].as_str().unwrap()
+                )],
+                vec![],
+            ),
+            "PATTERN" => (
+                FieldType::String,
+                vec![format!(
+                    "#[rust_sitter::leaf(pattern = {})]",
+                    raw_string(rule["value"].as_str().unwrap())
+                )],
+                vec![],
+            ),
+            "SYMBOL" => (
+                FieldType::Named(self.type_name(rule["name"].as_str().unwrap())),
+                vec![],
+                vec![],
+            ),
+            "FIELD" => self.element(owner, hint, &rule["content"]),
+            "CHOICE" => {
+                let members = rule["members"].as_array().unwrap();
+                let non_blank: Vec<&Value> =
+                    members.iter().filter(|m| m["type"] != "BLANK").collect();
+                if non_blank.len() < members.len() {
+                    if let Some((element, delimiter)) = delimited_list(non_blank[0]) {
+                        if non_blank.len() == 1 {
+                            return self.list(owner, hint, element, Some(delimiter), false);
+                        }
+                    }
+
+                    let (inner, attrs, todos) = if non_blank.len() == 1 {
+                        self.element(owner, hint, non_blank[0])
+                    } else {
+                        let choice = Value::Object(Map::from_iter([
+                            ("type".to_string(), "CHOICE".into()),
+                            (
+                                "members".to_string(),
+                                non_blank.into_iter().cloned().collect(),
+                            ),
+                        ]));
+                        self.element(owner, hint, &choice)
+                    };
+                    (FieldType::Option(Box::new(inner)), attrs, todos)
+                } else {
+                    let name = self.fresh_type_name(format!("{owner}{}", camel_case(hint)));
+                    self.define(name.clone(), rule, vec![]);
+                    (FieldType::Named(name), vec![], vec![])
+                }
+            }
+            "SEQ" => {
+                if let Some((element, delimiter)) = delimited_list(rule) {
+                    return self.list(owner, hint, element, Some(delimiter), true);
+                }
+
+                let name = self.fresh_type_name(format!("{owner}{}", camel_case(hint)));
+                self.define(name.clone(), rule, vec![]);
+                (FieldType::Named(name), vec![], vec![])
+            }
+            kind @ ("REPEAT" | "REPEAT1") => {
+                self.list(owner, hint, &rule["content"], None, kind == "REPEAT1")
+            }
+            kind @ ("PREC" | "PREC_LEFT" | "PREC_RIGHT" | "PREC_DYNAMIC") => {
+                let (ty, attrs, mut todos) = self.element(owner, hint, &rule["content"]);
+                todos.push(format!(
+                    "the {} precedence {} on this field was dropped",
+                    kind.to_lowercase(),
+                    rule["value"]
+                ));
+                (ty, attrs, todos)
+            }
+            "TOKEN" | "IMMEDIATE_TOKEN"
+                if matches!(rule["content"]["type"].as_str(), Some("STRING" | "PATTERN")) =>
+            {
+                let (ty, attrs, mut todos) = self.element(owner, hint, &rule["content"]);
+                if rule["type"] == "IMMEDIATE_TOKEN" {
+                    todos.push("this token must immediately follow the previous one".to_string());
+                }
+                (ty, attrs, todos)
+            }
+            "ALIAS" => {
+                let (ty, attrs, mut todos) = self.element(owner, hint, &rule["content"]);
+                todos.push(format!("this field was aliased to `{}`", rule["value"]));
+                (ty, attrs, todos)
+            }
+            other => (
+                FieldType::Unit,
+                vec!["#[rust_sitter::skip(())]".to_string()],
+                vec![format!("could not map the {other} rule {rule}")],
+            ),
+        }
+    }
+
+    fn list(
+        &mut self,
+        owner: &str,
+        hint: &str,
+        element: &Value,
+        delimiter: Option<&str>,
+        non_empty: bool,
+    ) -> (FieldType, Vec<String>, Vec<String>) {
+        let (inner, mut attrs, todos) = self.element(owner, hint, element);
+        let inner = match inner {
+            // Vecs of Vecs or Options cannot be expressed directly, so they get a type.
+            FieldType::Vec(_) | FieldType::Option(_) => {
+                let name = self.fresh_type_name(format!("{owner}{}", camel_case(hint)));
+                self.define(name.clone(), element, vec![]);
+                attrs.clear();
+                FieldType::Named(name)
+            }
+            inner => inner,
+        };
+
+        attrs.push(if non_empty {
+            "#[rust_sitter::repeat(non_empty = true)]".to_string()
+        } else {
+            "#[rust_sitter::repeat]".to_string()
+        });
+        if let Some(delimiter) = delimiter {
+            attrs.push(format!(
+                "#[rust_sitter::delimited(#[rust_sitter::leaf(text = {delimiter:?})] ())]"
+            ));
+        }
+
+        (FieldType::Vec(Box::new(inner)), attrs, todos)
+    }
+
+    /// Converts the members of a sequence into fields, flattening nested sequences.
+    fn fields(&mut self, owner: &str, rule: &Value, out: &mut Vec<FieldDef>) {
+        match rule["type"].as_str().unwrap() {
+            "SEQ" if delimited_list(rule).is_none() => rule["members"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .for_each(|member| self.fields(owner, member, out)),
+            "BLANK" => {}
+            kind => {
+                let (name, todos) = match kind {
+                    "FIELD" => {
+                        let original = rule["name"].as_str().unwrap();
+                        let name = escape_keyword(snake_case(original));
+                        if name != original {
+                            (name, vec![format!("the field `{original}` was renamed")])
+                        } else {
+                            (name, vec![])
+                        }
+                    }
+                    "STRING" => (
+                        format!(
+                            "_{}",
+                            token_name(rule["value"].as_str().unwrap())
+                                .unwrap_or_else(|| out.len().to_string())
+                        ),
+                        vec![],
+                    ),
+                    "SYMBOL" => (
+                        escape_keyword(snake_case(
+                            rule["name"].as_str().unwrap().trim_start_matches('_'),
+                        )),
+                        vec![],
+                    ),
+                    "REPEAT" | "REPEAT1" if rule["content"]["type"] == "SYMBOL" => (
+                        escape_keyword(snake_case(
+                            rule["content"]["name"]
+                                .as_str()
+                                .unwrap()
+                                .trim_start_matches('_'),
+                        )),
+                        vec![],
+                    ),
+                    _ => (format!("_{}", out.len()), vec![]),
+                };
+
+                let mut name = name;
+                if out.iter().any(|f| f.name == name) {
+                    name = (2..)
+                        .map(|i| format!("{name}{i}"))
+                        .find(|n| !out.iter().any(|f| f.name == *n))
+                        .unwrap();
+                }
+
+                let (ty, attrs, mut element_todos) = self.element(owner, &name, rule);
+                element_todos.extend(todos);
+                out.push(FieldDef {
+                    name,
+                    ty,
+                    attrs,
+                    todos: element_todos,
+                });
+            }
+        }
+    }
+
+    /// Converts a member of a choice into an enum variant.
+    fn variant(&mut self, owner: &str, index: usize, rule: &Value) -> VariantDef {
+        let (rule, attrs, todos) = strip_precedence(rule);
+        let (name, tuple) = match rule["type"].as_str().unwrap() {
+            "SYMBOL" => (camel_case(rule["name"].as_str().unwrap()), true),
+            "STRING" => (
+                token_name(rule["value"].as_str().unwrap())
+                    .map(|n| camel_case(&n))
+                    .unwrap_or_else(|| format!("Variant{index}")),
+                true,
+            ),
+            "SEQ" | "FIELD" if delimited_list(rule).is_none() => {
+                // Name sequences after their first keyword, such as `Let` for `let x = 1`.
+                let keyword = rule["members"].as_array().and_then(|members| {
+                    members.iter().find_map(|m| {
+                        let text = m["value"].as_str().filter(|_| m["type"] == "STRING")?;
+                        text.chars()
+                            .all(|c| c.is_ascii_alphabetic() || c == '_')
+                            .then(|| camel_case(text))
+                    })
+                });
+                (keyword.unwrap_or_else(|| format!("Variant{index}")), false)
+            }
+            _ => (format!("Variant{index}"), true),
+        };
+        let name = escape_keyword(name);
+
+        let fields = if tuple {
+            let (ty, attrs, todos) = self.element(owner, &name, rule);
+            vec![FieldDef {
+                name: String::new(),
+                ty,
+                attrs,
+                todos,
+            }]
+        } else {
+            let mut fields = vec![];
+            self.fields(&format!("{owner}{name}"), rule, &mut fields);
+            fields
+        };
+
+        VariantDef {
+            name,
+            attrs,
+            todos,
+            fields,
+            tuple,
+        }
+    }
+
+    /// Defines the type for a rule, as an enum for a choice or a struct otherwise.
+    fn define(&mut self, name: String, rule: &Value, extra_attrs: Vec<String>) {
+        // Reserve the name before converting the rule, which may define nested types.
+        let index = self.types.len();
+        self.types.push(TypeDef {
+            name: name.clone(),
+            attrs: vec![],
+            todos: vec![],
+            body: TypeBody::Struct(vec![]),
+        });
+
+        let (rule, mut attrs, mut todos) = strip_precedence(rule);
+        attrs.extend(extra_attrs);
+
+        let is_choice = rule["type"] == "CHOICE"
+            && rule["members"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .all(|m| m["type"] != "BLANK");
+
+        let body = if is_choice {
+            let mut variants: Vec<VariantDef> = vec![];
+            for (i, member) in rule["members"].as_array().unwrap().iter().enumerate() {
+                let mut variant = self.variant(&name, i, member);
+                if variants.iter().any(|v| v.name == variant.name) {
+                    variant.name = format!("{}{i}", variant.name);
+                }
+                variants.push(variant);
+            }
+            TypeBody::Enum(variants)
+        } else {
+            let mut fields = vec![];
+            match rule["type"].as_str().unwrap() {
+                "STRING" | "PATTERN" | "TOKEN" | "IMMEDIATE_TOKEN" => {
+                    let (ty, attrs, todos) = self.element(&name, "value", rule);
+                    let field_name = if matches!(ty, FieldType::Unit) {
+                        "_value"
+                    } else {
+                        "value"
+                    };
+                    fields.push(FieldDef {
+                        name: field_name.to_string(),
+                        ty,
+                        attrs,
+                        todos,
+                    });
+                }
+                _ => self.fields(&name, rule, &mut fields),
+            }
+
+            if fields.is_empty() {
+                todos.push("this rule matches an empty string".to_string());
+            }
+            TypeBody::Struct(fields)
+        };
+
+        self.types[index] = TypeDef {
+            name,
+            attrs,
+            todos,
+            body,
+        };
+    }
+}
+
+/// Splits the precedence off a rule, returning the attribute for it (or a note if it cannot
+/// be expressed with an attribute).
+fn strip_precedence(rule: &Value) -> (&Value, Vec<String>, Vec<String>) {
+    let attr = match rule["type"].as_str().unwrap() {
+        "PREC" => "prec",
+        "PREC_LEFT" => "prec_left",
+        "PREC_RIGHT" => "prec_right",
+        "PREC_DYNAMIC" => {
+            return (
+                &rule["content"],
+                vec![],
+                vec![format!(
+                    "the dynamic precedence {} was dropped",
+                    rule["value"]
+                )],
+            );
+        }
+        _ => return (rule, vec![], vec![]),
+    };
+
+    match rule["value"].as_i64() {
+        Some(value) => (
+            &rule["content"],
+            vec![format!("#[rust_sitter::{attr}({value})]")],
+            vec![],
+        ),
+        None => (
+            &rule["content"],
+            vec![],
+            vec![format!(
+                "the named precedence {} was dropped",
+                rule["value"]
+            )],
+        ),
+    }
+}
+
+/// Matches a list with a delimiter, `seq(x, repeat(seq("delimiter", x)))`, returning `x` and
+/// the delimiter.
+fn delimited_list(rule: &Value) -> Option<(&Value, &str)> {
+    let members = rule["members"].as_array()?;
+    if rule["type"] != "SEQ" || members.len() != 2 || members[1]["type"] != "REPEAT" {
+        return None;
+    }
+
+    let repeated = members[1]["content"]["members"].as_array()?;
+    if members[1]["content"]["type"] != "SEQ"
+        || repeated.len() != 2
+        || repeated[0]["type"] != "STRING"
+        || repeated[1] != members[0]
+    {
+        return None;
+    }
+
+    Some((&members[0], repeated[0]["value"].as_str()?))
+}
+
+/// The types that each type refers to directly, without going through a `Vec`.
+fn direct_references(types: &[TypeDef]) -> HashMap<&str, Vec<&str>> {
+    fn collect<'a>(ty: &'a FieldType, out: &mut Vec<&'a str>) {
+        match ty {
+            FieldType::Named(name) => out.push(name),
+            FieldType::Option(inner) => collect(inner, out),
+            _ => {}
+        }
+    }
+
+    types
+        .iter()
+        .map(|t| {
+            let mut out = vec![];
+            match &t.body {
+                TypeBody::Struct(fields) => fields.iter().for_each(|f| collect(&f.ty, &mut out)),
+                TypeBody::Enum(variants) => variants
+                    .iter()
+                    .flat_map(|v| &v.fields)
+                    .for_each(|f| collect(&f.ty, &mut out)),
+            }
+            (t.name.as_str(), out)
+        })
+        .collect()
+}
+
+pub(crate) fn reaches(from: &str, to: &str, references: &HashMap<&str, Vec<&str>>) -> bool {
+    let mut seen = HashSet::new();
+    let mut stack = vec![from];
+    while let Some(current) = stack.pop() {
+        if current == to {
+            return true;
+        }
+        if seen.insert(current) {
+            stack.extend(references.get(current).into_iter().flatten());
+        }
+    }
+    false
+}
+
+fn render_type(
+    ty: &FieldType,
+    owner: &str,
+    references: &HashMap<&str, Vec<&str>>,
+    boxed: bool,
+) -> String {
+    match ty {
+        FieldType::Unit => "()".to_string(),
+        FieldType::String => "String".to_string(),
+        FieldType::Named(name) if boxed && reaches(name, owner, references) => {
+            format!("Box<{name}>")
+        }
+        FieldType::Named(name) => name.clone(),
+        FieldType::Vec(inner) => format!("Vec<{}>", render_type(inner, owner, references, false)),
+        FieldType::Option(inner) => {
+            format!("Option<{}>", render_type(inner, owner, references, boxed))
+        }
+    }
+}
+
+fn render_todos(todos: &[String], indent: &str, out: &mut String) {
+    for todo in todos {
+        out.push_str(&format!("{indent}// TODO: {todo}\n"));
+    }
+}
+
+fn render_attrs(attrs: &[String], indent: &str, out: &mut String) {
+    for attr in attrs {
+        out.push_str(&format!("{indent}{attr}\n"));
+    }
+}
+
+fn render_fields(
+    fields: &[FieldDef],
+    owner: &str,
+    references: &HashMap<&str, Vec<&str>>,
+    public: bool,
+    indent: &str,
+    out: &mut String,
+) {
+    for field in fields {
+        render_todos(&field.todos, indent, out);
+        render_attrs(&field.attrs, indent, out);
+        let visibility = if public && !field.name.starts_with('_') {
+            "pub "
+        } else {
+            ""
+        };
+        out.push_str(&format!(
+            "{indent}{visibility}{}: {},\n",
+            field.name,
+            render_type(&field.ty, owner, references, true)
+        ));
+    }
+}
+
+/// Converts a grammar in Tree Sitter's JSON format (`src/grammar.json`) into the source of an
+/// equivalent Rust Sitter grammar module. Choices become enums, sequences become structs with
+/// a field for each member, repetitions become `Vec`s, strings and patterns become leaves and
+/// precedences become the precedence attributes. The first rule becomes the root type, and
+/// types that contain themselves are boxed. Parts of the grammar that cannot be expressed this
+/// way are marked with `// TODO:` comments.
+pub fn import_grammar_json(grammar_json: &str) -> String {
+    let grammar: Value = serde_json::from_str(grammar_json).unwrap();
+    let rules = grammar["rules"]
+        .as_object()
+        .expect("Expected the grammar to have rules");
+
+    let mut importer = Importer {
+        rules,
+        type_names: HashMap::new(),
+        types: vec![],
+    };
+    for rule in rules.keys() {
+        let name = importer.fresh_type_name(camel_case(rule));
+        importer.type_names.insert(rule.clone(), name);
+    }
+
+    let mut module_todos = vec![];
+    let mut extra_rules = HashSet::new();
+    let mut extra_types = vec![];
+    for (i, extra) in grammar["extras"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .enumerate()
+    {
+        match extra["type"].as_str().unwrap() {
+            "SYMBOL" => {
+                extra_rules.insert(extra["name"].as_str().unwrap().to_string());
+            }
+            "STRING" | "PATTERN" => {
+                let name = if extra["value"] == "\\s" {
+                    "Whitespace".to_string()
+                } else {
+                    format!("Extra{i}")
+                };
+                extra_types.push((importer.fresh_type_name(name), extra));
+            }
+            _ => module_todos.push(format!("could not map the extra {extra}")),
+        }
+    }
+
+    for key in [
+        "externals",
+        "conflicts",
+        "inline",
+        "supertypes",
+        "precedences",
+    ] {
+        if grammar[key].as_array().is_some_and(|a| !a.is_empty()) {
+            module_todos.push(format!("`{key}` are not supported: {}", grammar[key]));
+        }
+    }
+
+    for (i, (rule_name, rule)) in importer.rules.iter().enumerate() {
+        let mut attrs = vec![];
+        if i == 0 {
+            attrs.push("#[rust_sitter::language]".to_string());
+        }
+        if extra_rules.contains(rule_name) {
+            attrs.push("#[rust_sitter::extra]".to_string());
+        }
+
+        let name = importer.type_name(rule_name);
+        importer.define(name, rule, attrs);
+        if rule_name.starts_with('_') {
+            let index = importer
+                .types
+                .iter()
+                .position(|t| t.name == importer.type_name(rule_name))
+                .unwrap();
+            importer.types[index].todos.push(format!(
+                "the hidden rule `{rule_name}` is visible in Rust Sitter"
+            ));
+        }
+    }
+
+    for (name, extra) in extra_types {
+        importer.define(
+            name.clone(),
+            extra,
+            vec!["#[rust_sitter::extra]".to_string()],
+        );
+        // The text of extras is never needed, so they are matched into a unit field.
+        if let Some(TypeBody::Struct(fields)) = importer
+            .types
+            .iter_mut()
+            .find(|t| t.name == name)
+            .map(|t| &mut t.body)
+        {
+            fields[0].name = format!("_{}", snake_case(&name));
+            fields[0].ty = FieldType::Unit;
+        }
+    }
+
+    if let Some(word) = grammar["word"].as_str() {
+        let word_type = importer.type_name(word);
+        let word_field = importer.types.iter_mut().find_map(|t| match &mut t.body {
+            TypeBody::Struct(fields) if t.name == word_type && fields.len() == 1 => {
+                Some(&mut fields[0])
+            }
+            _ => None,
+        });
+        match word_field {
+            Some(field) => field.attrs.push("#[rust_sitter::word]".to_string()),
+            None => module_todos.push(format!("could not mark the rule `{word}` as the word")),
+        }
+    }
+
+    let references = direct_references(&importer.types);
+    let indent = INDENT;
+    let mut out = format!(
+        "#[rust_sitter::grammar({:?})]\npub mod grammar {{\n",
+        grammar["name"].as_str().unwrap()
+    );
+    render_todos(&module_todos, indent, &mut out);
+
+    for (i, ty) in importer.types.iter().enumerate() {
+        if i > 0 || !module_todos.is_empty() {
+            out.push('\n');
+        }
+        render_todos(&ty.todos, indent, &mut out);
+        render_attrs(&ty.attrs, indent, &mut out);
+        out.push_str(&format!("{indent}#[derive(Debug)]\n"));
+
+        match &ty.body {
+            TypeBody::Struct(fields) => {
+                out.push_str(&format!("{indent}pub struct {} {{\n", ty.name));
+                render_fields(
+                    fields,
+                    &ty.name,
+                    &references,
+                    true,
+                    &indent.repeat(2),
+                    &mut out,
+                );
+                out.push_str(&format!("{indent}}}\n"));
+            }
+            TypeBody::Enum(variants) => {
+                out.push_str(&format!("{indent}pub enum {} {{\n", ty.name));
+                let variant_indent = indent.repeat(2);
+                for variant in variants {
+                    render_todos(&variant.todos, &variant_indent, &mut out);
+                    render_attrs(&variant.attrs, &variant_indent, &mut out);
+                    if variant.tuple {
+                        let field = &variant.fields[0];
+                        render_todos(&field.todos, &variant_indent, &mut out);
+                        let attrs: String = field.attrs.iter().map(|a| format!("{a} ")).collect();
+                        out.push_str(&format!(
+                            "{variant_indent}{}({attrs}{}),\n",
+                            variant.name,
+                            render_type(&field.ty, &ty.name, &references, true)
+                        ));
+                    } else {
+                        out.push_str(&format!("{variant_indent}{} {{\n", variant.name));
+                        render_fields(
+                            &variant.fields,
+                            &ty.name,
+                            &references,
+                            false,
+                            &indent.repeat(3),
+                            &mut out,
+                        );
+                        out.push_str(&format!("{variant_indent}}},\n"));
+                    }
+                }
+                out.push_str(&format!("{indent}}}\n"));
+            }
+        }
+    }
+
+    out.push_str("}\n");
+    out
+}
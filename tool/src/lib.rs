@@ -1,37 +1,355 @@
-use syn::{parse_quote, Item};
+use std::collections::HashSet;
+
+use quote::ToTokens;
+use rust_sitter_common::{grammar_args, highlights_query, rule_names, BUILTIN_WRAPPERS};
+use serde_json::Map;
+use serde_json::Value;
+use syn::{parse_quote, Fields, Item, ItemEnum, ItemMod, ItemStruct};
 
 mod expansion;
 use expansion::*;
 
+mod docs;
+pub use docs::{generate_grammar_docs, write_grammar_docs, GrammarDocs};
+
+mod grammar_js;
+pub use grammar_js::generate_grammar_js;
+
+mod import;
+pub use import::import_grammar_json;
+
+mod node_types;
+pub use node_types::generate_node_types_bindings;
+
+#[cfg(feature = "build_parsers")]
+mod check;
+#[cfg(feature = "build_parsers")]
+pub use check::check_grammars;
+
+mod samples;
+pub use samples::{generate_samples, SampleGenerator};
+
 /// Generates JSON strings defining Tree Sitter grammars for every Rust Sitter
 /// grammar found in the given module and recursive submodules.
+///
+/// When called from a build script, grammars exported by dependencies with
+/// [`export_grammars`] can be used as the base of an `extends` parameter.
 pub fn generate_grammars(root_file: &Path) -> Vec<String> {
     let root_file = syn_inline_mod::parse_and_inline_modules(root_file).items;
-    let mut out = vec![];
+    generate_grammars_for_items(&root_file, &imported_grammars())
+        .into_iter()
+        .map(|g| without_type_signatures(g).to_string())
+        .collect()
+}
+
+/// Exports every Rust Sitter grammar found in the given module and recursive
+/// submodules, so that grammars in dependent crates can extend them. The signatures
+/// of their types are exported too, to check the types that override them.
+///
+/// This must be called from the build script of a crate with a `links` key in
+/// its `Cargo.toml`, since the grammars are published to the build scripts of
+/// dependent crates through `DEP_<links>_RUST_SITTER_GRAMMARS` metadata.
+pub fn export_grammars(root_file: &Path) {
+    let root_file = syn_inline_mod::parse_and_inline_modules(root_file).items;
+    let grammars = generate_grammars_for_items(&root_file, &imported_grammars());
+
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    let grammars_file = out_dir.join("rust-sitter-grammars.json");
+    std::fs::write(&grammars_file, Value::Array(grammars).to_string()).unwrap();
+
+    println!("cargo:rust_sitter_grammars={}", grammars_file.display());
+}
+
+/// Generates the highlights query for every Rust Sitter grammar found in the given module and
+/// recursive submodules that has `#[rust_sitter::highlight]` annotations, paired with the name
+/// of the grammar.
+pub fn generate_highlight_queries(root_file: &Path) -> Vec<(String, String)> {
+    let root_file = syn_inline_mod::parse_and_inline_modules(root_file).items;
+    let mut modules = vec![];
     root_file
         .iter()
-        .for_each(|i| generate_all_grammars(i, &mut out));
-    out
+        .for_each(|i| collect_grammar_modules(i, &mut modules));
+
+    modules
+        .iter()
+        .filter_map(|m| {
+            let name = grammar_args(&m.attrs).unwrap().name.value();
+            let query = highlights_query(&m.content.as_ref().unwrap().1);
+            (!query.is_empty()).then_some((name, query))
+        })
+        .collect()
+}
+
+/// Writes the highlights query of each grammar with `#[rust_sitter::highlight]` annotations
+/// to `<out_dir>/<grammar name>/queries/highlights.scm`.
+pub fn write_highlight_queries(root_file: &Path, out_dir: &Path) {
+    generate_highlight_queries(root_file)
+        .iter()
+        .for_each(|(name, query)| {
+            let queries_dir = out_dir.join(name).join("queries");
+            std::fs::create_dir_all(&queries_dir).unwrap();
+            std::fs::write(queries_dir.join("highlights.scm"), query).unwrap();
+        });
+}
+
+/// Maps the node kinds of every Rust Sitter grammar found in the given module and recursive
+/// submodules to the Rust types, variants and fields they correspond to (as in the generated
+/// `rule_name` function), paired with the name of the grammar.
+pub fn generate_rule_names(root_file: &Path) -> Vec<(String, Vec<(String, String)>)> {
+    let root_file = syn_inline_mod::parse_and_inline_modules(root_file).items;
+    let mut modules = vec![];
+    root_file
+        .iter()
+        .for_each(|i| collect_grammar_modules(i, &mut modules));
+
+    modules
+        .iter()
+        .map(|m| {
+            let args = grammar_args(&m.attrs).unwrap();
+            let registered_wrappers = args.wrappers();
+            let mut wrappers: HashSet<&str> = BUILTIN_WRAPPERS.into_iter().collect();
+            wrappers.extend(registered_wrappers.iter().map(String::as_str));
+
+            let items = &m.content.as_ref().unwrap().1;
+            (
+                args.name.value(),
+                rule_names(items, &root_type(items), &wrappers),
+            )
+        })
+        .collect()
 }
 
-fn generate_all_grammars(item: &Item, out: &mut Vec<String>) {
+/// The name of the type annotated with `#[rust_sitter::language]`.
+fn root_type(items: &[Item]) -> String {
+    items
+        .iter()
+        .find_map(|item| match item {
+            Item::Enum(ItemEnum { ident, attrs, .. })
+            | Item::Struct(ItemStruct { ident, attrs, .. }) => attrs
+                .iter()
+                .any(|attr| attr.path == parse_quote!(rust_sitter::language))
+                .then(|| ident.to_string()),
+            _ => None,
+        })
+        .expect("Each parser must have the root type annotated with `#[rust_sitter::language]`")
+}
+
+/// Loads the grammars exported by dependencies of the crate whose build script
+/// is currently running.
+fn imported_grammars() -> Vec<Value> {
+    std::env::vars()
+        .filter(|(key, _)| key.starts_with("DEP_") && key.ends_with("_RUST_SITTER_GRAMMARS"))
+        .flat_map(|(_, grammars_file)| {
+            let contents = std::fs::read_to_string(&grammars_file).unwrap();
+            match serde_json::from_str(&contents).unwrap() {
+                Value::Array(grammars) => grammars,
+                _ => panic!("Expected a list of grammars in {grammars_file}"),
+            }
+        })
+        .collect()
+}
+
+fn generate_grammars_for_items(items: &[Item], imported: &[Value]) -> Vec<Value> {
+    let mut modules = vec![];
+    items
+        .iter()
+        .for_each(|i| collect_grammar_modules(i, &mut modules));
+
+    modules
+        .iter()
+        .map(|m| generate_extended_grammar(m, &modules, imported, &mut vec![]))
+        .collect()
+}
+
+fn collect_grammar_modules<'a>(item: &'a Item, out: &mut Vec<&'a ItemMod>) {
     if let Item::Mod(m) = item {
         m.content
             .iter()
-            .for_each(|(_, items)| items.iter().for_each(|i| generate_all_grammars(i, out)));
+            .for_each(|(_, items)| items.iter().for_each(|i| collect_grammar_modules(i, out)));
 
         if m.attrs
             .iter()
             .any(|a| a.path == parse_quote!(rust_sitter::grammar))
         {
-            out.push(generate_grammar(m).to_string())
+            out.push(m)
         }
     }
 }
 
+/// The key under which generated grammars keep the signatures of their Rust types (and of
+/// the types of the grammars they extend), so that overrides can be checked against the base
+/// types even when the base grammar is imported from a dependency.
+pub(crate) const TYPES_KEY: &str = "rust_sitter_types";
+
+/// A type in a signature, spelled with the last segment of each path and with lifetimes and the
+/// type parameters of the grammar type replaced, so that `std::boxed::Box<Expr<'a>>` and
+/// `Box<Expr<'src>>` compare equal.
+fn normalized_type(ty: &syn::Type, params: &[String]) -> String {
+    let argument = |arg: &syn::GenericArgument| match arg {
+        syn::GenericArgument::Lifetime(_) => "'_".to_string(),
+        syn::GenericArgument::Type(ty) => normalized_type(ty, params),
+        other => other.to_token_stream().to_string(),
+    };
+
+    match ty {
+        syn::Type::Path(p) if p.qself.is_none() => {
+            let last = p.path.segments.last().unwrap();
+            let name = last.ident.to_string();
+            let name = match params.iter().position(|param| *param == name) {
+                Some(index) => format!("T{index}"),
+                None => name,
+            };
+            match &last.arguments {
+                syn::PathArguments::AngleBracketed(args) => format!(
+                    "{name}<{}>",
+                    args.args
+                        .iter()
+                        .map(argument)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                _ => name,
+            }
+        }
+        syn::Type::Reference(r) => format!(
+            "&'_ {}{}",
+            if r.mutability.is_some() { "mut " } else { "" },
+            normalized_type(&r.elem, params)
+        ),
+        syn::Type::Tuple(t) => format!(
+            "({})",
+            t.elems
+                .iter()
+                .map(|ty| normalized_type(ty, params))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        syn::Type::Paren(p) => normalized_type(&p.elem, params),
+        syn::Type::Group(g) => normalized_type(&g.elem, params),
+        other => other.to_token_stream().to_string(),
+    }
+}
+
+/// The normalized names and types of a list of fields, naming unnamed fields by position.
+fn fields_signature(fields: &Fields, params: &[String]) -> Value {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, f)| {
+            let name = f
+                .ident
+                .as_ref()
+                .map(|ident| ident.to_string().trim_start_matches("r#").to_string())
+                .unwrap_or_else(|| i.to_string());
+            Value::from(vec![name, normalized_type(&f.ty, params)])
+        })
+        .collect()
+}
+
+/// The signature of a grammar type: the names and normalized types of its fields (and the names
+/// of its variants), which an override must keep. Attributes, visibility and the spelling of
+/// paths, lifetimes and type parameters are left out.
+fn type_signature(item: &Item) -> Option<(String, Value)> {
+    let params = |generics: &syn::Generics| -> Vec<String> {
+        generics
+            .type_params()
+            .map(|param| param.ident.to_string())
+            .collect()
+    };
+
+    match item {
+        Item::Struct(s) => Some((
+            s.ident.to_string(),
+            fields_signature(&s.fields, &params(&s.generics)),
+        )),
+        Item::Enum(e) => {
+            let params = params(&e.generics);
+            let variants: Map<String, Value> = e
+                .variants
+                .iter()
+                .map(|v| (v.ident.to_string(), fields_signature(&v.fields, &params)))
+                .collect();
+            Some((e.ident.to_string(), Value::Object(variants)))
+        }
+        _ => None,
+    }
+}
+
+/// Removes the type signatures kept in a generated grammar, leaving a plain Tree Sitter grammar.
+fn without_type_signatures(mut grammar: Value) -> Value {
+    grammar.as_object_mut().unwrap().remove(TYPES_KEY);
+    grammar
+}
+
+/// Generates the grammar for a module, merging in the rules of the grammar it
+/// extends (and transitively, the grammars that one extends). Base grammars are
+/// looked up among the modules of the current crate first, and then among the
+/// grammars imported from dependencies, which already include their own bases.
+///
+/// Types that override a base type must keep its fields and their types, since the
+/// base grammar still extracts them with its own `Extract` implementations.
+fn generate_extended_grammar(
+    module: &ItemMod,
+    modules: &[&ItemMod],
+    imported: &[Value],
+    visiting: &mut Vec<String>,
+) -> Value {
+    let args = grammar_args(&module.attrs).unwrap();
+    let mut types: Map<String, Value> = module
+        .content
+        .as_ref()
+        .unwrap()
+        .1
+        .iter()
+        .filter_map(type_signature)
+        .collect();
+
+    let mut grammar = generate_grammar(module);
+    if let Some(base_name) = args.extends() {
+        visiting.push(args.name.value());
+        if visiting.contains(&base_name) {
+            panic!("Grammar `{base_name}` transitively extends itself");
+        }
+
+        let base_grammar = if let Some(base_module) = modules
+            .iter()
+            .find(|m| grammar_args(&m.attrs).unwrap().name.value() == base_name)
+        {
+            generate_extended_grammar(base_module, modules, imported, visiting)
+        } else {
+            imported
+                .iter()
+                .find(|g| g["name"] == base_name.as_str())
+                .cloned()
+                .unwrap_or_else(|| panic!("Could not find the grammar `{base_name}` to extend"))
+        };
+
+        let base_types = base_grammar[TYPES_KEY]
+            .as_object()
+            .cloned()
+            .unwrap_or_default();
+        for (name, signature) in &types {
+            if base_types.get(name).is_some_and(|base| base != signature) {
+                panic!(
+                    "The type `{name}` in grammar `{}` overrides the type of the same name in `{base_name}`, but changes its fields. Overriding types must keep the fields and field types of the base type, which is still extracted by the base grammar",
+                    args.name.value()
+                );
+            }
+        }
+        for (name, signature) in base_types {
+            types.entry(name).or_insert(signature);
+        }
+
+        grammar = extend_grammar(&base_grammar, grammar);
+    }
+
+    grammar[TYPES_KEY] = Value::Object(types);
+    grammar
+}
+
 #[cfg(feature = "build_parsers")]
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[cfg(feature = "build_parsers")]
 use tree_sitter_cli::generate;
@@ -40,32 +358,143 @@ use tree_sitter_cli::generate;
 /// Using the `cc` crate, generates and compiles a C parser with Tree Sitter
 /// for every Rust Sitter grammar found in the given module and recursive
 /// submodules.
+///
+/// The generated C and the compiled parsers are cached in `OUT_DIR`, keyed by a
+/// hash of the grammar, so grammars that did not change since the last run of the
+/// build script are neither regenerated nor recompiled.
+///
+/// Prints `cargo:rerun-if-changed` for each file containing a grammar, as well as
+/// the root file and the files that declare modules, so that the build script
+/// only re-runs when one of them is edited.
 pub fn build_parsers(root_file: &Path) {
-    use std::env;
+    let (items, grammar_files) = parse_grammar_files(root_file);
+    for file in &grammar_files {
+        println!("cargo:rerun-if-changed={}", file.display());
+    }
+
+    let target = std::env::var("TARGET").unwrap();
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    generate_grammars_for_items(&items, &imported_grammars())
+        .into_iter()
+        .for_each(|grammar| {
+            let grammar = without_type_signatures(grammar).to_string();
+            let grammar_name = compile_parser(&grammar, &out_dir, &target, cc::Build::new());
+            println!("cargo:rustc-link-lib=static={grammar_name}");
+            println!("cargo:rustc-link-search=native={}", out_dir.display());
+        });
+}
+
+#[cfg(feature = "build_parsers")]
+/// Whether the items declare a module in another file (`mod foo;`), at any depth.
+fn declares_module_file(items: &[Item]) -> bool {
+    items.iter().any(|item| match item {
+        Item::Mod(m) => match &m.content {
+            Some((_, items)) => declares_module_file(items),
+            None => true,
+        },
+        _ => false,
+    })
+}
 
+#[cfg(feature = "build_parsers")]
+/// Parses the given module with its submodules inlined, like
+/// `syn_inline_mod::parse_and_inline_modules`, also returning the files that
+/// the grammars depend on. Grammar modules are always written inline, so these
+/// are the files that define Rust Sitter grammars, along with the root file and
+/// every file that declares a module in another file, since adding or removing
+/// a module there can add or remove grammars.
+fn parse_grammar_files(root_file: &Path) -> (Vec<Item>, Vec<PathBuf>) {
+    let mut grammar_files = vec![];
+    let (file, _) = syn_inline_mod::InlinerBuilder::new()
+        .inline_with_callback(root_file, |path, contents| {
+            let watched = path == root_file
+                || syn::parse_file(&contents).is_ok_and(|file| {
+                    let mut modules = vec![];
+                    file.items
+                        .iter()
+                        .for_each(|i| collect_grammar_modules(i, &mut modules));
+                    !modules.is_empty() || declares_module_file(&file.items)
+                });
+            if watched {
+                grammar_files.push(path.to_path_buf());
+            }
+        })
+        .unwrap()
+        .into_output_and_errors();
+
+    (file.items, grammar_files)
+}
+
+#[cfg(feature = "build_parsers")]
+/// Like [`build_parsers`], but for use outside of a build script: the parsers are compiled
+/// for `target` into static libraries in `out_dir`, without printing instructions for Cargo.
+pub fn build_parsers_into(root_file: &Path, out_dir: &Path, host: &str, target: &str) {
+    std::fs::create_dir_all(out_dir).unwrap();
     generate_grammars(root_file).iter().for_each(|grammar| {
-        let dir = tempfile::Builder::new()
-            .prefix("grammar")
-            .tempdir()
-            .unwrap();
-        let grammar_file = dir.path().join("parser.c");
-        let mut f = std::fs::File::create(grammar_file).unwrap();
+        let mut build = cc::Build::new();
+        build.host(host).target(target).opt_level(2).debug(false);
+        compile_parser(grammar, out_dir, target, build);
+    });
+}
+
+#[cfg(feature = "build_parsers")]
+/// Hashes bytes with 64-bit FNV-1a, which is stable across Rust versions and platforms
+/// (unlike `DefaultHasher`), so cache keys survive toolchain updates.
+fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(feature = "build_parsers")]
+/// The directory caching the generated C and compiled library of a grammar, named after
+/// the grammar and a hash of its JSON, the target and the version of this crate.
+fn parser_cache_dir(out_dir: &Path, grammar_name: &str, grammar: &str, target: &str) -> PathBuf {
+    let key = [grammar, target, env!("CARGO_PKG_VERSION")].join("\0");
+    out_dir.join("rust-sitter-cache").join(format!(
+        "{grammar_name}-{:016x}",
+        fnv1a_hash(key.as_bytes())
+    ))
+}
+
+#[cfg(feature = "build_parsers")]
+/// The file name `cc` gives to a static library for the target.
+fn static_lib_file(name: &str, target: &str) -> String {
+    if target.contains("msvc") {
+        format!("{name}.lib")
+    } else {
+        format!("lib{name}.a")
+    }
+}
+
+#[cfg(feature = "build_parsers")]
+/// Generates the C parser for a grammar and compiles it with `build` into a static library
+/// in `out_dir`, reusing the library cached for an identical grammar if there is one.
+/// Returns the name of the grammar, which is also the name of the library.
+fn compile_parser(grammar: &str, out_dir: &Path, target: &str, mut build: cc::Build) -> String {
+    let grammar_value: Value = serde_json::from_str(grammar).unwrap();
+    let grammar_name = grammar_value["name"].as_str().unwrap().to_string();
+    let lib_file = static_lib_file(&grammar_name, target);
+    let cache_dir = parser_cache_dir(out_dir, &grammar_name, grammar, target);
+
+    if !cache_dir.join(&lib_file).exists() {
+        remove_stale_caches(&cache_dir, &grammar_name);
+        std::fs::create_dir_all(&cache_dir).unwrap();
 
-        let (grammar_name, grammar_c) = generate::generate_parser_for_grammar(grammar).unwrap();
-        f.write_all(grammar_c.as_bytes()).unwrap();
-        drop(f);
+        let (_, grammar_c) = generate::generate_parser_for_grammar(grammar).unwrap();
+        std::fs::write(cache_dir.join("parser.c"), grammar_c).unwrap();
 
-        let header_dir = dir.path().join("tree_sitter");
-        std::fs::create_dir(&header_dir).unwrap();
+        let header_dir = cache_dir.join("tree_sitter");
+        std::fs::create_dir_all(&header_dir).unwrap();
         let mut parser_file = std::fs::File::create(header_dir.join("parser.h")).unwrap();
         parser_file
             .write_all(tree_sitter::PARSER_HEADER.as_bytes())
             .unwrap();
         drop(parser_file);
 
-        let sysroot_dir = dir.path().join("sysroot");
-        if env::var("TARGET").unwrap().starts_with("wasm32") {
-            std::fs::create_dir(&sysroot_dir).unwrap();
+        let sysroot_dir = cache_dir.join("sysroot");
+        if target.starts_with("wasm32") {
+            std::fs::create_dir_all(&sysroot_dir).unwrap();
             let mut stdint = std::fs::File::create(sysroot_dir.join("stdint.h")).unwrap();
             stdint
                 .write_all(include_bytes!("wasm-sysroot/stdint.h"))
@@ -91,20 +520,110 @@ pub fn build_parsers(root_file: &Path) {
             drop(stdbool);
         }
 
-        cc::Build::new()
-            .include(&dir)
+        build
+            .out_dir(&cache_dir)
+            .cargo_metadata(false)
+            .include(&cache_dir)
             .include(&sysroot_dir)
             .flag_if_supported("-Wno-everything")
-            .file(dir.path().join("parser.c"))
+            .file(cache_dir.join("parser.c"))
             .compile(&grammar_name);
-    });
+    }
+
+    std::fs::copy(cache_dir.join(&lib_file), out_dir.join(&lib_file)).unwrap();
+    grammar_name
+}
+
+#[cfg(feature = "build_parsers")]
+/// Removes the caches of previous versions of a grammar, so that they do not accumulate
+/// in `OUT_DIR` as the grammar is edited.
+fn remove_stale_caches(cache_dir: &Path, grammar_name: &str) {
+    let entries = match std::fs::read_dir(cache_dir.parent().unwrap()) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        let is_stale = file_name
+            .to_str()
+            .and_then(|f| f.strip_prefix(grammar_name)?.strip_prefix('-'))
+            .is_some_and(|hash| hash.len() == 16 && hash.chars().all(|c| c.is_ascii_hexdigit()));
+        if is_stale {
+            std::fs::remove_dir_all(entry.path()).unwrap();
+        }
+    }
+}
+
+#[cfg(feature = "build_parsers")]
+/// Writes a complete Tree Sitter grammar package for every Rust Sitter grammar
+/// found in the given module and recursive submodules, for use with editors and
+/// other Tree Sitter tooling. Each package is written to `<out_dir>/<grammar name>`
+/// and contains the grammar JSON (`src/grammar.json`), the generated parser
+/// (`src/parser.c` and `src/tree_sitter/parser.h`), `src/node-types.json`, the
+/// equivalent `grammar.js`, the generated queries (such as
+/// `queries/highlights.scm`) and a `package.json` describing them to the
+/// `tree-sitter` CLI. Returns the package directories.
+pub fn emit_grammar_package(root_file: &Path, out_dir: &Path) -> Vec<PathBuf> {
+    write_highlight_queries(root_file, out_dir);
+
+    generate_grammars(root_file)
+        .iter()
+        .map(|grammar| {
+            let grammar: Value = serde_json::from_str(grammar).unwrap();
+            let grammar_name = grammar["name"].as_str().unwrap();
+            let package_dir = out_dir.join(grammar_name);
+            let src_dir = package_dir.join("src");
+            std::fs::create_dir_all(&src_dir).unwrap();
+
+            let grammar_file = src_dir.join("grammar.json");
+            std::fs::write(
+                &grammar_file,
+                serde_json::to_string_pretty(&grammar).unwrap(),
+            )
+            .unwrap();
+            std::fs::write(
+                package_dir.join("grammar.js"),
+                generate_grammar_js(&grammar),
+            )
+            .unwrap();
+            generate::generate_parser_in_directory(
+                &package_dir,
+                Some(grammar_file.to_str().unwrap()),
+                tree_sitter::LANGUAGE_VERSION,
+                false,
+                None,
+            )
+            .unwrap();
+
+            let mut language = serde_json::json!({ "scope": format!("source.{grammar_name}") });
+            if package_dir.join("queries").join("highlights.scm").exists() {
+                language["highlights"] = "queries/highlights.scm".into();
+            }
+            let package = serde_json::json!({
+                "name": format!("tree-sitter-{grammar_name}"),
+                "version": "0.0.1",
+                "tree-sitter": [language],
+            });
+            std::fs::write(
+                package_dir.join("package.json"),
+                serde_json::to_string_pretty(&package).unwrap(),
+            )
+            .unwrap();
+
+            package_dir
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use syn::parse_quote;
 
-    use super::generate_grammar;
+    use super::{
+        generate_grammar, generate_grammar_js, generate_grammars_for_items,
+        generate_node_types_bindings, generate_samples, import_grammar_json,
+    };
 
     #[test]
     fn enum_with_named_field() {
@@ -470,4 +989,899 @@ mod tests {
         insta::assert_display_snapshot!(grammar);
         tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
     }
+
+    #[test]
+    fn custom_wrappers() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test", wrappers = [Node])]
+            mod grammar {
+                use std::rc::Rc;
+
+                #[rust_sitter::language]
+                pub struct NumberList {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    numbers: Vec<Node<i32>>,
+                    tail: Option<Rc<Tail>>,
+                }
+
+                pub struct Tail {
+                    #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                    name: Node<String>,
+                }
+
+                #[rust_sitter::extra]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s")]
+                    _whitespace: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m);
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_extends() {
+        let file: syn::File = parse_quote! {
+            #[rust_sitter::grammar("query", extends = "expr")]
+            mod query {
+                use super::expr::Expression;
+
+                #[rust_sitter::language]
+                pub struct Query {
+                    #[rust_sitter::leaf(text = "?")]
+                    _question: (),
+                    e: Expression,
+                }
+
+                // Overrides keep the fields of the base type, which still extracts them, so
+                // the new pattern must only match text that `i32::from_str` accepts.
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"[+]?\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                }
+            }
+
+            #[rust_sitter::grammar("expr")]
+            mod expr {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(Number),
+                    #[rust_sitter::prec_left(1)]
+                    Sub(
+                        Box<Expression>,
+                        #[rust_sitter::leaf(text = "-")]
+                        (),
+                        Box<Expression>
+                    ),
+                }
+
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                }
+
+                #[rust_sitter::extra]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s")]
+                    _whitespace: (),
+                }
+            }
+        };
+
+        let grammars = generate_grammars_for_items(&file.items, &[]);
+        let grammar = &grammars[0];
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "The type `Number` in grammar `query` overrides the type of the same name in `expr`, but changes its fields"
+    )]
+    fn grammar_extends_changed_type() {
+        let file: syn::File = parse_quote! {
+            #[rust_sitter::grammar("query", extends = "expr")]
+            mod query {
+                #[rust_sitter::language]
+                pub struct Query {
+                    #[rust_sitter::leaf(text = "?")]
+                    _question: (),
+                    number: Number,
+                }
+
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+(\.\d+)?", transform = |v| v.parse().unwrap())]
+                    v: f64,
+                }
+            }
+
+            #[rust_sitter::grammar("expr")]
+            mod expr {
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                }
+            }
+        };
+
+        generate_grammars_for_items(&file.items, &[]);
+    }
+
+    #[test]
+    fn grammar_extends_respelled_type() {
+        let file: syn::File = parse_quote! {
+            #[rust_sitter::grammar("query", extends = "expr")]
+            mod query {
+                #[rust_sitter::language]
+                pub struct Query<'src> {
+                    #[rust_sitter::leaf(text = "?")]
+                    _question: (),
+                    expression: Expression<'src>,
+                }
+
+                // Only the attributes and the spelling of paths and lifetimes differ.
+                pub enum Expression<'src> {
+                    Name(#[rust_sitter::leaf(pattern = r"[a-zA-Z]+")] &'src str),
+                    #[rust_sitter::prec_left(2)]
+                    Sub(
+                        std::boxed::Box<Expression<'src>>,
+                        #[rust_sitter::leaf(text = "-")]
+                        (),
+                        ::std::boxed::Box<Expression<'src>>,
+                    ),
+                }
+            }
+
+            #[rust_sitter::grammar("expr")]
+            mod expr {
+                #[rust_sitter::language]
+                pub enum Expression<'a> {
+                    Name(#[rust_sitter::leaf(pattern = r"[a-z]+")] &'a str),
+                    #[rust_sitter::prec_left(1)]
+                    Sub(Box<Expression<'a>>, #[rust_sitter::leaf(text = "-")] (), Box<Expression<'a>>),
+                }
+
+                #[rust_sitter::extra]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s")]
+                    _whitespace: (),
+                }
+            }
+        };
+
+        let grammars = generate_grammars_for_items(&file.items, &[]);
+        assert_eq!(
+            grammars[0][super::TYPES_KEY]["Expression"],
+            grammars[1][super::TYPES_KEY]["Expression"]
+        );
+    }
+
+    #[test]
+    fn grammar_extends_imported() {
+        let base = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("literals")]
+            mod literals {
+                #[rust_sitter::language]
+                pub enum Literal {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                        i32
+                    ),
+                    Bool(
+                        #[rust_sitter::leaf(pattern = r"true|false", transform = |v| v == "true")]
+                        bool
+                    ),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let file: syn::File = parse_quote! {
+            #[rust_sitter::grammar("list", extends = "literals")]
+            mod list {
+                use common_syntax::literals::Literal;
+
+                #[rust_sitter::language]
+                pub struct List {
+                    #[rust_sitter::delimited(
+                        #[rust_sitter::leaf(text = ",")]
+                        ()
+                    )]
+                    values: Vec<Literal>,
+                }
+            }
+        };
+
+        let grammars = generate_grammars_for_items(&file.items, &[generate_grammar(&base)]);
+        let grammar = &grammars[0];
+        insta::assert_display_snapshot!(grammar);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn grammar_samples() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                        i32
+                    ),
+                    Variable(
+                        #[rust_sitter::leaf(pattern = r"[a-z][a-z0-9]{0,2}")]
+                        String
+                    ),
+                    Let {
+                        #[rust_sitter::leaf(text = "let")]
+                        _let: (),
+                        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                        name: String,
+                        #[rust_sitter::leaf(text = "=")]
+                        _eq: (),
+                        value: Box<Expression>,
+                    },
+                    List(
+                        #[rust_sitter::leaf(text = "[")]
+                        (),
+                        #[rust_sitter::delimited(
+                            #[rust_sitter::leaf(text = ",")]
+                            ()
+                        )]
+                        Vec<Expression>,
+                        #[rust_sitter::leaf(text = "]")]
+                        (),
+                    ),
+                }
+
+                #[rust_sitter::extra]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s")]
+                    _whitespace: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = generate_grammar(&m).to_string();
+        let samples = generate_samples(&grammar, 10, 4, 42);
+        assert_eq!(samples, generate_samples(&grammar, 10, 4, 42));
+        insta::assert_debug_snapshot!(samples);
+    }
+
+    #[test]
+    fn grammar_js() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+/\d+|[/]\d+", transform = |v| v.parse().unwrap())]
+                        i32
+                    ),
+                    #[rust_sitter::prec_left(1)]
+                    Sub(
+                        Box<Expression>,
+                        #[rust_sitter::leaf(text = "-")]
+                        (),
+                        Box<Expression>,
+                    ),
+                    Call {
+                        #[rust_sitter::word]
+                        #[rust_sitter::leaf(pattern = r"[a-z_]+")]
+                        name: String,
+                        #[rust_sitter::leaf(text = "(")]
+                        _open: (),
+                        #[rust_sitter::delimited(
+                            #[rust_sitter::leaf(text = ",")]
+                            ()
+                        )]
+                        arguments: Vec<Expression>,
+                        #[rust_sitter::leaf(text = "'")]
+                        _quote: Option<()>,
+                        #[rust_sitter::leaf(text = ")")]
+                        _close: (),
+                    },
+                }
+
+                #[rust_sitter::extra]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s")]
+                    _whitespace: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        insta::assert_display_snapshot!(generate_grammar_js(&generate_grammar(&m)));
+    }
+
+    #[test]
+    fn grammar_highlights() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    #[rust_sitter::highlight("number")]
+                    Number(
+                        #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                        i32
+                    ),
+                    Let {
+                        #[rust_sitter::highlight("keyword")]
+                        #[rust_sitter::leaf(text = "let")]
+                        _let: (),
+                        #[rust_sitter::highlight("variable")]
+                        #[rust_sitter::leaf(pattern = r"[a-z]+")]
+                        name: String,
+                        #[rust_sitter::leaf(text = "=")]
+                        _eq: (),
+                        #[rust_sitter::highlight("constant")]
+                        value: Box<Expression>,
+                    },
+                    List(
+                        #[rust_sitter::leaf(text = "[")]
+                        (),
+                        #[rust_sitter::delimited(
+                            #[rust_sitter::highlight("punctuation.delimiter")]
+                            #[rust_sitter::leaf(text = ",")]
+                            ()
+                        )]
+                        Vec<Expression>,
+                        #[rust_sitter::leaf(text = "]")]
+                        (),
+                    ),
+                }
+
+                #[rust_sitter::extra]
+                #[rust_sitter::highlight("comment")]
+                struct Comment {
+                    #[rust_sitter::leaf(pattern = r"#.*")]
+                    _comment: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        insta::assert_display_snapshot!(rust_sitter_common::highlights_query(
+            &m.content.unwrap().1
+        ));
+    }
+
+    #[cfg(feature = "build_parsers")]
+    #[test]
+    fn grammar_package() {
+        let dir = tempfile::tempdir().unwrap();
+        let root_file = dir.path().join("lib.rs");
+        std::fs::write(
+            &root_file,
+            r#"
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct NumberList {
+                    #[rust_sitter::highlight("number")]
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    numbers: Vec<i32>,
+                }
+
+                #[rust_sitter::extra]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s")]
+                    _whitespace: (),
+                }
+            }
+            "#,
+        )
+        .unwrap();
+
+        let out_dir = dir.path().join("grammars");
+        let packages = super::emit_grammar_package(&root_file, &out_dir);
+        assert_eq!(packages, vec![out_dir.join("test")]);
+
+        let read = |path: &str| std::fs::read_to_string(out_dir.join("test").join(path)).unwrap();
+        assert!(read("src/parser.c").contains("tree_sitter_test"));
+        assert!(read("grammar.js").starts_with("module.exports = grammar({"));
+        assert!(read("src/tree_sitter/parser.h").contains("TSLanguage"));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&read("src/grammar.json")).unwrap()["name"],
+            "test"
+        );
+        insta::assert_display_snapshot!(read("src/node-types.json"));
+        assert_eq!(
+            read("queries/highlights.scm"),
+            "(NumberList_numbers) @number\n"
+        );
+        insta::assert_display_snapshot!(read("package.json"));
+    }
+
+    #[test]
+    fn cached_parsers() {
+        let dir = tempfile::tempdir().unwrap();
+        let root_file = dir.path().join("lib.rs");
+        std::fs::write(
+            &root_file,
+            r#"
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                }
+            }
+            "#,
+        )
+        .unwrap();
+
+        // A cached library for the same grammar and target is reused without compiling.
+        let target = "x86_64-unknown-linux-gnu";
+        let out_dir = dir.path().join("out");
+        let grammar = &super::generate_grammars(&root_file)[0];
+        let cache_dir = super::parser_cache_dir(&out_dir, "test", grammar, target);
+        std::fs::create_dir_all(&cache_dir).unwrap();
+        std::fs::write(cache_dir.join("libtest.a"), "cached").unwrap();
+
+        super::build_parsers_into(&root_file, &out_dir, target, target);
+        assert_eq!(
+            std::fs::read_to_string(out_dir.join("libtest.a")).unwrap(),
+            "cached"
+        );
+
+        // Changing the grammar or the target changes the cache key.
+        let other_grammar = grammar.replace(r"\\d+", "[0-9]+");
+        assert_ne!(&other_grammar, grammar);
+        assert_ne!(
+            super::parser_cache_dir(&out_dir, "test", &other_grammar, target),
+            cache_dir
+        );
+        assert_ne!(
+            super::parser_cache_dir(&out_dir, "test", grammar, "wasm32-unknown-unknown"),
+            cache_dir
+        );
+    }
+
+    #[test]
+    fn grammar_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, contents: &str| {
+            std::fs::write(dir.path().join(path), contents).unwrap();
+        };
+        write("lib.rs", "mod arithmetic; mod utils; mod nested;");
+        write(
+            "arithmetic.rs",
+            r#"
+            #[rust_sitter::grammar("arithmetic")]
+            pub mod grammar {
+                #[rust_sitter::language]
+                pub struct Number {
+                    #[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())]
+                    v: i32,
+                }
+            }
+            "#,
+        );
+        write("utils.rs", "pub fn unrelated() {}");
+        write("nested.rs", "pub mod outer { mod inner; }");
+        std::fs::create_dir_all(dir.path().join("nested/outer")).unwrap();
+        write("nested/outer/inner.rs", "pub fn unrelated() {}");
+
+        let (items, grammar_files) = super::parse_grammar_files(&dir.path().join("lib.rs"));
+        assert_eq!(items.len(), 3);
+        assert_eq!(
+            grammar_files,
+            vec![
+                dir.path().join("lib.rs"),
+                dir.path().join("arithmetic.rs"),
+                dir.path().join("nested.rs"),
+            ]
+        );
+    }
+
+    #[test]
+    fn import_grammar() {
+        let imported = import_grammar_json(
+            r#"{
+                "name": "calc",
+                "word": "identifier",
+                "rules": {
+                    "source_file": {
+                        "type": "REPEAT",
+                        "content": { "type": "SYMBOL", "name": "statement" }
+                    },
+                    "statement": {
+                        "type": "CHOICE",
+                        "members": [
+                            {
+                                "type": "SEQ",
+                                "members": [
+                                    { "type": "STRING", "value": "let" },
+                                    {
+                                        "type": "FIELD",
+                                        "name": "name",
+                                        "content": { "type": "SYMBOL", "name": "identifier" }
+                                    },
+                                    { "type": "STRING", "value": "=" },
+                                    {
+                                        "type": "FIELD",
+                                        "name": "value",
+                                        "content": { "type": "SYMBOL", "name": "_expression" }
+                                    },
+                                    { "type": "STRING", "value": ";" }
+                                ]
+                            },
+                            {
+                                "type": "SEQ",
+                                "members": [
+                                    { "type": "SYMBOL", "name": "_expression" },
+                                    { "type": "STRING", "value": ";" }
+                                ]
+                            }
+                        ]
+                    },
+                    "_expression": {
+                        "type": "CHOICE",
+                        "members": [
+                            { "type": "SYMBOL", "name": "number" },
+                            { "type": "SYMBOL", "name": "identifier" },
+                            { "type": "SYMBOL", "name": "call" },
+                            {
+                                "type": "PREC_LEFT",
+                                "value": 1,
+                                "content": {
+                                    "type": "SEQ",
+                                    "members": [
+                                        {
+                                            "type": "FIELD",
+                                            "name": "left",
+                                            "content": { "type": "SYMBOL", "name": "_expression" }
+                                        },
+                                        {
+                                            "type": "FIELD",
+                                            "name": "operator",
+                                            "content": {
+                                                "type": "CHOICE",
+                                                "members": [
+                                                    { "type": "STRING", "value": "+" },
+                                                    { "type": "STRING", "value": "-" }
+                                                ]
+                                            }
+                                        },
+                                        {
+                                            "type": "FIELD",
+                                            "name": "right",
+                                            "content": { "type": "SYMBOL", "name": "_expression" }
+                                        }
+                                    ]
+                                }
+                            }
+                        ]
+                    },
+                    "call": {
+                        "type": "PREC_DYNAMIC",
+                        "value": 1,
+                        "content": {
+                            "type": "SEQ",
+                            "members": [
+                                {
+                                    "type": "FIELD",
+                                    "name": "function",
+                                    "content": { "type": "SYMBOL", "name": "identifier" }
+                                },
+                                { "type": "STRING", "value": "(" },
+                                {
+                                    "type": "FIELD",
+                                    "name": "arguments",
+                                    "content": {
+                                        "type": "CHOICE",
+                                        "members": [
+                                            {
+                                                "type": "SEQ",
+                                                "members": [
+                                                    { "type": "SYMBOL", "name": "_expression" },
+                                                    {
+                                                        "type": "REPEAT",
+                                                        "content": {
+                                                            "type": "SEQ",
+                                                            "members": [
+                                                                { "type": "STRING", "value": "," },
+                                                                { "type": "SYMBOL", "name": "_expression" }
+                                                            ]
+                                                        }
+                                                    }
+                                                ]
+                                            },
+                                            { "type": "BLANK" }
+                                        ]
+                                    }
+                                },
+                                { "type": "STRING", "value": ")" }
+                            ]
+                        }
+                    },
+                    "number": { "type": "PATTERN", "value": "\\d+" },
+                    "identifier": { "type": "PATTERN", "value": "[a-z_]+" },
+                    "comment": { "type": "PATTERN", "value": "//.*" }
+                },
+                "extras": [
+                    { "type": "PATTERN", "value": "\\s" },
+                    { "type": "SYMBOL", "name": "comment" }
+                ],
+                "externals": [{ "type": "SYMBOL", "name": "heredoc" }],
+                "conflicts": [],
+                "inline": []
+            }"#,
+        );
+        insta::assert_snapshot!(imported);
+
+        let m = syn::parse_file(&imported)
+            .unwrap()
+            .items
+            .into_iter()
+            .find_map(|item| match item {
+                syn::Item::Mod(m) => Some(m),
+                _ => None,
+            })
+            .unwrap();
+        let grammar = generate_grammar(&m);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn import_grammar_keywords() {
+        let imported = import_grammar_json(
+            r#"{
+                "name": "keywords",
+                "rules": {
+                    "source_file": {
+                        "type": "REPEAT",
+                        "content": { "type": "SYMBOL", "name": "statement" }
+                    },
+                    "statement": {
+                        "type": "CHOICE",
+                        "members": [
+                            { "type": "SYMBOL", "name": "type" },
+                            { "type": "SYMBOL", "name": "self" }
+                        ]
+                    },
+                    "type": {
+                        "type": "SEQ",
+                        "members": [
+                            { "type": "STRING", "value": "type" },
+                            { "type": "SYMBOL", "name": "self" },
+                            {
+                                "type": "FIELD",
+                                "name": "match",
+                                "content": { "type": "SYMBOL", "name": "self" }
+                            },
+                            {
+                                "type": "REPEAT",
+                                "content": { "type": "SYMBOL", "name": "yield" }
+                            }
+                        ]
+                    },
+                    "self": { "type": "PATTERN", "value": "[a-z]+" },
+                    "yield": { "type": "PATTERN", "value": "\\d+" }
+                },
+                "extras": [{ "type": "PATTERN", "value": "\\s" }],
+                "conflicts": [],
+                "inline": []
+            }"#,
+        );
+        insta::assert_snapshot!(imported);
+
+        let m = syn::parse_file(&imported)
+            .unwrap()
+            .items
+            .into_iter()
+            .find_map(|item| match item {
+                syn::Item::Mod(m) => Some(m),
+                _ => None,
+            })
+            .unwrap();
+        let grammar = generate_grammar(&m);
+        tree_sitter_cli::generate::generate_parser_for_grammar(&grammar.to_string()).unwrap();
+    }
+
+    #[test]
+    fn import_punctuation_names() {
+        use super::import::token_name;
+
+        for (open, name) in [("(", "paren"), ("[", "bracket"), ("{", "brace")] {
+            assert_eq!(token_name(open), Some(format!("open_{name}")));
+        }
+        for (close, name) in [(")", "paren"), ("]", "bracket"), ("}", "brace")] {
+            assert_eq!(token_name(close), Some(format!("close_{name}")));
+        }
+        assert_eq!(token_name("&").as_deref(), Some("and"));
+        assert_eq!(token_name("|").as_deref(), Some("or"));
+    }
+
+    #[test]
+    fn node_types_bindings() {
+        let bindings = generate_node_types_bindings(
+            r#"[
+                {
+                    "type": "_value",
+                    "named": true,
+                    "subtypes": [
+                        { "type": "array", "named": true },
+                        { "type": "number", "named": true },
+                        { "type": "null", "named": true }
+                    ]
+                },
+                {
+                    "type": "array",
+                    "named": true,
+                    "fields": {},
+                    "children": {
+                        "multiple": true,
+                        "required": false,
+                        "types": [{ "type": "_value", "named": true }]
+                    }
+                },
+                {
+                    "type": "document",
+                    "named": true,
+                    "fields": {
+                        "type": {
+                            "multiple": false,
+                            "required": false,
+                            "types": [{ "type": "type_name", "named": true }]
+                        },
+                        "value": {
+                            "multiple": false,
+                            "required": true,
+                            "types": [{ "type": "_value", "named": true }]
+                        },
+                        "sign": {
+                            "multiple": false,
+                            "required": false,
+                            "types": [
+                                { "type": "+", "named": false },
+                                { "type": "-", "named": false }
+                            ]
+                        }
+                    }
+                },
+                { "type": "null", "named": true, "fields": {} },
+                { "type": "number", "named": true },
+                { "type": "type_name", "named": true },
+                { "type": "+", "named": false },
+                { "type": "-", "named": false },
+                { "type": "[", "named": false },
+                { "type": "]", "named": false }
+            ]"#,
+        );
+        insta::assert_snapshot!(bindings);
+        syn::parse_file(&bindings).unwrap();
+    }
+
+    #[test]
+    fn check_grammar() {
+        let dir = tempfile::tempdir().unwrap();
+        let root_file = dir.path().join("lib.rs");
+        std::fs::write(
+            &root_file,
+            r#"
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                #[rust_sitter::language]
+                pub enum Expression {
+                    Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+                    Sub(Box<Expression>, #[rust_sitter::leaf(text = "-")] (), Box<Expression>),
+                }
+
+                #[rust_sitter::extra]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s")]
+                    _whitespace: (),
+                }
+
+                pub struct Unused {
+                    #[rust_sitter::leaf(text = "unused")]
+                    _unused: (),
+                }
+            }
+            "#,
+        )
+        .unwrap();
+
+        let problems = super::check_grammars(&root_file);
+        assert_eq!(problems.len(), 2);
+        assert!(problems.iter().all(|(grammar, _)| grammar == "test"));
+        assert!(problems[0]
+            .1
+            .contains("Specify a left or right associativity in `Expression::Sub`"));
+        assert_eq!(
+            problems[1].1,
+            "The type `Unused` is not reachable from the root type, so it is never parsed"
+        );
+    }
+
+    #[test]
+    fn grammar_docs() {
+        let m = if let syn::Item::Mod(m) = parse_quote! {
+            /// Lists of numbers.
+            #[rust_sitter::grammar("test")]
+            mod grammar {
+                /// A list of expressions.
+                #[rust_sitter::language]
+                pub struct List {
+                    #[rust_sitter::leaf(text = "[")]
+                    _open: (),
+                    /// The elements, separated by commas.
+                    #[rust_sitter::delimited(
+                        #[rust_sitter::leaf(text = ",")]
+                        ()
+                    )]
+                    elements: Vec<Expression>,
+                    #[rust_sitter::leaf(text = "]")]
+                    _close: (),
+                }
+
+                pub enum Expression {
+                    /// A decimal number.
+                    Number(#[rust_sitter::leaf(pattern = r"\d+", transform = |v| v.parse().unwrap())] i32),
+                    #[rust_sitter::prec_left(1)]
+                    Sub(Box<Expression>, #[rust_sitter::leaf(text = "-")] (), Box<Expression>),
+                    Neg {
+                        #[rust_sitter::leaf(text = "-")]
+                        _minus: (),
+                        /// The negated expression.
+                        value: Option<Box<Expression>>,
+                    },
+                }
+
+                #[rust_sitter::extra]
+                struct Whitespace {
+                    #[rust_sitter::leaf(pattern = r"\s")]
+                    _whitespace: (),
+                }
+            }
+        } {
+            m
+        } else {
+            panic!()
+        };
+
+        let grammar = super::generate_extended_grammar(&m, &[&m], &[], &mut vec![]);
+        let docs = super::docs::grammar_docs(&m, &grammar);
+        assert_eq!(docs.name, "test");
+        insta::assert_display_snapshot!(docs.ebnf);
+        insta::assert_display_snapshot!(docs.reference);
+        assert_eq!(
+            docs.diagrams
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["List", "Expression", "Whitespace"]
+        );
+        insta::assert_display_snapshot!(docs.diagrams[2].1);
+        insta::assert_display_snapshot!(docs.diagrams[0].1);
+        insta::assert_display_snapshot!(docs.diagrams[1].1);
+    }
 }
@@ -0,0 +1,283 @@
+//! The `rust-sitter` command, which runs the steps usually done by a build script on the
+//! grammars in a Rust file.
+
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use serde_json::Value;
+use tree_sitter_cli::generate;
+
+fn main() {
+    let file_arg = Arg::with_name("file")
+        .help("The Rust file containing the grammars, such as `src/main.rs`")
+        .required(true);
+
+    let matches = App::new("rust-sitter")
+        .about("Generates, builds and checks Rust Sitter grammars")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("generate")
+                .about("Generates the Tree Sitter grammar JSON of each grammar")
+                .arg(&file_arg)
+                .arg(
+                    Arg::with_name("out-dir")
+                        .help(
+                            "Writes each grammar to `<out-dir>/<name>.json` instead of printing it",
+                        )
+                        .long("out-dir")
+                        .short("o")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("build")
+                .about("Compiles the parser of each grammar into a static library")
+                .arg(&file_arg)
+                .arg(
+                    Arg::with_name("out-dir")
+                        .help("The directory to write the libraries to")
+                        .long("out-dir")
+                        .short("o")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("target")
+                        .help("The target triple to compile for (defaults to the host)")
+                        .long("target")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("parse")
+                .about("Parses a file and prints its syntax tree with Rust names")
+                .arg(&file_arg)
+                .arg(
+                    Arg::with_name("input")
+                        .help("The file to parse")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("grammar")
+                        .help("The name of the grammar to parse with, if the file has several")
+                        .long("grammar")
+                        .short("g")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("docs")
+                .about("Writes an EBNF definition, railroad diagrams and a reference page for each grammar")
+                .arg(&file_arg)
+                .arg(
+                    Arg::with_name("out-dir")
+                        .help("The directory to write the documentation to")
+                        .long("out-dir")
+                        .short("o")
+                        .takes_value(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Reports conflicts and other problems in each grammar")
+                .arg(&file_arg),
+        )
+        .get_matches();
+
+    match matches.subcommand() {
+        ("generate", Some(args)) => generate_command(args),
+        ("build", Some(args)) => build_command(args),
+        ("parse", Some(args)) => parse_command(args),
+        ("docs", Some(args)) => docs_command(args),
+        ("check", Some(args)) => check_command(args),
+        _ => unreachable!(),
+    }
+}
+
+fn root_file(args: &ArgMatches) -> PathBuf {
+    PathBuf::from(args.value_of("file").unwrap())
+}
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("error: {message}");
+    process::exit(1)
+}
+
+fn generate_command(args: &ArgMatches) {
+    let grammars = rust_sitter_tool::generate_grammars(&root_file(args));
+    for grammar in grammars {
+        let grammar: Value = serde_json::from_str(&grammar).unwrap();
+        let json = serde_json::to_string_pretty(&grammar).unwrap();
+        match args.value_of("out-dir") {
+            Some(out_dir) => {
+                std::fs::create_dir_all(out_dir).unwrap();
+                let path =
+                    Path::new(out_dir).join(format!("{}.json", grammar["name"].as_str().unwrap()));
+                std::fs::write(&path, json).unwrap();
+                println!("{}", path.display());
+            }
+            None => println!("{json}"),
+        }
+    }
+}
+
+/// The target triple of the host, as reported by `rustc`.
+fn host_target() -> String {
+    let output = Command::new(std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string()))
+        .arg("-vV")
+        .output()
+        .unwrap_or_else(|e| {
+            fail(format!(
+                "Could not run `rustc` to find the host target: {e}"
+            ))
+        });
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))
+        .map(str::to_string)
+        .unwrap_or_else(|| fail("Could not find the host target in the output of `rustc -vV`"))
+}
+
+fn build_command(args: &ArgMatches) {
+    let host = host_target();
+    let target = args.value_of("target").map_or(host.clone(), str::to_string);
+    rust_sitter_tool::build_parsers_into(
+        &root_file(args),
+        Path::new(args.value_of("out-dir").unwrap()),
+        &host,
+        &target,
+    );
+}
+
+fn parse_command(args: &ArgMatches) {
+    let root_file = root_file(args);
+    let grammars: Vec<Value> = rust_sitter_tool::generate_grammars(&root_file)
+        .iter()
+        .map(|g| serde_json::from_str(g).unwrap())
+        .collect();
+    let grammar = match args.value_of("grammar") {
+        Some(name) => grammars
+            .iter()
+            .find(|g| g["name"] == name)
+            .unwrap_or_else(|| fail(format!("Could not find the grammar `{name}`"))),
+        None if grammars.len() == 1 => &grammars[0],
+        None => fail("The file has several grammars, choose one with `--grammar`"),
+    };
+    let grammar_name = grammar["name"].as_str().unwrap();
+
+    let input_path = args.value_of("input").unwrap();
+    let input = std::fs::read_to_string(input_path)
+        .unwrap_or_else(|e| fail(format!("Could not read `{input_path}`: {e}")));
+
+    // Generate the parser into a temporary grammar directory and load it as a shared library.
+    let dir = tempfile::Builder::new()
+        .prefix("grammar")
+        .tempdir()
+        .unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir_all(&src_dir).unwrap();
+    let grammar_file = src_dir.join("grammar.json");
+    std::fs::write(&grammar_file, grammar.to_string()).unwrap();
+    generate::generate_parser_in_directory(
+        &dir.path().to_path_buf(),
+        Some(grammar_file.to_str().unwrap()),
+        tree_sitter::LANGUAGE_VERSION,
+        false,
+        None,
+    )
+    .unwrap_or_else(|e| fail(e));
+    let language = tree_sitter_loader::Loader::with_parser_lib_path(dir.path().join("lib"))
+        .load_language_at_path(&src_dir, &src_dir)
+        .unwrap_or_else(|e| fail(e));
+
+    let mut parser = tree_sitter::Parser::new();
+    parser.set_language(language).unwrap();
+    let tree = parser.parse(&input, None).unwrap();
+
+    let rule_names = rust_sitter_tool::generate_rule_names(&root_file)
+        .into_iter()
+        .find(|(name, _)| name == grammar_name)
+        .map(|(_, rule_names)| rule_names)
+        .unwrap_or_default();
+    print_node(tree.root_node(), None, &input, &rule_names, 0);
+
+    if tree.root_node().has_error() {
+        process::exit(1);
+    }
+}
+
+/// Prints the named nodes below `node`, one per line, with the Rust names of their kinds.
+/// Nodes without named children are followed by their text.
+fn print_node(
+    node: tree_sitter::Node,
+    field: Option<&str>,
+    source: &str,
+    rule_names: &[(String, String)],
+    depth: usize,
+) {
+    let mut name = rule_names
+        .iter()
+        .find(|(kind, _)| kind == node.kind())
+        .map_or(node.kind(), |(_, rust_name)| rust_name)
+        .to_string();
+    if node.is_missing() {
+        name.insert_str(0, "MISSING ");
+    }
+
+    let mut line = "  ".repeat(depth);
+    // Leaves are named after their field already, such as `Expression::Number.0`.
+    if let Some(field) = field.filter(|_| !name.contains('.')) {
+        line.push_str(&format!("{field}: "));
+    }
+    line.push_str(&format!(
+        "{name} {}..{}",
+        node.start_byte(),
+        node.end_byte()
+    ));
+    if node.named_child_count() == 0 {
+        line.push_str(&format!(
+            " {:?}",
+            &source[node.start_byte()..node.end_byte()]
+        ));
+    }
+    println!("{line}");
+
+    let mut cursor = node.walk();
+    if cursor.goto_first_child() {
+        loop {
+            if cursor.node().is_named() || cursor.node().is_missing() {
+                print_node(
+                    cursor.node(),
+                    cursor.field_name(),
+                    source,
+                    rule_names,
+                    depth + 1,
+                );
+            }
+
+            if !cursor.goto_next_sibling() {
+                break;
+            }
+        }
+    }
+}
+
+fn docs_command(args: &ArgMatches) {
+    let out_dir = Path::new(args.value_of("out-dir").unwrap());
+    for docs_dir in rust_sitter_tool::write_grammar_docs(&root_file(args), out_dir) {
+        println!("{}", docs_dir.display());
+    }
+}
+
+fn check_command(args: &ArgMatches) {
+    let problems = rust_sitter_tool::check_grammars(&root_file(args));
+    for (grammar, message) in &problems {
+        println!("{grammar}: {message}");
+    }
+
+    if !problems.is_empty() {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,440 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::import::{camel_case, escape_keyword, reaches, snake_case, token_name};
+
+const INDENT: &str = "    ";
+
+/// What a field or a child of a node contains.
+enum Element {
+    /// Anonymous tokens, which have no content.
+    Unit,
+    Type(String),
+}
+
+struct FieldDef {
+    /// The Tree Sitter field name, or `None` for the children without a field.
+    tree_sitter_name: Option<String>,
+    name: String,
+    element: Element,
+    multiple: bool,
+    required: bool,
+}
+
+struct VariantDef {
+    name: String,
+    /// The concrete kinds (with whether they are named) matched by the variant.
+    kinds: Vec<(String, bool)>,
+    element: Element,
+}
+
+enum TypeBody {
+    /// A node without fields or children, which keeps its text.
+    Leaf,
+    Struct(Vec<FieldDef>),
+    Enum(Vec<VariantDef>),
+}
+
+struct TypeDef {
+    name: String,
+    kind: String,
+    body: TypeBody,
+}
+
+struct Generator<'a> {
+    nodes: HashMap<&'a str, &'a Value>,
+    type_names: HashMap<&'a str, String>,
+    types: Vec<TypeDef>,
+}
+
+impl<'a> Generator<'a> {
+    fn fresh_type_name(&self, name: String) -> String {
+        let name = escape_keyword(name);
+        let taken = |n: &String| {
+            self.type_names.values().any(|t| t == n) || self.types.iter().any(|t| t.name == *n)
+        };
+        if !taken(&name) {
+            return name;
+        }
+
+        (2..)
+            .map(|i| format!("{name}{i}"))
+            .find(|n| !taken(n))
+            .unwrap()
+    }
+
+    /// The concrete kinds that a node type can have, expanding supertypes into their subtypes.
+    fn concrete_kinds(&self, kind: &str, named: bool) -> Vec<(String, bool)> {
+        match self.nodes.get(kind).filter(|_| named) {
+            Some(node) if node["subtypes"].is_array() => node["subtypes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .flat_map(|subtype| {
+                    self.concrete_kinds(
+                        subtype["type"].as_str().unwrap(),
+                        subtype["named"].as_bool().unwrap(),
+                    )
+                })
+                .collect(),
+            _ => vec![(kind.to_string(), named)],
+        }
+    }
+
+    /// Builds the variants for a list of node types, as found in `subtypes` or in the `types`
+    /// of a field.
+    fn variants(&self, types: &[Value]) -> Vec<VariantDef> {
+        let mut variants: Vec<VariantDef> = vec![];
+        for (i, ty) in types.iter().enumerate() {
+            let kind = ty["type"].as_str().unwrap();
+            let named = ty["named"].as_bool().unwrap();
+            let (name, element) = if named {
+                let type_name = self.type_names[kind].clone();
+                (type_name.clone(), Element::Type(type_name))
+            } else {
+                (
+                    token_name(kind)
+                        .map(|n| escape_keyword(camel_case(&n)))
+                        .unwrap_or_else(|| format!("Token{i}")),
+                    Element::Unit,
+                )
+            };
+
+            let name = if variants.iter().any(|v| v.name == name) {
+                format!("{name}{i}")
+            } else {
+                name
+            };
+            variants.push(VariantDef {
+                name,
+                kinds: self.concrete_kinds(kind, named),
+                element,
+            });
+        }
+        variants
+    }
+
+    /// Converts the description of a field (or of the children) into a field definition,
+    /// defining an enum named after `owner` and `hint` if it can hold several node types.
+    fn field(
+        &mut self,
+        owner: &str,
+        hint: &str,
+        tree_sitter_name: Option<&str>,
+        field: &Value,
+    ) -> FieldDef {
+        let types = field["types"].as_array().unwrap();
+        let element = match types.as_slice() {
+            [ty] if ty["named"] == true => {
+                Element::Type(self.type_names[ty["type"].as_str().unwrap()].clone())
+            }
+            [_] => Element::Unit,
+            types => {
+                let name = self.fresh_type_name(format!("{owner}{}", camel_case(hint)));
+                let variants = self.variants(types);
+                self.types.push(TypeDef {
+                    name: name.clone(),
+                    kind: format!("{owner}.{hint}"),
+                    body: TypeBody::Enum(variants),
+                });
+                Element::Type(name)
+            }
+        };
+
+        let name = escape_keyword(snake_case(hint));
+
+        FieldDef {
+            tree_sitter_name: tree_sitter_name.map(str::to_string),
+            name,
+            element,
+            multiple: field["multiple"].as_bool().unwrap_or(false),
+            required: field["required"].as_bool().unwrap_or(false),
+        }
+    }
+
+    fn define(&mut self, node: &Value) {
+        let kind = node["type"].as_str().unwrap();
+        let name = self.type_names[kind].clone();
+
+        // Reserve the position of the type before defining the enums for its fields.
+        let index = self.types.len();
+        self.types.push(TypeDef {
+            name: name.clone(),
+            kind: kind.to_string(),
+            body: TypeBody::Leaf,
+        });
+
+        let body = if let Some(subtypes) = node["subtypes"].as_array() {
+            TypeBody::Enum(self.variants(subtypes))
+        } else {
+            let mut fields: Vec<FieldDef> = node["fields"]
+                .as_object()
+                .into_iter()
+                .flatten()
+                .map(|(field_name, field)| self.field(&name, field_name, Some(field_name), field))
+                .collect();
+            if node["children"].is_object() {
+                let mut children = self.field(&name, "children", None, &node["children"]);
+                if fields.iter().any(|f| f.name == children.name) {
+                    children.name = "children_".to_string();
+                }
+                fields.push(children);
+            }
+
+            if fields.is_empty() {
+                TypeBody::Leaf
+            } else {
+                TypeBody::Struct(fields)
+            }
+        };
+
+        self.types[index].body = body;
+    }
+}
+
+/// The types that each type refers to directly, without going through a `Vec`.
+fn direct_references(types: &[TypeDef]) -> HashMap<&str, Vec<&str>> {
+    types
+        .iter()
+        .map(|t| {
+            let references = match &t.body {
+                TypeBody::Leaf => vec![],
+                TypeBody::Struct(fields) => fields
+                    .iter()
+                    .filter(|f| !f.multiple)
+                    .filter_map(|f| match &f.element {
+                        Element::Type(name) => Some(name.as_str()),
+                        Element::Unit => None,
+                    })
+                    .collect(),
+                TypeBody::Enum(variants) => variants
+                    .iter()
+                    .filter_map(|v| match &v.element {
+                        Element::Type(name) => Some(name.as_str()),
+                        Element::Unit => None,
+                    })
+                    .collect(),
+            };
+            (t.name.as_str(), references)
+        })
+        .collect()
+}
+
+/// The Rust type of an element, boxed if it contains the type that owns it.
+fn element_type(
+    element: &Element,
+    owner: &str,
+    references: &HashMap<&str, Vec<&str>>,
+    boxed: bool,
+) -> String {
+    match element {
+        Element::Unit => "()".to_string(),
+        Element::Type(name) if boxed && reaches(name, owner, references) => {
+            format!("Box<{name}>")
+        }
+        Element::Type(name) => name.clone(),
+    }
+}
+
+/// Generates Rust types for the nodes of a Tree Sitter language from its `node-types.json`,
+/// along with implementations of `rust_sitter::Extract` that build them from a tree. This gives
+/// languages that are not defined with Rust Sitter, such as existing parsers for JSON or Bash,
+/// the same typed API through `rust_sitter::external::parse`.
+///
+/// Supertypes and fields that can hold several node types become enums, other named nodes
+/// become structs with a field for each Tree Sitter field (and a `children` field for the
+/// children without one), and nodes without fields or children keep their `text`. The output
+/// is a list of items, meant to be written to a file and included in a module.
+pub fn generate_node_types_bindings(node_types_json: &str) -> String {
+    let node_types: Value = serde_json::from_str(node_types_json).unwrap();
+    let node_types = node_types
+        .as_array()
+        .expect("Expected the node types to be a list");
+    let named_nodes: Vec<&Value> = node_types.iter().filter(|n| n["named"] == true).collect();
+
+    let mut generator = Generator {
+        nodes: named_nodes
+            .iter()
+            .map(|n| (n["type"].as_str().unwrap(), *n))
+            .collect(),
+        type_names: HashMap::new(),
+        types: vec![],
+    };
+    for node in &named_nodes {
+        let kind = node["type"].as_str().unwrap();
+        let name = generator.fresh_type_name(camel_case(kind));
+        generator.type_names.insert(kind, name);
+    }
+    for node in &named_nodes {
+        generator.define(node);
+    }
+
+    let references = direct_references(&generator.types);
+    let mut out = String::new();
+    for ty in &generator.types {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        render_type(ty, &references, &mut out);
+    }
+    out
+}
+
+fn render_type(ty: &TypeDef, references: &HashMap<&str, Vec<&str>>, out: &mut String) {
+    let name = &ty.name;
+    // Only enums pass the previous position on, to the types of their variants.
+    let passes_last_idx = match &ty.body {
+        TypeBody::Enum(variants) => variants
+            .iter()
+            .any(|v| matches!(v.element, Element::Type(_))),
+        _ => false,
+    };
+    let last_idx = if passes_last_idx {
+        "last_idx"
+    } else {
+        "_last_idx"
+    };
+    let extract_header = format!(
+        "impl rust_sitter::Extract<'_, {name}> for {name} {{\n\
+         {INDENT}type LeafFn = ();\n\
+         {INDENT}fn extract(\n\
+         {INDENT}{INDENT}node: Option<rust_sitter::tree_sitter::Node>,\n\
+         {INDENT}{INDENT}source: &[u8],\n\
+         {INDENT}{INDENT}last_idx: usize,\n\
+         {INDENT}{INDENT}leaf_fn: Option<&Self::LeafFn>,\n\
+         {INDENT}) -> {name} {{\n\
+         {INDENT}{INDENT}Self::try_extract(node, source, last_idx, leaf_fn)\n\
+         {INDENT}{INDENT}{INDENT}.unwrap_or_else(|e| panic!(\"Could not extract `{name}`: {{:?}}\", e.reason))\n\
+         {INDENT}}}\n\
+         {INDENT}fn try_extract(\n\
+         {INDENT}{INDENT}node: Option<rust_sitter::tree_sitter::Node>,\n\
+         {INDENT}{INDENT}source: &[u8],\n\
+         {INDENT}{INDENT}{last_idx}: usize,\n\
+         {INDENT}{INDENT}_leaf_fn: Option<&Self::LeafFn>,\n\
+         {INDENT}) -> Result<{name}, rust_sitter::errors::ParseError> {{\n\
+         {INDENT}{INDENT}let node = node.unwrap();\n"
+    );
+    let body_indent = INDENT.repeat(2);
+
+    match &ty.body {
+        TypeBody::Leaf => {
+            out.push_str(&format!(
+                "/// The `{}` node.\n#[derive(Debug)]\npub struct {name} {{\n{INDENT}pub text: String,\n}}\n\n",
+                ty.kind
+            ));
+            out.push_str(&extract_header);
+            out.push_str(&format!(
+                "{body_indent}Ok({name} {{\n\
+                 {body_indent}{INDENT}text: node.utf8_text(source).unwrap().to_string(),\n\
+                 {body_indent}}})\n"
+            ));
+        }
+        TypeBody::Struct(fields) => {
+            out.push_str(&format!(
+                "/// The `{}` node.\n#[derive(Debug)]\npub struct {name} {{\n",
+                ty.kind
+            ));
+            for field in fields {
+                let element = element_type(&field.element, name, references, !field.multiple);
+                let field_type = if field.multiple {
+                    format!("Vec<{element}>")
+                } else if !field.required {
+                    format!("Option<{element}>")
+                } else {
+                    element
+                };
+                out.push_str(&format!("{INDENT}pub {}: {field_type},\n", field.name));
+            }
+            out.push_str("}\n\n");
+
+            out.push_str(&extract_header);
+            out.push_str(&format!("{body_indent}Ok({name} {{\n"));
+            for field in fields {
+                let element = element_type(&field.element, name, references, !field.multiple);
+                let call = match (&field.tree_sitter_name, field.multiple, field.required) {
+                    (Some(f), true, _) => {
+                        format!(
+                            "rust_sitter::external::fields::<{element}, _>(node, {f:?}, source)"
+                        )
+                    }
+                    (Some(f), false, true) => {
+                        format!("rust_sitter::external::field::<{element}, _>(node, {f:?}, source)")
+                    }
+                    (Some(f), false, false) => format!(
+                        "rust_sitter::external::field::<Option<{element}>, _>(node, {f:?}, source)"
+                    ),
+                    (None, true, _) => {
+                        format!("rust_sitter::external::children::<{element}, _>(node, source)")
+                    }
+                    (None, false, true) => {
+                        format!("rust_sitter::external::child::<{element}, _>(node, source)")
+                    }
+                    (None, false, false) => format!(
+                        "rust_sitter::external::child::<Option<{element}>, _>(node, source)"
+                    ),
+                };
+                out.push_str(&format!("{body_indent}{INDENT}{}: {call}?,\n", field.name));
+            }
+            out.push_str(&format!("{body_indent}}})\n"));
+        }
+        TypeBody::Enum(variants) => {
+            let doc = if ty.kind.contains('.') {
+                format!("/// The node types of `{}`.\n", ty.kind)
+            } else {
+                format!("/// The `{}` node.\n", ty.kind)
+            };
+            // Variants are named after node kinds, which often share a prefix.
+            out.push_str(&format!(
+                "{doc}#[derive(Debug)]\n#[allow(clippy::enum_variant_names)]\npub enum {name} {{\n"
+            ));
+            for variant in variants {
+                match &variant.element {
+                    Element::Unit => out.push_str(&format!("{INDENT}{},\n", variant.name)),
+                    element => out.push_str(&format!(
+                        "{INDENT}{}({}),\n",
+                        variant.name,
+                        element_type(element, name, references, true)
+                    )),
+                }
+            }
+            out.push_str("}\n\n");
+
+            out.push_str(&extract_header);
+            out.push_str(&format!(
+                "{body_indent}match (node.kind(), node.is_named()) {{\n"
+            ));
+            for variant in variants {
+                let patterns: Vec<String> = variant
+                    .kinds
+                    .iter()
+                    .map(|(kind, named)| format!("({kind:?}, {named})"))
+                    .collect();
+                let value = match &variant.element {
+                    Element::Unit => format!("Ok({name}::{})", variant.name),
+                    element => format!(
+                        "Ok({name}::{}(\n\
+                         {body_indent}{INDENT}{INDENT}<{} as rust_sitter::Extract<_>>::try_extract(Some(node), source, last_idx, None)?,\n\
+                         {body_indent}{INDENT}))",
+                        variant.name,
+                        element_type(element, name, references, true)
+                    ),
+                };
+                out.push_str(&format!(
+                    "{body_indent}{INDENT}{} => {value},\n",
+                    patterns.join(" | ")
+                ));
+            }
+            out.push_str(&format!(
+                "{body_indent}{INDENT}(kind, _) => Err(rust_sitter::errors::ParseError {{\n\
+                 {body_indent}{INDENT}{INDENT}reason: rust_sitter::errors::ParseErrorReason::UnexpectedToken(kind.to_string()),\n\
+                 {body_indent}{INDENT}{INDENT}start: node.start_byte(),\n\
+                 {body_indent}{INDENT}{INDENT}end: node.end_byte(),\n\
+                 {body_indent}{INDENT}}}),\n\
+                 {body_indent}}}\n"
+            ));
+        }
+    }
+
+    out.push_str(&format!("{INDENT}}}\n}}\n"));
+}
@@ -0,0 +1,311 @@
+use std::collections::{HashMap, HashSet};
+
+use regex_syntax::hir::{Class, Hir, HirKind, Literal, RepetitionKind, RepetitionRange};
+use serde_json::Value;
+
+/// The most repetitions generated for `REPEAT` rules and unbounded regex repetitions.
+const MAX_REPETITIONS: usize = 3;
+
+/// How many times a pattern is resampled when it produces one of the grammar's keywords.
+const MAX_PATTERN_ATTEMPTS: usize = 16;
+
+/// A small, seedable SplitMix64 generator, so that samples are reproducible from a seed
+/// without depending on a particular version of a random number crate.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A random number in `low..=high`.
+    fn range(&mut self, low: usize, high: usize) -> usize {
+        low + (self.next_u64() % (high - low + 1) as u64) as usize
+    }
+}
+
+/// Generates random source strings that are accepted by a grammar, by walking the rules in its
+/// JSON definition (as produced by [`generate_grammars`](crate::generate_grammars)).
+///
+/// `STRING` rules produce their text and `PATTERN` rules produce a random match of their regex.
+/// Tokens are separated by a space whenever the grammar has extras, since they are usually
+/// whitespace. Once the sample is `max_depth` rules deep, the generator only takes the choices
+/// that lead to the shallowest trees, so that every sample is finite.
+pub struct SampleGenerator<'a> {
+    rules: &'a serde_json::Map<String, Value>,
+    keywords: HashSet<&'a str>,
+    min_depths: HashMap<&'a str, usize>,
+    separator: &'static str,
+    rng: Rng,
+}
+
+impl<'a> SampleGenerator<'a> {
+    pub fn new(grammar: &'a Value, seed: u64) -> SampleGenerator<'a> {
+        let rules = grammar["rules"]
+            .as_object()
+            .expect("Expected the grammar to have rules");
+
+        let mut keywords = HashSet::new();
+        rules
+            .values()
+            .for_each(|rule| collect_keywords(rule, &mut keywords));
+
+        let has_extras = grammar["extras"]
+            .as_array()
+            .map(|extras| !extras.is_empty())
+            .unwrap_or(false);
+
+        SampleGenerator {
+            rules,
+            keywords,
+            min_depths: min_depths(rules),
+            separator: if has_extras { " " } else { "" },
+            rng: Rng(seed),
+        }
+    }
+
+    /// Generates a source string for the root rule of the grammar.
+    pub fn sample(&mut self, max_depth: usize) -> String {
+        let mut tokens = vec![];
+        self.gen_symbol("source_file", 0, max_depth, &mut tokens);
+        tokens.retain(|t| !t.is_empty());
+        tokens.join(self.separator)
+    }
+
+    fn rule_depth(&self, rule: &Value) -> usize {
+        rule_min_depth(rule, &self.min_depths)
+    }
+
+    fn gen_symbol(&mut self, name: &str, depth: usize, max_depth: usize, out: &mut Vec<String>) {
+        let rule = self
+            .rules
+            .get(name)
+            .unwrap_or_else(|| panic!("Could not find the rule `{name}`"));
+        self.gen_rule(rule, depth + 1, max_depth, out);
+    }
+
+    fn gen_rule(&mut self, rule: &Value, depth: usize, max_depth: usize, out: &mut Vec<String>) {
+        match rule["type"].as_str().unwrap() {
+            "BLANK" => {}
+            "STRING" => out.push(rule["value"].as_str().unwrap().to_string()),
+            "PATTERN" => out.push(self.gen_pattern(rule["value"].as_str().unwrap())),
+            "SYMBOL" => self.gen_symbol(rule["name"].as_str().unwrap(), depth, max_depth, out),
+            "SEQ" => rule["members"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .for_each(|member| self.gen_rule(member, depth, max_depth, out)),
+            "CHOICE" => {
+                let members = rule["members"].as_array().unwrap();
+                let candidates: Vec<&Value> = if depth >= max_depth {
+                    let shallowest = members.iter().map(|m| self.rule_depth(m)).min().unwrap();
+                    members
+                        .iter()
+                        .filter(|m| self.rule_depth(m) == shallowest)
+                        .collect()
+                } else {
+                    members.iter().collect()
+                };
+
+                let member = candidates[self.rng.range(0, candidates.len() - 1)];
+                self.gen_rule(member, depth, max_depth, out);
+            }
+            kind @ ("REPEAT" | "REPEAT1") => {
+                let min = if kind == "REPEAT" { 0 } else { 1 };
+                let count = if depth >= max_depth {
+                    min
+                } else {
+                    self.rng.range(min, MAX_REPETITIONS)
+                };
+
+                for _ in 0..count {
+                    self.gen_rule(&rule["content"], depth, max_depth, out);
+                }
+            }
+            "FIELD" | "ALIAS" | "TOKEN" | "IMMEDIATE_TOKEN" | "PREC" | "PREC_LEFT"
+            | "PREC_RIGHT" | "PREC_DYNAMIC" => {
+                self.gen_rule(&rule["content"], depth, max_depth, out)
+            }
+            other => panic!("Unsupported rule type `{other}`"),
+        }
+    }
+
+    /// Generates a match of a regex, avoiding the grammar's keywords since the lexer would
+    /// treat them as the keyword instead.
+    fn gen_pattern(&mut self, pattern: &str) -> String {
+        let hir = regex_syntax::Parser::new()
+            .parse(pattern)
+            .unwrap_or_else(|e| panic!("Could not parse the pattern `{pattern}`: {e}"));
+
+        let mut sample = String::new();
+        for _ in 0..MAX_PATTERN_ATTEMPTS {
+            sample.clear();
+            self.gen_hir(&hir, &mut sample);
+            if !self.keywords.contains(sample.as_str()) {
+                break;
+            }
+        }
+
+        sample
+    }
+
+    fn gen_hir(&mut self, hir: &Hir, out: &mut String) {
+        match hir.kind() {
+            HirKind::Empty | HirKind::Anchor(_) | HirKind::WordBoundary(_) => {}
+            HirKind::Literal(Literal::Unicode(c)) => out.push(*c),
+            HirKind::Literal(Literal::Byte(b)) => out.push(*b as char),
+            HirKind::Class(class) => {
+                if let Some(c) = self.gen_class(class) {
+                    out.push(c);
+                }
+            }
+            HirKind::Repetition(repetition) => {
+                let (min, max) = match &repetition.kind {
+                    RepetitionKind::ZeroOrOne => (0, 1),
+                    RepetitionKind::ZeroOrMore => (0, MAX_REPETITIONS),
+                    RepetitionKind::OneOrMore => (1, MAX_REPETITIONS),
+                    RepetitionKind::Range(RepetitionRange::Exactly(n)) => {
+                        (*n as usize, *n as usize)
+                    }
+                    RepetitionKind::Range(RepetitionRange::AtLeast(n)) => {
+                        (*n as usize, *n as usize + MAX_REPETITIONS)
+                    }
+                    RepetitionKind::Range(RepetitionRange::Bounded(m, n)) => {
+                        (*m as usize, *n as usize)
+                    }
+                };
+
+                for _ in 0..self.rng.range(min, max) {
+                    self.gen_hir(&repetition.hir, out);
+                }
+            }
+            HirKind::Group(group) => self.gen_hir(&group.hir, out),
+            HirKind::Concat(hirs) => hirs.iter().for_each(|h| self.gen_hir(h, out)),
+            HirKind::Alternation(hirs) => {
+                let hir = &hirs[self.rng.range(0, hirs.len() - 1)];
+                self.gen_hir(hir, out);
+            }
+        }
+    }
+
+    /// Picks a character from a class, preferring printable ASCII characters (including space)
+    /// so that samples stay readable and do not run into line-based extras.
+    fn gen_class(&mut self, class: &Class) -> Option<char> {
+        let ranges: Vec<(char, char)> = match class {
+            Class::Unicode(class) => class.iter().map(|r| (r.start(), r.end())).collect(),
+            Class::Bytes(class) => class
+                .iter()
+                .map(|r| (r.start() as char, r.end() as char))
+                .collect(),
+        };
+
+        let printable: Vec<(char, char)> = ranges
+            .iter()
+            .filter_map(|(start, end)| {
+                let start = (*start).max(' ');
+                let end = (*end).min('~');
+                (start <= end).then_some((start, end))
+            })
+            .collect();
+        let ranges = if printable.is_empty() {
+            ranges
+        } else {
+            printable
+        };
+
+        let total: usize = ranges
+            .iter()
+            .map(|(start, end)| *end as usize - *start as usize + 1)
+            .sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut index = self.rng.range(0, total - 1);
+        for (start, end) in ranges {
+            let size = end as usize - start as usize + 1;
+            if index < size {
+                // Skip over surrogates, which are not valid characters.
+                return char::from_u32(start as u32 + index as u32).or(Some(start));
+            }
+            index -= size;
+        }
+
+        None
+    }
+}
+
+fn collect_keywords<'a>(rule: &'a Value, out: &mut HashSet<&'a str>) {
+    match rule["type"].as_str() {
+        Some("STRING") => {
+            out.insert(rule["value"].as_str().unwrap());
+        }
+        _ => {
+            if let Some(members) = rule["members"].as_array() {
+                members.iter().for_each(|m| collect_keywords(m, out));
+            }
+            if rule.get("content").is_some() {
+                collect_keywords(&rule["content"], out);
+            }
+        }
+    }
+}
+
+/// The fewest rules that must be nested to produce the given rule.
+fn rule_min_depth(rule: &Value, depths: &HashMap<&str, usize>) -> usize {
+    match rule["type"].as_str().unwrap() {
+        "SYMBOL" => depths
+            .get(rule["name"].as_str().unwrap())
+            .copied()
+            .unwrap_or(usize::MAX)
+            .saturating_add(1),
+        "SEQ" => rule["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| rule_min_depth(m, depths))
+            .max()
+            .unwrap_or(0),
+        "CHOICE" => rule["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| rule_min_depth(m, depths))
+            .min()
+            .unwrap_or(0),
+        "REPEAT" => 0,
+        _ if rule.get("content").is_some() => rule_min_depth(&rule["content"], depths),
+        _ => 0,
+    }
+}
+
+/// Computes the minimum depth of every rule, iterating until a fixed point is reached.
+fn min_depths(rules: &serde_json::Map<String, Value>) -> HashMap<&str, usize> {
+    let mut depths: HashMap<&str, usize> = HashMap::new();
+    loop {
+        let mut changed = false;
+        for (name, rule) in rules {
+            let depth = rule_min_depth(rule, &depths);
+            if depth < *depths.get(name.as_str()).unwrap_or(&usize::MAX) {
+                depths.insert(name, depth);
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return depths;
+        }
+    }
+}
+
+/// Generates `count` random source strings for a grammar given as JSON, with the rules nested
+/// at most around `max_depth` deep. The same `seed` always produces the same samples.
+pub fn generate_samples(grammar: &str, count: usize, max_depth: usize, seed: u64) -> Vec<String> {
+    let grammar: Value = serde_json::from_str(grammar).unwrap();
+    let mut generator = SampleGenerator::new(&grammar, seed);
+    (0..count).map(|_| generator.sample(max_depth)).collect()
+}